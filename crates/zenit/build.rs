@@ -29,6 +29,8 @@ fn main() -> io::Result<()> {
         command: CliCommand::Build(BuildCommand {
             output: PathBuf::from(env::var("OUT_DIR").unwrap()).join("zenit_builtin.lvl"),
             specification: PathBuf::from("zenit_builtin.toml"),
+            cache_dir: None,
+            no_cache: false,
         }),
     })
     .expect("builtin asset build failed");
@@ -0,0 +1,196 @@
+use crate::{
+    entities::{components::TransformComponent, Entity, Universe},
+    graphics::{AmbientLightComponent, LightComponent, LightKind},
+    scene::EngineBorrow,
+};
+use glam::*;
+use log::*;
+use std::io::{Read, Seek};
+use thiserror::Error;
+use zenit_lvl::{
+    config::LevelConfig,
+    game::{LevelLight, LevelLightFlags, LevelLightKind, LevelLighting},
+    node::{NodeHeader, NodeRead},
+};
+use zenit_utils::math::Radians;
+
+#[derive(Debug, Error)]
+pub enum LightLoadError {
+    #[error("a node parsing error occurred: {0:#?}")]
+    ParseError(anyhow::Error),
+}
+
+/// Loads a lighting node, and spawns its lights as entities inside the universe.
+///
+/// Any errors are logged, but not returned back. For better control, you may want to use
+/// [`load_lighting`] and [`spawn_light_entities`] instead.
+pub fn load_lighting_as_asset(
+    (mut r, node): (impl Read + Seek, NodeHeader),
+    engine: &mut EngineBorrow,
+) {
+    match load_lighting((&mut r, node)) {
+        Ok(lighting) => {
+            let entities = spawn_light_entities(engine.universe, &lighting);
+            trace!(
+                "Loaded lighting `{}` ({} entities)...",
+                engine.assets.names.display(lighting.name_hash),
+                entities.len()
+            );
+        }
+        Err(e) => error!("An error occurred while loading lighting: {e:#?}"),
+    }
+}
+
+/// Parses a lighting node.
+pub fn load_lighting(
+    (mut r, node): (impl Read + Seek, NodeHeader),
+) -> Result<LevelLighting, LightLoadError> {
+    let config = LevelConfig::read_node_at(&mut r, node).map_err(LightLoadError::ParseError)?;
+    Ok(LevelLighting::from(&config))
+}
+
+/// Creates an entity for every light, and an additional one with an [`AmbientLightComponent`]
+/// if global lighting is defined.
+pub fn spawn_light_entities(universe: &mut Universe, lighting: &LevelLighting) -> Vec<Entity> {
+    let mut entities = Vec::with_capacity(lighting.lights.len() + 1);
+
+    for light in &lighting.lights {
+        entities.push(
+            universe
+                .build_entity()
+                .with_component(TransformComponent(Affine3A::from_rotation_translation(
+                    light.rotation,
+                    light.position,
+                )))
+                .with_component(convert_light(light))
+                .finish(),
+        );
+    }
+
+    if let Some(global) = &lighting.global {
+        // Ambient colors are stored in the [0; 255] range
+        entities.push(universe.create_entity_with(AmbientLightComponent {
+            top: global.ambient_top.unwrap_or(Vec3::ZERO) / 255.0,
+            bottom: global.ambient_bottom.unwrap_or(Vec3::ZERO) / 255.0,
+        }));
+    }
+
+    entities
+}
+
+/// Converts a level light into its ECS counterpart.
+pub fn convert_light(light: &LevelLight) -> LightComponent {
+    LightComponent {
+        kind: match light.kind {
+            LevelLightKind::Directional => LightKind::Directional,
+            LevelLightKind::Omni { range } => LightKind::Omni { range },
+            LevelLightKind::Spot {
+                range,
+                inner_cone,
+                outer_cone,
+            } => LightKind::Spot {
+                range,
+                inner_cone: Radians(inner_cone),
+                outer_cone: Radians(outer_cone),
+            },
+        },
+        color: light.color,
+        cast_shadow: light.flags.contains(LevelLightFlags::CAST_SHADOW),
+        is_static: light.flags.contains(LevelLightFlags::STATIC),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenit_lvl::game::GlobalLighting;
+
+    fn light(name: &str, kind: LevelLightKind, flags: LevelLightFlags) -> LevelLight {
+        LevelLight {
+            name: name.to_string(),
+            kind,
+            position: vec3(1.0, 2.0, 3.0),
+            rotation: Quat::from_rotation_y(1.0),
+            color: vec3(1.0, 0.5, 0.25),
+            flags,
+        }
+    }
+
+    #[test]
+    fn light_entities() {
+        let lighting = LevelLighting {
+            name_hash: 0,
+            lights: vec![
+                light(
+                    "sun",
+                    LevelLightKind::Directional,
+                    LevelLightFlags::CAST_SHADOW | LevelLightFlags::STATIC,
+                ),
+                light(
+                    "lamp",
+                    LevelLightKind::Omni { range: 15.0 },
+                    LevelLightFlags::CAST_SPECULAR,
+                ),
+                light(
+                    "spot",
+                    LevelLightKind::Spot {
+                        range: 20.0,
+                        inner_cone: 0.5,
+                        outer_cone: 0.75,
+                    },
+                    LevelLightFlags::STATIC,
+                ),
+            ],
+            global: Some(GlobalLighting {
+                lights: [Some(String::from("sun")), None],
+                ambient_top: Some(vec3(255.0, 0.0, 51.0)),
+                ambient_bottom: None,
+            }),
+        };
+
+        let mut universe = Universe::new();
+        let entities = spawn_light_entities(&mut universe, &lighting);
+        assert_eq!(entities.len(), 4);
+
+        let components: Vec<_> = entities[..3]
+            .iter()
+            .map(|&entity| {
+                let light = universe.get_component::<LightComponent>(entity).unwrap();
+                (light.kind, light.cast_shadow, light.is_static)
+            })
+            .collect();
+        assert_eq!(
+            components,
+            [
+                (LightKind::Directional, true, true),
+                (LightKind::Omni { range: 15.0 }, false, false),
+                (
+                    LightKind::Spot {
+                        range: 20.0,
+                        inner_cone: Radians(0.5),
+                        outer_cone: Radians(0.75),
+                    },
+                    false,
+                    true
+                ),
+            ]
+        );
+
+        let sun = universe
+            .get_component::<LightComponent>(entities[0])
+            .unwrap();
+        assert_eq!(sun.color, vec3(1.0, 0.5, 0.25));
+        let transform = universe
+            .get_component::<TransformComponent>(entities[0])
+            .unwrap();
+        assert_eq!(transform.0.translation, vec3a(1.0, 2.0, 3.0));
+
+        // Ambient colors are normalized
+        let ambient = universe
+            .get_component::<AmbientLightComponent>(entities[3])
+            .unwrap();
+        assert_eq!(ambient.top, vec3(1.0, 0.0, 0.2));
+        assert_eq!(ambient.bottom, Vec3::ZERO);
+        assert!(!universe.has_component::<LightComponent>(entities[3]));
+    }
+}
@@ -1,11 +1,18 @@
 use super::ZENIT_BUILTIN_LVL;
-use crate::{assets::texture_loader::load_texture_as_asset, scene::EngineBorrow};
+use crate::{
+    assets::{
+        light_loader::load_lighting_as_asset, sky_loader::load_sky_as_asset,
+        texture_loader::load_texture_as_asset,
+    },
+    scene::EngineBorrow,
+};
 use log::*;
-use std::io::{Cursor, Read, Seek};
-use zenit_lvl::node::{read_node_children, read_node_header};
+use zenit_lvl::node::NodeSlice;
 use zenit_utils::{ok, AnyResult};
 
+pub mod light_loader;
 pub mod shader_loader;
+pub mod sky_loader;
 pub mod texture_loader;
 
 pub struct AssetLoader<'a> {
@@ -17,16 +24,16 @@ impl<'a> AssetLoader<'a> {
         Self { engine }
     }
 
-    pub fn load_level_file(&mut self, mut r: impl Read + Seek, label: &str) -> AnyResult {
+    /// Loads an in-memory level file. Texture data is borrowed straight from the buffer.
+    pub fn load_level_slice(&mut self, data: &[u8], label: &str) -> AnyResult {
         trace!("Loading level file `{label}`...");
 
-        let header = read_node_header(&mut r)?;
-        let children = read_node_children(&mut r, header)?;
-
-        for child in children {
-            // For clarity, keep the loader invocations as one-liners.
-            match child.name.as_bytes() {
-                b"tex_" => load_texture_as_asset((&mut r, child), self.engine),
+        for child in NodeSlice::root(data)?.children()? {
+            let node = (child.reader(), child.header());
+            match child.name().as_bytes() {
+                b"tex_" => load_texture_as_asset(child, self.engine),
+                b"sky_" => load_sky_as_asset(node, self.engine),
+                b"lght" => load_lighting_as_asset(node, self.engine),
                 _ => {}
             }
         }
@@ -34,7 +41,7 @@ impl<'a> AssetLoader<'a> {
     }
 
     pub fn load_builtins(&mut self) -> AnyResult {
-        self.load_level_file(Cursor::new(ZENIT_BUILTIN_LVL), "zenit_builtin")?;
+        self.load_level_slice(ZENIT_BUILTIN_LVL, "zenit_builtin")?;
         self.engine.assets.error_texture = self
             .engine
             .assets
@@ -0,0 +1,82 @@
+use crate::{
+    assets::AssetManager,
+    graphics::{FogSettings, SkyboxBackground, SkyboxDescriptor, SkyboxHandle},
+    scene::EngineBorrow,
+};
+use glam::*;
+use log::*;
+use std::io::{Read, Seek};
+use thiserror::Error;
+use zenit_lvl::{
+    config::LevelConfig,
+    game::SkyDefinition,
+    node::{NodeHeader, NodeRead},
+};
+
+/// Cubemap used whenever a sky doesn't define a texture that Zenit can display.
+pub const FALLBACK_SKY_CUBEMAP: &str = "zenit_prototype_skybox";
+
+#[derive(Debug, Error)]
+pub enum SkyLoadError {
+    #[error("a node parsing error occurred: {0:#?}")]
+    ParseError(anyhow::Error),
+}
+
+/// Loads a sky definition, and registers its skybox inside the asset manager.
+///
+/// Any errors are logged, but not returned back. For better control, you may want to use
+/// [`load_sky`] instead.
+pub fn load_sky_as_asset((mut r, node): (impl Read + Seek, NodeHeader), engine: &mut EngineBorrow) {
+    match load_sky((&mut r, node), engine) {
+        Ok((name_hash, skybox)) => {
+            trace!("Loaded sky `{}`...", engine.assets.names.display(name_hash));
+            engine.assets.skyboxes.insert(name_hash, skybox);
+        }
+        Err(e) => error!("An error occurred while loading a sky: {e:#?}"),
+    }
+}
+
+/// Loads a sky definition and creates its skybox, without registering it inside the asset manager.
+pub fn load_sky(
+    (mut r, node): (impl Read + Seek, NodeHeader),
+    engine: &mut EngineBorrow,
+) -> Result<(u32, SkyboxHandle), SkyLoadError> {
+    let config = LevelConfig::read_node_at(&mut r, node).map_err(SkyLoadError::ParseError)?;
+    let sky = SkyDefinition::from(&config);
+
+    let descriptor = create_skybox_descriptor(&sky, engine.assets);
+    Ok((sky.name_hash, engine.renderer.create_skybox(&descriptor)))
+}
+
+/// Converts a [`SkyDefinition`] into a renderer [`SkyboxDescriptor`].
+///
+/// The dome texture is used if it was loaded as a cubemap. Otherwise, the background falls back to
+/// [`FALLBACK_SKY_CUBEMAP`], or if even that is missing, to a solid fog color.
+///
+/// Fog settings are only filled in if the sky defines both the fog color and range.
+pub fn create_skybox_descriptor(sky: &SkyDefinition, assets: &AssetManager) -> SkyboxDescriptor {
+    // Sky colors are stored in the [0; 255] range
+    let fog_color = sky.fog_color.map(|color| color / 255.0);
+
+    let dome_cubemap = sky
+        .dome
+        .as_ref()
+        .and_then(|dome| dome.texture.as_ref())
+        .and_then(|texture| assets.cubemaps.get(texture));
+
+    SkyboxDescriptor {
+        name: format!("sky {}", assets.names.display(sky.name_hash)),
+        background: match dome_cubemap.or_else(|| assets.cubemaps.get(FALLBACK_SKY_CUBEMAP)) {
+            Some(cubemap) => SkyboxBackground::Textured(cubemap.clone()),
+            None => SkyboxBackground::Solid(fog_color.unwrap_or(vec4(0.0, 0.0, 0.0, 1.0))),
+        },
+        fog: match (fog_color, sky.fog_range) {
+            (Some(color), Some(range)) => Some(FogSettings {
+                color,
+                near: range.x,
+                far: range.y,
+            }),
+            _ => None,
+        },
+    }
+}
@@ -8,12 +8,12 @@ use crate::{
 use glam::uvec2;
 use itertools::Itertools;
 use log::*;
-use std::io::{Read, Seek};
+use std::borrow::Cow;
 use thiserror::Error;
 use wgpu::TextureFormat;
 use zenit_lvl::{
     game::{D3DFormat, LevelTexture, LevelTextureFormat, LevelTextureFormatInfo, LevelTextureKind},
-    node::{NodeHeader, NodeRead},
+    node::NodeSlice,
 };
 
 pub enum LoadedTexture {
@@ -39,11 +39,8 @@ pub enum TextureLoadError {
 ///
 /// Any errors are logged, but not returned back. For better control, you may want to use
 /// [`load_texture`] instead.
-pub fn load_texture_as_asset(
-    (mut r, node): (impl Read + Seek, NodeHeader),
-    engine: &mut EngineBorrow,
-) {
-    let (name, texture) = match load_texture((&mut r, node), engine) {
+pub fn load_texture_as_asset(node: NodeSlice, engine: &mut EngineBorrow) {
+    let (name, texture) = match load_texture(node, engine) {
         Ok(v) => v,
         Err(e) => {
             error!("An error occurred while loading a texture: {e:#?}");
@@ -64,14 +61,16 @@ pub fn load_texture_as_asset(
 }
 
 /// Loads a texture without registering it inside the asset manager.
+///
+/// Texture data is borrowed from the level file, and is only copied if it needs to be converted.
 pub fn load_texture(
-    (mut r, node): (impl Read + Seek, NodeHeader),
+    node: NodeSlice,
     engine: &mut EngineBorrow,
 ) -> Result<(String, LoadedTexture), TextureLoadError> {
     use LevelTextureKind::*;
     use TextureLoadError::*;
 
-    let level_texture = LevelTexture::read_node_at(&mut r, node).map_err(|err| ParseError(err))?;
+    let level_texture = node.read::<LevelTexture>().map_err(ParseError)?;
     let texture_name = level_texture.name.into_string().map_err(|_| BadName)?;
 
     // Choose a texture format feasible for loading
@@ -98,7 +97,7 @@ pub fn load_texture(
                 size: uvec2(info.width as u32, info.height as u32),
                 mip_levels: info.mipmaps as u32,
                 format: wgpu_format,
-                unfiltered: unfiltered.len() > 0,
+                unfiltered,
                 d3d_format: Some(info.format),
             });
 
@@ -112,10 +111,7 @@ pub fn load_texture(
                     mip_level: mipmap.info.mip_level,
                     data: &convert_texture_format(
                         &info,
-                        mipmap
-                            .body
-                            .read_cached(&mut r)
-                            .map_err(|err| ReadError(err))?,
+                        Cow::Borrowed(mipmap.body.read_slice(node.file()).map_err(ReadError)?),
                     ),
                 })
             }
@@ -128,7 +124,7 @@ pub fn load_texture(
                 size: uvec2(info.width as u32, info.height as u32),
                 mip_levels: info.mipmaps as u32,
                 format: wgpu_format,
-                unfiltered: unfiltered.len() > 0,
+                unfiltered,
                 d3d_format: Some(info.format),
             });
 
@@ -146,10 +142,7 @@ pub fn load_texture(
                         mip_level: mipmap.info.mip_level,
                         data: &convert_texture_format(
                             &info,
-                            mipmap
-                                .body
-                                .read_cached(&mut r)
-                                .map_err(|err| ReadError(err))?,
+                            Cow::Borrowed(mipmap.body.read_slice(node.file()).map_err(ReadError)?),
                         ),
                     })
                 }
@@ -204,17 +197,17 @@ fn d3dformat_to_wgpu(d3d: D3DFormat) -> TextureFormat {
 }
 
 /// Converts D3D9 optimized texture data into data that can be used by `wgpu`.
-/// If no conversion is necessary, the vector will be given back. The texture
+/// If no conversion is necessary, the data will be given back. The texture
 /// format will match the result of [`d3dformat_to_wgpu`].
-fn convert_texture_format(info: &LevelTextureFormatInfo, data: Vec<u8>) -> Vec<u8> {
+fn convert_texture_format<'a>(info: &LevelTextureFormatInfo, data: Cow<'a, [u8]>) -> Cow<'a, [u8]> {
     use D3DFormat::*;
     match info.format {
         DXT1 => data,
         DXT3 => data,
         A8R8G8B8 => data,
-        R5G6B5 => convert_color_depth(data, 5, 6, 5, 0),
-        A1R5G5B5 => convert_color_depth(data, 5, 5, 5, 1),
-        A4R4G4B4 => convert_color_depth(data, 4, 4, 4, 4),
+        R5G6B5 => convert_color_depth(&data, 5, 6, 5, 0).into(),
+        A1R5G5B5 => convert_color_depth(&data, 5, 5, 5, 1).into(),
+        A4R4G4B4 => convert_color_depth(&data, 4, 4, 4, 4).into(),
         A8 => data,
         L8 => data,
         A8L8 => data,
@@ -222,9 +215,9 @@ fn convert_texture_format(info: &LevelTextureFormatInfo, data: Vec<u8>) -> Vec<u
         V8U8 => data,
         R8G8B8 => {
             // Convert to Rgba8Unorm
-            data.into_iter()
+            data.iter()
                 .tuples()
-                .flat_map(|(b, g, r)| [r, g, b, 255])
+                .flat_map(|(&b, &g, &r)| [r, g, b, 255])
                 .collect()
         }
     }
@@ -234,7 +227,7 @@ fn convert_texture_format(info: &LevelTextureFormatInfo, data: Vec<u8>) -> Vec<u
 /// a roughly equivalent 32-bit RGBA counterpart (what a mouthful).
 #[inline(always)]
 fn convert_color_depth(
-    data16: Vec<u8>,
+    data16: &[u8],
     r_bits: u16,
     g_bits: u16,
     b_bits: u16,
@@ -243,9 +236,9 @@ fn convert_color_depth(
     debug_assert_eq!(r_bits + g_bits + b_bits + a_bits, 16);
 
     data16
-        .into_iter()
+        .iter()
         .tuples()
-        .flat_map(|(low, high)| {
+        .flat_map(|(&low, &high)| {
             let value = u16::from_le_bytes([low, high]);
 
             let a_offset = r_bits + g_bits + b_bits;
@@ -274,22 +267,55 @@ fn color_to_8bits(pixel_value: u16, shift: u16, depth: u16) -> u8 {
     // Extract the component value using bitops magic
     let value = (pixel_value >> shift) & ((1u16 << depth) - 1);
 
-    // Perform a basic bitshift to extend the value to an 8-bit depth
-    let mut result = value << (8 - depth);
-
-    // We could end here, but it'd cause some inaccuracies, when for example converting a 5-bit
-    // component - the highest value of 31 wouldn't translate to 255, but to 248.
-    //
-    // This happens because the newly created bottom 3 bits are initialized with zero. We need to
-    // scale them proportionally to the source value. For example, with a 5-bit component,
-    // color values [0; 31] get scaled to [0; 7] (as we are filling 3 bits).
+    // A basic bitshift to extend the value to an 8-bit depth would cause some inaccuracies,
+    // when for example converting a 5-bit component - the highest value of 31 wouldn't translate
+    // to 255, but to 248. Scale the value proportionally instead, rounding to the nearest
+    // 8-bit value.
     //
     // This way, a source value of 0 will still be translated to 0, and a value of 31 will be
-    // translated to 255.
-    //
-    // A more generalized version of this would be scaling [0; 2**depth] to [0; 2**(8-depth)].
-    // The expression below is basically a simplified version of this
-    result |= value >> (depth + depth - 8);
+    // translated to 255. Unlike replicating the top bits into the bottom ones, this also works
+    // for depths below 4 bits (such as the 1-bit alpha of A1R5G5B5).
+    let max = (1u16 << depth) - 1;
+    ((value * 255 + max / 2) / max) as u8
+}
 
-    result as u8
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenit_mdk::exporter::texture::converter::create_converter;
+
+    /// Textures exported by the MDK should be decoded back into the same colors.
+    #[test]
+    fn exported_textures_round_trip() {
+        use D3DFormat::*;
+
+        // Every possible 16-bit value
+        let data16: Vec<u8> = (0..=u16::MAX).flat_map(u16::to_le_bytes).collect();
+        let data24: Vec<u8> = (0..256 * 256 * 3u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect();
+
+        for (format, data) in [
+            (R5G6B5, &data16),
+            (A1R5G5B5, &data16),
+            (A4R4G4B4, &data16),
+            (R8G8B8, &data24),
+        ] {
+            let info = LevelTextureFormatInfo {
+                format,
+                width: 256,
+                height: 256,
+                unk_0x08: 1,
+                mipmaps: 1,
+                kind: LevelTextureKind::D2,
+            };
+            let rgba = convert_texture_format(&info, Cow::Borrowed(data));
+
+            let mut exported = vec![];
+            create_converter(format, false)
+                .write_texture(&mut exported, &rgba, 256, 256)
+                .unwrap();
+            assert_eq!(&exported, data, "{format:?} doesn't round trip");
+        }
+    }
 }
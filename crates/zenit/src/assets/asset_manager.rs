@@ -1,9 +1,10 @@
 use super::game_root::GameRoot;
-use crate::graphics::{CubemapHandle, Renderer, TextureDescriptor, TextureHandle};
+use crate::graphics::{CubemapHandle, Renderer, SkyboxHandle, TextureDescriptor, TextureHandle};
 use ahash::AHashMap;
 use glam::uvec2;
 use std::path::PathBuf;
 use wgpu::TextureFormat;
+use zenit_utils::HashDictionary;
 
 /// The asset manager is responsible for:
 ///  * loading assets from files
@@ -13,6 +14,10 @@ pub struct AssetManager {
 
     pub textures: AHashMap<String, TextureHandle>,
     pub cubemaps: AHashMap<String, CubemapHandle>,
+    /// Skyboxes created from `sky_` nodes, keyed by the hashed name of the sky definition.
+    pub skyboxes: AHashMap<u32, SkyboxHandle>,
+    /// Known names of hashes, used for displaying them in logs and the DevUi.
+    pub names: HashDictionary,
 
     /// Fallback texture for failed lookups
     pub error_texture: TextureHandle,
@@ -24,6 +29,8 @@ impl AssetManager {
             game_root,
             textures: AHashMap::default(),
             cubemaps: AHashMap::default(),
+            skyboxes: AHashMap::default(),
+            names: HashDictionary::with_builtin_names(),
 
             // Temporary texture, overwritten later
             error_texture: renderer.create_texture(&TextureDescriptor {
@@ -0,0 +1,132 @@
+use byteorder::{WriteBytesExt, LE};
+use log::*;
+use std::{
+    io::{Seek, SeekFrom, Write},
+    time::Duration,
+};
+use zenit_utils::{
+    ok,
+    wav::{write_wav_header, MAX_WAV_SAMPLES},
+    AnyResult,
+};
+
+/// Output of the [`super::Mixer`].
+pub trait AudioBackend: Send {
+    /// Sample rate the mixer should output in.
+    fn sample_rate(&self) -> u32;
+
+    /// Returns how many frames should be mixed and submitted, given the time since the last
+    /// submission.
+    ///
+    /// By default it's just the amount of frames played during that time. Backends with their
+    /// own buffering may want to request more or less, depending on how full the buffer is.
+    fn frames_requested(&mut self, elapsed: Duration) -> usize {
+        (elapsed.as_secs_f64() * self.sample_rate() as f64).round() as usize
+    }
+
+    /// Outputs mixed interleaved stereo frames.
+    fn submit(&mut self, frames: &[f32]) -> AnyResult;
+}
+
+/// Backend that discards all audio.
+pub struct NullBackend {
+    sample_rate: u32,
+}
+
+impl NullBackend {
+    pub fn new(sample_rate: u32) -> Self {
+        Self { sample_rate }
+    }
+}
+
+impl AudioBackend for NullBackend {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn submit(&mut self, _frames: &[f32]) -> AnyResult {
+        ok()
+    }
+}
+
+/// Backend that records all audio into a 16-bit stereo WAV file.
+///
+/// The WAV header is rewritten after every submission, so the file stays valid even if the
+/// engine doesn't shut down cleanly. Recording stops once the file reaches the 4 GiB limit of the
+/// WAV format (about 6.7 hours at 44.1 kHz).
+pub struct WavBackend<W: Write + Seek + Send> {
+    writer: W,
+    sample_rate: u32,
+    /// Total amount of written samples, across both channels
+    samples_written: u32,
+}
+
+impl<W: Write + Seek + Send> WavBackend<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> AnyResult<Self> {
+        write_wav_header(&mut writer, sample_rate, 2, 0)?;
+        Ok(Self {
+            writer,
+            sample_rate,
+            samples_written: 0,
+        })
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write + Seek + Send> AudioBackend for WavBackend<W> {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn submit(&mut self, frames: &[f32]) -> AnyResult {
+        // Only whole stereo frames are written
+        let remaining = (MAX_WAV_SAMPLES - self.samples_written) as usize & !1;
+        if remaining == 0 {
+            return ok();
+        }
+        if frames.len() >= remaining {
+            warn!("The audio dump reached the maximum size of a WAV file, recording stopped");
+        }
+        let frames = &frames[..frames.len().min(remaining)];
+
+        for &sample in frames {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_i16::<LE>(sample)?;
+        }
+        self.samples_written += frames.len() as u32;
+
+        let position = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(0))?;
+        write_wav_header(&mut self.writer, self.sample_rate, 2, self.samples_written)?;
+        self.writer.seek(SeekFrom::Start(position))?;
+        self.writer.flush()?;
+
+        ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn wav_recording_stops_at_size_limit() {
+        let mut backend = WavBackend::new(Cursor::new(Vec::new()), 44100).unwrap();
+        // Pretend that hours of audio were already recorded, with room for a single frame left
+        backend.samples_written = MAX_WAV_SAMPLES - 3;
+
+        backend.submit(&[0.5, -0.5, 0.5, -0.5]).unwrap();
+        assert_eq!(backend.samples_written, MAX_WAV_SAMPLES - 1);
+        backend.submit(&[0.5, -0.5]).unwrap();
+        assert_eq!(backend.samples_written, MAX_WAV_SAMPLES - 1);
+
+        let file = backend.into_inner().into_inner();
+        assert_eq!(file.len(), 44 + 2 * 2);
+        assert_eq!(&file[40..44], &((MAX_WAV_SAMPLES - 1) * 2).to_le_bytes());
+    }
+}
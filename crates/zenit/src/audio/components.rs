@@ -0,0 +1,17 @@
+use super::VoiceId;
+use crate::entities::Component;
+
+/// Marks the entity, whose `TransformComponent` is used as the listener of positional audio.
+/// Only one listener is supported, if there are more, an arbitrary one is picked.
+#[derive(Debug, Clone, Default)]
+pub struct AudioListenerComponent;
+
+impl Component for AudioListenerComponent {}
+
+/// Links a positional voice to the entity, so that it follows the entity's `TransformComponent`.
+#[derive(Debug, Clone)]
+pub struct AudioSourceComponent {
+    pub voice: VoiceId,
+}
+
+impl Component for AudioSourceComponent {}
@@ -0,0 +1,441 @@
+use glam::*;
+use std::{f32::consts::FRAC_PI_2, sync::Arc};
+
+/// Decoded sound data, shareable between any amount of voices.
+#[derive(Debug, Clone)]
+pub struct SoundBuffer {
+    pub sample_rate: u32,
+    /// Either 1 (mono) or 2 (stereo)
+    pub channels: u16,
+    /// Interleaved samples
+    pub samples: Arc<[i16]>,
+}
+
+impl SoundBuffer {
+    pub fn new(sample_rate: u32, channels: u16, samples: impl Into<Arc<[i16]>>) -> Self {
+        assert!(
+            channels == 1 || channels == 2,
+            "only mono and stereo sounds are supported"
+        );
+        Self {
+            sample_rate,
+            channels,
+            samples: samples.into(),
+        }
+    }
+
+    /// Amount of samples per channel.
+    pub fn frame_count(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    /// Returns the frame at the given index, as a stereo pair in range [-1; 1].
+    fn frame(&self, index: usize) -> [f32; 2] {
+        let scale = 1.0 / i16::MAX as f32;
+        match self.channels {
+            1 => [self.samples[index] as f32 * scale; 2],
+            _ => [
+                self.samples[index * 2] as f32 * scale,
+                self.samples[index * 2 + 1] as f32 * scale,
+            ],
+        }
+    }
+}
+
+/// Mixer buses, each with its own volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioBus {
+    Effects,
+    Music,
+    Voice,
+    Interface,
+}
+
+impl AudioBus {
+    pub const COUNT: usize = 4;
+}
+
+/// Describes how a voice is positioned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VoiceSpatial {
+    /// Played as is, without panning or attenuation.
+    Ambient,
+    /// Panned and attenuated relative to the listener.
+    Positional {
+        position: Vec3A,
+        /// Distance up to which the voice is played at full volume
+        min_distance: f32,
+        /// Distance at which the voice becomes inaudible. Volume falls off linearly from
+        /// `min_distance`.
+        max_distance: f32,
+    },
+}
+
+/// Parameters of a new voice.
+#[derive(Debug, Clone)]
+pub struct VoiceDescriptor {
+    pub buffer: SoundBuffer,
+    pub bus: AudioBus,
+    pub volume: f32,
+    pub looping: bool,
+    pub spatial: VoiceSpatial,
+}
+
+/// Identifies a voice playing in the [`Mixer`]. Stays valid after the voice stops, but no
+/// longer refers to anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VoiceId {
+    index: u32,
+    generation: u32,
+}
+
+#[derive(Debug)]
+struct Voice {
+    desc: VoiceDescriptor,
+    /// Fractional read position in the buffer, in frames
+    cursor: f64,
+}
+
+/// Software audio mixer, outputting interleaved stereo `f32` frames.
+///
+/// Available in the global state as a lockable resource.
+#[derive(Debug)]
+pub struct Mixer {
+    sample_rate: u32,
+    voices: Vec<(u32, Option<Voice>)>,
+    master_volume: f32,
+    bus_volumes: [f32; AudioBus::COUNT],
+    /// Listener's world transform
+    listener: Affine3A,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            voices: Vec::new(),
+            master_volume: 1.0,
+            bus_volumes: [1.0; AudioBus::COUNT],
+            listener: Affine3A::IDENTITY,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Starts playing a new voice.
+    pub fn play(&mut self, desc: VoiceDescriptor) -> VoiceId {
+        let voice = Some(Voice { desc, cursor: 0.0 });
+
+        let index = match self.voices.iter().position(|(_, voice)| voice.is_none()) {
+            Some(index) => {
+                let slot = &mut self.voices[index];
+                slot.0 = slot.0.wrapping_add(1);
+                slot.1 = voice;
+                index
+            }
+            None => {
+                self.voices.push((0, voice));
+                self.voices.len() - 1
+            }
+        };
+
+        VoiceId {
+            index: index as u32,
+            generation: self.voices[index].0,
+        }
+    }
+
+    /// Stops a voice. Does nothing if it has already finished.
+    pub fn stop(&mut self, id: VoiceId) {
+        if let Some(slot) = self.voice_slot(id) {
+            *slot = None;
+        }
+    }
+
+    /// Checks whether the voice is still playing.
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        matches!(
+            self.voices.get(id.index as usize),
+            Some((generation, Some(_))) if *generation == id.generation
+        )
+    }
+
+    /// Amount of currently playing voices.
+    pub fn voice_count(&self) -> usize {
+        self.voices
+            .iter()
+            .filter(|(_, voice)| voice.is_some())
+            .count()
+    }
+
+    pub fn set_voice_volume(&mut self, id: VoiceId, volume: f32) {
+        if let Some(Some(voice)) = self.voice_slot(id) {
+            voice.desc.volume = volume;
+        }
+    }
+
+    /// Moves a positional voice. Does nothing for ambient voices.
+    pub fn set_voice_position(&mut self, id: VoiceId, new_position: Vec3A) {
+        if let Some(Some(voice)) = self.voice_slot(id) {
+            if let VoiceSpatial::Positional { position, .. } = &mut voice.desc.spatial {
+                *position = new_position;
+            }
+        }
+    }
+
+    pub fn set_listener(&mut self, transform: Affine3A) {
+        self.listener = transform;
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume;
+    }
+
+    pub fn bus_volume(&self, bus: AudioBus) -> f32 {
+        self.bus_volumes[bus as usize]
+    }
+
+    pub fn set_bus_volume(&mut self, bus: AudioBus, volume: f32) {
+        self.bus_volumes[bus as usize] = volume;
+    }
+
+    /// Mixes all voices into the output, which is interpreted as interleaved stereo frames.
+    /// The output is overwritten, not added to. Finished voices are removed.
+    pub fn mix(&mut self, output: &mut [f32]) {
+        output.fill(0.0);
+        let frames = output.len() / 2;
+        let listener = self.listener.inverse();
+
+        for (_, slot) in &mut self.voices {
+            let Some(voice) = slot else { continue };
+
+            let volume =
+                voice.desc.volume * self.master_volume * self.bus_volumes[voice.desc.bus as usize];
+            let [left_gain, right_gain] = spatial_gains(&voice.desc.spatial, listener);
+            let gains = [volume * left_gain, volume * right_gain];
+
+            let buffer = &voice.desc.buffer;
+            let buffer_frames = buffer.frame_count();
+            let step = buffer.sample_rate as f64 / self.sample_rate as f64;
+
+            let mut finished = buffer_frames == 0;
+            for frame in output.chunks_exact_mut(2).take(frames) {
+                if finished {
+                    break;
+                }
+
+                // Linear interpolation between neighbouring frames
+                let index = voice.cursor as usize;
+                let fraction = (voice.cursor - index as f64) as f32;
+                let current = buffer.frame(index);
+                let next = match index + 1 {
+                    next if next < buffer_frames => buffer.frame(next),
+                    _ if voice.desc.looping => buffer.frame(0),
+                    _ => current,
+                };
+
+                for channel in 0..2 {
+                    let sample = current[channel] + (next[channel] - current[channel]) * fraction;
+                    frame[channel] += sample * gains[channel];
+                }
+
+                voice.cursor += step;
+                if voice.cursor >= buffer_frames as f64 {
+                    if voice.desc.looping {
+                        voice.cursor %= buffer_frames as f64;
+                    } else {
+                        finished = true;
+                    }
+                }
+            }
+
+            if finished {
+                *slot = None;
+            }
+        }
+    }
+
+    fn voice_slot(&mut self, id: VoiceId) -> Option<&mut Option<Voice>> {
+        match self.voices.get_mut(id.index as usize) {
+            Some((generation, slot)) if *generation == id.generation => Some(slot),
+            _ => None,
+        }
+    }
+}
+
+/// Calculates left and right channel gains of a voice, given the inverse listener transform.
+fn spatial_gains(spatial: &VoiceSpatial, inverse_listener: Affine3A) -> [f32; 2] {
+    let VoiceSpatial::Positional {
+        position,
+        min_distance,
+        max_distance,
+    } = *spatial
+    else {
+        return [1.0, 1.0];
+    };
+
+    let local = inverse_listener.transform_point3a(position);
+    let distance = local.length();
+
+    let attenuation = if distance <= min_distance {
+        1.0
+    } else if distance >= max_distance {
+        0.0
+    } else {
+        1.0 - (distance - min_distance) / (max_distance - min_distance)
+    };
+
+    // Equal power panning, based on how far to the side the voice is. The listener looks
+    // towards -Z, so +X is to its right.
+    let pan = match local.try_normalize() {
+        Some(direction) => direction.x,
+        None => 0.0,
+    };
+    let angle = (pan + 1.0) * 0.5 * FRAC_PI_2;
+
+    [angle.cos() * attenuation, angle.sin() * attenuation]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constant_buffer(value: i16, frames: usize) -> SoundBuffer {
+        SoundBuffer::new(100, 1, vec![value; frames])
+    }
+
+    fn voice(buffer: SoundBuffer, bus: AudioBus, spatial: VoiceSpatial) -> VoiceDescriptor {
+        VoiceDescriptor {
+            buffer,
+            bus,
+            volume: 1.0,
+            looping: false,
+            spatial,
+        }
+    }
+
+    #[test]
+    fn ambient_voice_plays_and_finishes() {
+        let mut mixer = Mixer::new(100);
+        let id = mixer.play(voice(
+            constant_buffer(i16::MAX, 10),
+            AudioBus::Effects,
+            VoiceSpatial::Ambient,
+        ));
+
+        let mut output = vec![0.0; 2 * 20];
+        mixer.mix(&mut output);
+
+        assert!(output[..20].iter().all(|&s| (s - 1.0).abs() < 1e-4));
+        assert!(output[20..].iter().all(|&s| s == 0.0));
+        assert!(!mixer.is_playing(id));
+        assert_eq!(mixer.voice_count(), 0);
+    }
+
+    #[test]
+    fn looping_voice_keeps_playing() {
+        let mut mixer = Mixer::new(100);
+        let mut desc = voice(
+            constant_buffer(i16::MAX, 3),
+            AudioBus::Music,
+            VoiceSpatial::Ambient,
+        );
+        desc.looping = true;
+        let id = mixer.play(desc);
+
+        let mut output = vec![0.0; 2 * 20];
+        mixer.mix(&mut output);
+        assert!(output.iter().all(|&s| (s - 1.0).abs() < 1e-4));
+        assert!(mixer.is_playing(id));
+
+        mixer.stop(id);
+        assert!(!mixer.is_playing(id));
+    }
+
+    #[test]
+    fn bus_volumes_apply() {
+        let mut mixer = Mixer::new(100);
+        mixer.set_bus_volume(AudioBus::Voice, 0.5);
+        mixer.set_master_volume(0.5);
+        mixer.play(voice(
+            constant_buffer(i16::MAX, 10),
+            AudioBus::Voice,
+            VoiceSpatial::Ambient,
+        ));
+        mixer.play(voice(
+            constant_buffer(i16::MAX, 10),
+            AudioBus::Interface,
+            VoiceSpatial::Ambient,
+        ));
+
+        let mut output = vec![0.0; 2];
+        mixer.mix(&mut output);
+        assert!((output[0] - 0.75).abs() < 1e-4);
+    }
+
+    #[test]
+    fn positional_voices_are_panned_and_attenuated() {
+        let positional = |x: f32| VoiceSpatial::Positional {
+            position: vec3a(x, 0.0, 0.0),
+            min_distance: 1.0,
+            max_distance: 11.0,
+        };
+
+        let mut mixer = Mixer::new(100);
+        mixer.play(voice(
+            constant_buffer(i16::MAX, 10),
+            AudioBus::Effects,
+            positional(1.0),
+        ));
+        let mut output = vec![0.0; 2];
+        mixer.mix(&mut output);
+        assert!(output[0].abs() < 1e-4, "right voice leaks into the left");
+        assert!((output[1] - 1.0).abs() < 1e-4);
+
+        // Halfway between min and max distance, on the left
+        let mut mixer = Mixer::new(100);
+        mixer.play(voice(
+            constant_buffer(i16::MAX, 10),
+            AudioBus::Effects,
+            positional(-6.0),
+        ));
+        mixer.mix(&mut output);
+        assert!((output[0] - 0.5).abs() < 1e-4);
+        assert!(output[1].abs() < 1e-4);
+
+        // Turning the listener around swaps the sides
+        let mut mixer = Mixer::new(100);
+        mixer.set_listener(Affine3A::from_rotation_y(std::f32::consts::PI));
+        mixer.play(voice(
+            constant_buffer(i16::MAX, 10),
+            AudioBus::Effects,
+            positional(-6.0),
+        ));
+        mixer.mix(&mut output);
+        assert!(output[0].abs() < 1e-4);
+        assert!((output[1] - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn voices_are_resampled() {
+        // 50 Hz buffer played at 100 Hz takes twice as many frames
+        let mut mixer = Mixer::new(100);
+        let id = mixer.play(voice(
+            SoundBuffer::new(50, 1, vec![i16::MAX; 10]),
+            AudioBus::Effects,
+            VoiceSpatial::Ambient,
+        ));
+
+        let mut output = vec![0.0; 2 * 19];
+        mixer.mix(&mut output);
+        assert!(mixer.is_playing(id));
+        mixer.mix(&mut output[..2]);
+        assert!(!mixer.is_playing(id));
+    }
+}
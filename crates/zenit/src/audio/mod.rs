@@ -0,0 +1,37 @@
+//! Zenit audio mixer
+//!
+//! Audio is mixed entirely in software, by the [`Mixer`]. It owns a set of playing voices, each
+//! reading from a shared [`SoundBuffer`], and mixes them into a stereo stream. The stream is then
+//! handed over to an [`AudioBackend`], which is responsible for actually outputting it somewhere.
+//!
+//! ## [`AudioSystem`]
+//! The audio system is an engine [`crate::engine::System`]. Every frame it synchronizes voice
+//! positions with the ECS, mixes as many frames as the backend requests, and submits them.
+//!
+//! ## Positional audio
+//! Voices can be ambient (played as is), or positional. Positional voices are panned and
+//! attenuated relative to the listener, which is defined by an entity with an
+//! [`AudioListenerComponent`] and a `TransformComponent`. Voices linked to entities with an
+//! [`AudioSourceComponent`] follow their `TransformComponent`s automatically.
+//!
+//! ## Backends
+//! There's currently no backend outputting to an actual audio device. The [`NullBackend`]
+//! discards everything, while the [`WavBackend`] records everything into a WAV file, which is
+//! useful for testing the mixer headlessly.
+//!
+
+#[doc(inline)]
+pub use backend::*;
+mod backend;
+
+#[doc(inline)]
+pub use components::*;
+mod components;
+
+#[doc(inline)]
+pub use mixer::*;
+mod mixer;
+
+#[doc(inline)]
+pub use system::*;
+mod system;
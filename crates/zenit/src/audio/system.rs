@@ -0,0 +1,167 @@
+use super::{AudioBackend, AudioListenerComponent, AudioSourceComponent, Mixer};
+use crate::{
+    engine::{EngineContext, GlobalState, System},
+    entities::{components::TransformComponent, Universe},
+};
+use log::*;
+use std::time::{Duration, Instant};
+use zenit_utils::{ok, AnyResult};
+
+/// Upper limit of audio mixed in a single frame, so that long stalls don't result in mixing
+/// (and submitting) huge amounts of audio at once.
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+/// The audio system mixes all voices every frame and passes them to the backend.
+pub struct AudioSystem {
+    backend: Box<dyn AudioBackend>,
+    last_submission: Option<Instant>,
+    buffer: Vec<f32>,
+}
+
+impl AudioSystem {
+    /// Creates the audio system alongside its mixer, which should be put in the global state.
+    pub fn new(backend: Box<dyn AudioBackend>) -> (Mixer, Self) {
+        let mixer = Mixer::new(backend.sample_rate());
+        let system = Self {
+            backend,
+            last_submission: None,
+            buffer: Vec::new(),
+        };
+        (mixer, system)
+    }
+
+    /// Synchronizes the mixer with the ECS, and mixes audio played during the elapsed time.
+    pub fn process(
+        &mut self,
+        mixer: &mut Mixer,
+        universe: &Universe,
+        elapsed: Duration,
+    ) -> AnyResult {
+        sync_with_universe(mixer, universe);
+
+        let frames = self.backend.frames_requested(elapsed.min(MAX_FRAME_TIME));
+        if frames == 0 {
+            return ok();
+        }
+
+        self.buffer.resize(frames * 2, 0.0);
+        mixer.mix(&mut self.buffer);
+        self.backend.submit(&self.buffer)
+    }
+}
+
+impl System for AudioSystem {
+    fn label(&self) -> &'static str {
+        "Audio System"
+    }
+
+    fn main_process(&mut self, _ec: &EngineContext, gs: &GlobalState) {
+        let now = Instant::now();
+        let elapsed = match self.last_submission.replace(now) {
+            Some(last) => now - last,
+            None => Duration::ZERO,
+        };
+
+        let universe = gs.read::<Universe>();
+        let mut mixer = gs.lock::<Mixer>();
+        if let Err(error) = self.process(&mut mixer, &universe, elapsed) {
+            error!("An error occurred while submitting audio: {error:#?}");
+        }
+    }
+}
+
+/// Updates the listener and positions of all voices linked to entities.
+fn sync_with_universe(mixer: &mut Mixer, universe: &Universe) {
+    let listener = universe
+        .get_components::<AudioListenerComponent>()
+        .find_map(|(entity, _)| entity.get_component::<TransformComponent>().cloned());
+    if let Some(TransformComponent(transform)) = listener {
+        mixer.set_listener(transform);
+    }
+
+    for (entity, source) in universe.get_components::<AudioSourceComponent>() {
+        if let Some(TransformComponent(transform)) = entity.get_component::<TransformComponent>() {
+            mixer.set_voice_position(source.voice, transform.translation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AudioBus, SoundBuffer, VoiceDescriptor, VoiceSpatial, WavBackend};
+    use glam::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn sources_follow_their_entities() {
+        let (mut mixer, mut system) = AudioSystem::new(Box::new(
+            WavBackend::new(Cursor::new(Vec::new()), 100).unwrap(),
+        ));
+
+        let voice = mixer.play(VoiceDescriptor {
+            buffer: SoundBuffer::new(100, 1, vec![i16::MAX; 100]),
+            bus: AudioBus::Effects,
+            volume: 1.0,
+            looping: false,
+            spatial: VoiceSpatial::Positional {
+                position: Vec3A::ZERO,
+                min_distance: 1.0,
+                max_distance: 10.0,
+            },
+        });
+
+        let mut universe = Universe::new();
+        universe
+            .build_entity()
+            .with_component(AudioListenerComponent)
+            .with_component(TransformComponent(Affine3A::IDENTITY))
+            .finish();
+        let source = universe
+            .build_entity()
+            .with_component(AudioSourceComponent { voice })
+            .with_component(TransformComponent(Affine3A::from_translation(vec3(
+                20.0, 0.0, 0.0,
+            ))))
+            .finish();
+
+        // Out of range, so it's mixed in silence, but keeps playing
+        system
+            .process(&mut mixer, &universe, Duration::from_millis(100))
+            .unwrap();
+        assert!(mixer.is_playing(voice));
+
+        universe.set_component(
+            source,
+            TransformComponent(Affine3A::from_translation(vec3(0.0, 0.0, -1.0))),
+        );
+        sync_with_universe(&mut mixer, &universe);
+
+        let mut output = vec![0.0; 2];
+        mixer.mix(&mut output);
+        let expected = std::f32::consts::FRAC_1_SQRT_2;
+        assert!((output[0] - expected).abs() < 1e-4);
+        assert!((output[1] - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn wav_backend_records_everything() {
+        let mut backend = WavBackend::new(Cursor::new(Vec::new()), 100).unwrap();
+        backend.submit(&[1.0, -1.0, 0.0, 0.5]).unwrap();
+        backend.submit(&[2.0, -2.0]).unwrap();
+
+        let data = backend.into_inner().into_inner();
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(data[40..44].try_into().unwrap()), 12);
+        assert_eq!(data.len(), 44 + 12);
+
+        let samples: Vec<i16> = data[44..]
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        assert_eq!(
+            samples,
+            [i16::MAX, -i16::MAX, 0, i16::MAX / 2, i16::MAX, -i16::MAX]
+        );
+    }
+}
@@ -12,4 +12,12 @@ pub struct Args {
     #[clap(long)]
     /// Forces the engine to run singlethreaded. You should generally keep this off.
     pub single_thread: bool,
+
+    #[clap(long)]
+    /// Records all mixed audio into the specified WAV file.
+    pub audio_dump: Option<PathBuf>,
+
+    #[clap(long)]
+    /// Loads a word list (one name per line) used for displaying hashed names. Can be repeated.
+    pub hash_dictionary: Vec<PathBuf>,
 }
@@ -0,0 +1,341 @@
+use super::{sweep_sphere_triangle, Ray, RayHit};
+use glam::*;
+use thiserror::Error;
+use zenit_lvl::game::{CollisionMask, LevelCollisionMesh};
+
+/// Maximum amount of triangles stored in a single BVH leaf
+const MAX_LEAF_TRIANGLES: usize = 4;
+
+/// A triangle collision mesh, with a bounding volume hierarchy accelerating queries.
+///
+/// The hierarchy stored in level files isn't reused, as it's built around the game's quirks.
+/// Instead, the mesh is triangulated and a new BVH is built on load.
+#[derive(Debug, Clone)]
+pub struct CollisionMesh {
+    pub mask: CollisionMask,
+    vertices: Vec<Vec3A>,
+    triangles: Vec<[u32; 3]>,
+    nodes: Vec<BvhNode>,
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    min: Vec3A,
+    max: Vec3A,
+    kind: BvhNodeKind,
+}
+
+#[derive(Debug, Clone)]
+enum BvhNodeKind {
+    /// Indices of both children in the node list
+    Branch(u32, u32),
+    /// Range of triangles in the triangle list
+    Leaf(u32, u32),
+}
+
+#[derive(Debug, Error)]
+pub enum CollisionMeshError {
+    #[error("triangle references vertex {index}, but the mesh only has {vertex_count} vertices")]
+    VertexOutOfBounds { index: u32, vertex_count: usize },
+}
+
+impl TryFrom<&LevelCollisionMesh> for CollisionMesh {
+    type Error = CollisionMeshError;
+
+    fn try_from(value: &LevelCollisionMesh) -> Result<Self, Self::Error> {
+        Self::new(
+            value.positions.iter().copied().map(Vec3A::from).collect(),
+            value
+                .triangles()
+                .map(|indices| indices.map(u32::from))
+                .collect(),
+            value.mask.unwrap_or(CollisionMask::all()),
+        )
+    }
+}
+
+impl CollisionMesh {
+    /// Creates a new collision mesh and builds its BVH. Fails if any triangle references an out
+    /// of bounds vertex.
+    pub fn new(
+        vertices: Vec<Vec3A>,
+        mut triangles: Vec<[u32; 3]>,
+        mask: CollisionMask,
+    ) -> Result<Self, CollisionMeshError> {
+        if let Some(&index) = triangles
+            .iter()
+            .flatten()
+            .find(|&&i| i as usize >= vertices.len())
+        {
+            return Err(CollisionMeshError::VertexOutOfBounds {
+                index,
+                vertex_count: vertices.len(),
+            });
+        }
+
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            let len = triangles.len();
+            build_bvh(&vertices, &mut triangles, &mut nodes, 0, len);
+        }
+
+        Ok(Self {
+            mask,
+            vertices,
+            triangles,
+            nodes,
+        })
+    }
+
+    pub fn vertices(&self) -> &[Vec3A] {
+        &self.vertices
+    }
+
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    /// Casts a ray against the mesh.
+    pub fn cast_ray(&self, ray: &Ray, max_distance: f32) -> Option<RayHit> {
+        self.sweep_sphere(ray, 0.0, max_distance)
+    }
+
+    /// Sweeps a sphere against the mesh.
+    pub fn sweep_sphere(&self, ray: &Ray, radius: f32, max_distance: f32) -> Option<RayHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut result: Option<RayHit> = None;
+        let mut stack = vec![0u32];
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index as usize];
+            let limit = result.map(|hit| hit.distance).unwrap_or(max_distance);
+
+            // Inflating the bounds by the radius keeps the test conservative for sweeps
+            match ray_aabb_distance(ray, node.min - radius, node.max + radius) {
+                Some(distance) if distance <= limit => {}
+                _ => continue,
+            }
+
+            match node.kind {
+                BvhNodeKind::Branch(left, right) => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                BvhNodeKind::Leaf(start, end) => {
+                    for triangle in &self.triangles[start as usize..end as usize] {
+                        let triangle = triangle.map(|i| self.vertices[i as usize]);
+                        let hit = sweep_sphere_triangle(ray, radius, triangle)
+                            .filter(|hit| hit.distance <= max_distance);
+                        result = RayHit::closest(result, hit);
+                    }
+                }
+            }
+        }
+
+        result
+    }
+}
+
+/// Recursively builds BVH nodes for the `start..end` triangle range, returning the node index.
+///
+/// Triangles are split along the longest axis of their centroids' bounds, at the median.
+fn build_bvh(
+    vertices: &[Vec3A],
+    triangles: &mut [[u32; 3]],
+    nodes: &mut Vec<BvhNode>,
+    start: usize,
+    end: usize,
+) -> u32 {
+    let range = &mut triangles[start..end];
+
+    let (min, max) = range.iter().flatten().map(|&i| vertices[i as usize]).fold(
+        (Vec3A::splat(f32::INFINITY), Vec3A::splat(f32::NEG_INFINITY)),
+        |(min, max), v| (min.min(v), max.max(v)),
+    );
+
+    let index = nodes.len() as u32;
+    nodes.push(BvhNode {
+        min,
+        max,
+        kind: BvhNodeKind::Leaf(start as u32, end as u32),
+    });
+
+    if range.len() <= MAX_LEAF_TRIANGLES {
+        return index;
+    }
+
+    let centroid = |t: &[u32; 3]| t.iter().map(|&i| vertices[i as usize]).sum::<Vec3A>() / 3.0;
+    let (centroid_min, centroid_max) = range.iter().map(centroid).fold(
+        (Vec3A::splat(f32::INFINITY), Vec3A::splat(f32::NEG_INFINITY)),
+        |(min, max), c| (min.min(c), max.max(c)),
+    );
+
+    let extent = centroid_max - centroid_min;
+    let axis = if extent.x >= extent.y && extent.x >= extent.z {
+        0
+    } else if extent.y >= extent.z {
+        1
+    } else {
+        2
+    };
+
+    let middle = range.len() / 2;
+    range.select_nth_unstable_by(middle, |a, b| {
+        centroid(a)[axis].total_cmp(&centroid(b)[axis])
+    });
+
+    let left = build_bvh(vertices, triangles, nodes, start, start + middle);
+    let right = build_bvh(vertices, triangles, nodes, start + middle, end);
+    nodes[index as usize].kind = BvhNodeKind::Branch(left, right);
+
+    index
+}
+
+/// Returns the distance at which the ray enters the box, or 0 if it starts inside of it.
+fn ray_aabb_distance(ray: &Ray, min: Vec3A, max: Vec3A) -> Option<f32> {
+    let mut t_enter = 0.0f32;
+    let mut t_exit = f32::INFINITY;
+
+    for axis in 0..3 {
+        let (origin, direction) = (ray.origin[axis], ray.direction[axis]);
+
+        // Parallel axes would produce NaNs (0 * inf), which glam's min/max don't handle
+        // consistently. The ray is either always or never within the slab instead.
+        if direction == 0.0 {
+            if origin < min[axis] || origin > max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let inverse = direction.recip();
+        let t1 = (min[axis] - origin) * inverse;
+        let t2 = (max[axis] - origin) * inverse;
+        t_enter = t_enter.max(t1.min(t2));
+        t_exit = t_exit.min(t1.max(t2));
+    }
+
+    (t_enter <= t_exit).then_some(t_enter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenit_lvl::game::{CollisionMeshInfo, CollisionTreeNode};
+
+    /// A flat grid of `size` by `size` quads on the XZ plane, with Y = 0
+    fn grid(size: u32) -> CollisionMesh {
+        let mut vertices = Vec::new();
+        for z in 0..=size {
+            for x in 0..=size {
+                vertices.push(vec3a(x as f32, 0.0, z as f32));
+            }
+        }
+
+        let mut triangles = Vec::new();
+        let row = size + 1;
+        for z in 0..size {
+            for x in 0..size {
+                let i = z * row + x;
+                triangles.push([i, i + row, i + 1]);
+                triangles.push([i + 1, i + row, i + row + 1]);
+            }
+        }
+
+        CollisionMesh::new(vertices, triangles, CollisionMask::TERRAIN).unwrap()
+    }
+
+    #[test]
+    fn ray_hits_mesh_through_bvh() {
+        let mesh = grid(16);
+        assert!(mesh.nodes.len() > 1, "BVH should have been split");
+
+        let ray = Ray::new(vec3a(7.3, 10.0, 11.6), -Vec3A::Y);
+        let hit = mesh.cast_ray(&ray, 100.0).expect("ray missed");
+        assert!((hit.distance - 10.0).abs() < 1e-4);
+        assert!(hit.normal.abs_diff_eq(Vec3A::Y, 1e-4));
+
+        let outside = Ray::new(vec3a(-1.0, 10.0, 5.0), -Vec3A::Y);
+        assert!(mesh.cast_ray(&outside, 100.0).is_none());
+        assert!(mesh.cast_ray(&ray, 5.0).is_none());
+    }
+
+    #[test]
+    fn sphere_sweeps_against_mesh() {
+        let mesh = grid(8);
+
+        let ray = Ray::new(vec3a(4.5, 10.0, 4.5), -Vec3A::Y);
+        let hit = mesh.sweep_sphere(&ray, 1.0, 100.0).expect("sweep missed");
+        assert!((hit.distance - 9.0).abs() < 1e-4);
+
+        // Slides along the grid's edge, touching it only because of the radius
+        let ray = Ray::new(vec3a(-0.5, 0.0, -10.0), Vec3A::Z);
+        assert!(mesh.cast_ray(&ray, 100.0).is_none());
+        let hit = mesh.sweep_sphere(&ray, 1.0, 100.0).expect("sweep missed");
+        assert!(hit.distance < 10.0);
+    }
+
+    #[test]
+    fn parallel_rays_against_boxes() {
+        let (min, max) = (vec3a(0.0, 0.0, 0.0), vec3a(1.0, 1.0, 1.0));
+
+        // Touching the box's face, and starting on its boundary
+        let ray = Ray::new(vec3a(0.5, 0.0, -2.0), Vec3A::Z);
+        assert_eq!(ray_aabb_distance(&ray, min, max), Some(2.0));
+        let ray = Ray::new(vec3a(1.0, 1.0, 0.5), Vec3A::Z);
+        assert_eq!(ray_aabb_distance(&ray, min, max), Some(0.0));
+
+        // Outside of the slabs of the parallel axes
+        let ray = Ray::new(vec3a(0.5, 1.5, -2.0), Vec3A::Z);
+        assert_eq!(ray_aabb_distance(&ray, min, max), None);
+        let ray = Ray::new(vec3a(-0.5, 0.5, -2.0), Vec3A::Z);
+        assert_eq!(ray_aabb_distance(&ray, min, max), None);
+
+        // Pointing away from the box
+        let ray = Ray::new(vec3a(0.5, 0.5, -2.0), -Vec3A::Z);
+        assert_eq!(ray_aabb_distance(&ray, min, max), None);
+    }
+
+    #[test]
+    fn out_of_bounds_indices_are_rejected() {
+        let vertices = vec![Vec3A::ZERO, Vec3A::X, Vec3A::Z];
+        let result = CollisionMesh::new(vertices, vec![[0, 1, 3]], CollisionMask::all());
+        assert!(matches!(
+            result,
+            Err(CollisionMeshError::VertexOutOfBounds {
+                index: 3,
+                vertex_count: 3
+            })
+        ));
+
+        // Corrupt level meshes are reported instead of panicking
+        let mut level_mesh = LevelCollisionMesh {
+            name: Default::default(),
+            mask: None,
+            node: Default::default(),
+            info: CollisionMeshInfo {
+                vertex_count: 3,
+                node_count: 0,
+                leaf_count: 1,
+                index_count: 4,
+                aabb_min: [0.0; 3],
+                aabb_max: [1.0, 0.0, 1.0],
+            },
+            positions: vec![[0.0; 3], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            tree: vec![CollisionTreeNode::Leaf {
+                indices: vec![0, 1, 2],
+            }],
+        };
+        let mesh = CollisionMesh::try_from(&level_mesh).unwrap();
+        assert_eq!(mesh.triangles(), [[0, 1, 2]]);
+        assert_eq!(mesh.mask, CollisionMask::all());
+
+        level_mesh.tree = vec![CollisionTreeNode::Leaf {
+            indices: vec![0, 1, 2, 5],
+        }];
+        assert!(CollisionMesh::try_from(&level_mesh).is_err());
+    }
+}
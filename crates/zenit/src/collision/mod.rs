@@ -0,0 +1,470 @@
+//! CPU-side collision queries
+//!
+//! This module implements ray casts and sphere sweeps against the collision data stored in level
+//! files - primitive shapes (spheres, cylinders and cubes) and triangle meshes. It doesn't touch
+//! the GPU or the ECS in any way, it's pure math.
+//!
+//! ## Conventions
+//!  * All queries are expressed as a [`Ray`] with a normalized direction, and a maximum distance.
+//!    Hit distances are measured along that ray.
+//!  * Transforms of collision shapes are assumed to be rigid (rotation + translation only).
+//!  * A sphere sweep is a ray cast with a thickness. It reports the distance at which a sphere
+//!    moving along the ray first touches the shape.
+//!  * Queries starting inside of a shape report a hit at distance 0.
+//!
+
+use glam::*;
+use zenit_lvl::game::{CollisionMask, CollisionShape, LevelCollisionPrimitive};
+
+#[doc(inline)]
+pub use mesh::*;
+mod mesh;
+
+/// A half-line, used for all collision queries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3A,
+    /// Normalized direction of the ray
+    pub direction: Vec3A,
+}
+
+impl Ray {
+    /// Creates a new ray, normalizing the direction.
+    pub fn new(origin: Vec3A, direction: Vec3A) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    /// Returns the point at the given distance along the ray.
+    #[inline]
+    pub fn at(&self, distance: f32) -> Vec3A {
+        self.origin + self.direction * distance
+    }
+}
+
+/// Result of a successful collision query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// Distance along the ray, at which the hit occurred
+    pub distance: f32,
+    /// Normalized surface normal at the hit point. For sphere sweeps, it points from the contact
+    /// point towards the sphere's center.
+    pub normal: Vec3A,
+}
+
+impl RayHit {
+    /// Picks the closer of two optional hits.
+    pub fn closest(a: Option<RayHit>, b: Option<RayHit>) -> Option<RayHit> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(if a.distance <= b.distance { a } else { b }),
+            (a, None) => a,
+            (None, b) => b,
+        }
+    }
+}
+
+/// A primitive collision shape placed in the world.
+#[derive(Debug, Clone)]
+pub struct CollisionPrimitive {
+    pub shape: CollisionShape,
+    pub transform: Affine3A,
+    pub mask: CollisionMask,
+}
+
+impl From<&LevelCollisionPrimitive> for CollisionPrimitive {
+    fn from(value: &LevelCollisionPrimitive) -> Self {
+        let [x, y, z] = value.transform.rotation;
+        Self {
+            shape: value.shape,
+            transform: Affine3A {
+                matrix3: Mat3A::from_cols(x.into(), y.into(), z.into()).transpose(),
+                translation: value.transform.position.into(),
+            },
+            mask: value.mask.unwrap_or(CollisionMask::all()),
+        }
+    }
+}
+
+impl CollisionPrimitive {
+    /// Casts a ray against the primitive.
+    pub fn cast_ray(&self, ray: &Ray, max_distance: f32) -> Option<RayHit> {
+        self.sweep_sphere(ray, 0.0, max_distance)
+    }
+
+    /// Sweeps a sphere against the primitive.
+    ///
+    /// Sweeps against cylinders and cubes are approximated by inflating the shape by the radius,
+    /// which overestimates the shape around its edges and corners.
+    pub fn sweep_sphere(&self, ray: &Ray, radius: f32, max_distance: f32) -> Option<RayHit> {
+        // Transform the ray into the primitive's local space. The direction is deliberately
+        // not renormalized, so distances along the local ray match the world ones.
+        let inverse = self.transform.inverse();
+        let local = Ray {
+            origin: inverse.transform_point3a(ray.origin),
+            direction: inverse.transform_vector3a(ray.direction),
+        };
+
+        let hit = match self.shape {
+            CollisionShape::Sphere { radius: sphere } => {
+                ray_sphere(&local, Vec3A::ZERO, sphere + radius)
+            }
+            CollisionShape::Cylinder {
+                radius: cylinder,
+                half_height,
+            } => ray_cylinder(&local, cylinder + radius, half_height + radius),
+            CollisionShape::Cube { half_extents } => {
+                let extents = Vec3A::from(half_extents) + radius;
+                ray_aabb(&local, -extents, extents)
+            }
+        }?;
+
+        (hit.distance <= max_distance).then(|| RayHit {
+            distance: hit.distance,
+            normal: self.transform.transform_vector3a(hit.normal).normalize(),
+        })
+    }
+}
+
+/// Intersects a ray with a sphere.
+pub fn ray_sphere(ray: &Ray, center: Vec3A, radius: f32) -> Option<RayHit> {
+    let m = ray.origin - center;
+    let a = ray.direction.length_squared();
+    let b = m.dot(ray.direction);
+    let c = m.length_squared() - radius * radius;
+
+    if c <= 0.0 {
+        // Starting inside
+        return Some(RayHit {
+            distance: 0.0,
+            normal: -ray.direction.normalize(),
+        });
+    }
+
+    if b > 0.0 {
+        // Outside, and pointing away
+        return None;
+    }
+
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let distance = (-b - discriminant.sqrt()) / a;
+    Some(RayHit {
+        distance,
+        normal: (m + ray.direction * distance).normalize(),
+    })
+}
+
+/// Intersects a ray with an axis aligned bounding box, using the slab method.
+pub fn ray_aabb(ray: &Ray, min: Vec3A, max: Vec3A) -> Option<RayHit> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut normal = Vec3A::ZERO;
+
+    for axis in 0..3 {
+        let origin = ray.origin[axis];
+        let direction = ray.direction[axis];
+
+        if direction.abs() < f32::EPSILON {
+            // Parallel to the slab, must already be within it
+            if origin < min[axis] || origin > max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let t1 = (min[axis] - origin) / direction;
+        let t2 = (max[axis] - origin) / direction;
+        let (near, far, sign) = if t1 < t2 {
+            (t1, t2, -1.0)
+        } else {
+            (t2, t1, 1.0)
+        };
+
+        if near > t_enter {
+            t_enter = near;
+            normal = Vec3A::ZERO;
+            normal[axis] = sign;
+        }
+        t_exit = t_exit.min(far);
+
+        if t_enter > t_exit {
+            return None;
+        }
+    }
+
+    if t_exit < 0.0 {
+        return None;
+    }
+
+    if t_enter <= 0.0 {
+        return Some(RayHit {
+            distance: 0.0,
+            normal: -ray.direction.normalize(),
+        });
+    }
+
+    Some(RayHit {
+        distance: t_enter,
+        normal,
+    })
+}
+
+/// Intersects a ray with a capped cylinder, centered at the origin and aligned along the Y axis.
+pub fn ray_cylinder(ray: &Ray, radius: f32, half_height: f32) -> Option<RayHit> {
+    let origin = ray.origin;
+    let direction = ray.direction;
+
+    let inside_radius = origin.x * origin.x + origin.z * origin.z <= radius * radius;
+    if inside_radius && origin.y.abs() <= half_height {
+        return Some(RayHit {
+            distance: 0.0,
+            normal: -direction.normalize(),
+        });
+    }
+
+    let mut result = None;
+
+    // Side
+    let a = direction.x * direction.x + direction.z * direction.z;
+    if a > f32::EPSILON {
+        let b = origin.x * direction.x + origin.z * direction.z;
+        let c = origin.x * origin.x + origin.z * origin.z - radius * radius;
+        let discriminant = b * b - a * c;
+        if discriminant >= 0.0 {
+            let distance = (-b - discriminant.sqrt()) / a;
+            let point = origin + direction * distance;
+            if distance >= 0.0 && point.y.abs() <= half_height {
+                result = Some(RayHit {
+                    distance,
+                    normal: vec3a(point.x, 0.0, point.z).normalize(),
+                });
+            }
+        }
+    }
+
+    // Caps
+    if direction.y.abs() > f32::EPSILON {
+        for cap in [-half_height, half_height] {
+            let distance = (cap - origin.y) / direction.y;
+            let point = origin + direction * distance;
+            if distance >= 0.0 && point.x * point.x + point.z * point.z <= radius * radius {
+                result = RayHit::closest(
+                    result,
+                    Some(RayHit {
+                        distance,
+                        normal: vec3a(0.0, cap.signum(), 0.0),
+                    }),
+                );
+            }
+        }
+    }
+
+    result
+}
+
+/// Intersects a ray with a capsule spanning between points `a` and `b`.
+pub fn ray_capsule(ray: &Ray, a: Vec3A, b: Vec3A, radius: f32) -> Option<RayHit> {
+    let axis = b - a;
+    let length = axis.length();
+    if length < f32::EPSILON {
+        return ray_sphere(ray, a, radius);
+    }
+    let axis = axis / length;
+
+    // Intersect with the infinite cylinder around the axis first
+    let offset = ray.origin - a;
+    let direction_perp = ray.direction - axis * ray.direction.dot(axis);
+    let offset_perp = offset - axis * offset.dot(axis);
+
+    let qa = direction_perp.length_squared();
+    let qb = offset_perp.dot(direction_perp);
+    let qc = offset_perp.length_squared() - radius * radius;
+
+    let mut result = None;
+    if qa > f32::EPSILON {
+        let discriminant = qb * qb - qa * qc;
+        if discriminant >= 0.0 {
+            let distance = ((-qb - discriminant.sqrt()) / qa).max(0.0);
+            let point = ray.at(distance);
+            let projection = (point - a).dot(axis);
+            if (0.0..=length).contains(&projection) && (qc > 0.0 || distance == 0.0) {
+                let closest = a + axis * projection;
+                result = Some(RayHit {
+                    distance,
+                    normal: (point - closest).try_normalize().unwrap_or(-ray.direction),
+                });
+            }
+        }
+    }
+
+    // And then the end caps
+    result = RayHit::closest(result, ray_sphere(ray, a, radius));
+    result = RayHit::closest(result, ray_sphere(ray, b, radius));
+    result
+}
+
+/// Sweeps a sphere against a single triangle.
+pub fn sweep_sphere_triangle(ray: &Ray, radius: f32, triangle: [Vec3A; 3]) -> Option<RayHit> {
+    let [a, b, c] = triangle;
+    let mut normal = (b - a).cross(c - a).try_normalize()?;
+
+    // Face the ray
+    if normal.dot(ray.direction) > 0.0 {
+        normal = -normal;
+    }
+
+    let mut result = None;
+
+    // Face - intersect with the plane offset by the radius
+    let denominator = normal.dot(ray.direction);
+    if denominator.abs() > f32::EPSILON {
+        let plane_distance = (a + normal * radius - ray.origin).dot(normal);
+        let distance = plane_distance / denominator;
+
+        // A ray starting within the radius of the plane can still touch the face at 0
+        let distance = if distance < 0.0 && plane_distance.abs() <= radius {
+            0.0
+        } else {
+            distance
+        };
+
+        if distance >= 0.0 {
+            let contact = ray.at(distance) - normal * radius;
+            if point_in_triangle(contact, a, b, c) {
+                result = Some(RayHit { distance, normal });
+            }
+        }
+    }
+
+    // Edges and vertices, only matters for sweeps
+    if radius > 0.0 {
+        for (start, end) in [(a, b), (b, c), (c, a)] {
+            result = RayHit::closest(result, ray_capsule(ray, start, end, radius));
+        }
+    }
+
+    result
+}
+
+/// Checks whether a point lying on a triangle's plane is within the triangle.
+fn point_in_triangle(point: Vec3A, a: Vec3A, b: Vec3A, c: Vec3A) -> bool {
+    let normal = (b - a).cross(c - a);
+    let edge_test = |start: Vec3A, end: Vec3A| (end - start).cross(point - start).dot(normal);
+    edge_test(a, b) >= 0.0 && edge_test(b, c) >= 0.0 && edge_test(c, a) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenit_utils::math::AngleExt;
+
+    fn primitive(shape: CollisionShape, transform: Affine3A) -> CollisionPrimitive {
+        CollisionPrimitive {
+            shape,
+            transform,
+            mask: CollisionMask::all(),
+        }
+    }
+
+    #[test]
+    fn ray_hits_sphere() {
+        let sphere = primitive(
+            CollisionShape::Sphere { radius: 1.0 },
+            Affine3A::from_translation(vec3(0.0, 0.0, 10.0)),
+        );
+        let ray = Ray::new(Vec3A::ZERO, Vec3A::Z);
+
+        let hit = sphere.cast_ray(&ray, 100.0).expect("ray missed");
+        assert!((hit.distance - 9.0).abs() < 1e-4);
+        assert!(hit.normal.abs_diff_eq(-Vec3A::Z, 1e-4));
+
+        assert!(sphere.cast_ray(&ray, 5.0).is_none());
+        assert!(sphere
+            .cast_ray(&Ray::new(Vec3A::ZERO, -Vec3A::Z), 100.0)
+            .is_none());
+    }
+
+    #[test]
+    fn ray_hits_rotated_cube() {
+        // A cube rotated 45 degrees around Y, so its edge faces the ray
+        let cube = primitive(
+            CollisionShape::Cube {
+                half_extents: [1.0, 1.0, 1.0],
+            },
+            Affine3A::from_rotation_translation(
+                Quat::from_rotation_y(45.0f32.degrees().to_radians()),
+                vec3(0.0, 0.0, 10.0),
+            ),
+        );
+
+        let hit = cube
+            .cast_ray(&Ray::new(Vec3A::ZERO, Vec3A::Z), 100.0)
+            .expect("ray missed");
+        assert!((hit.distance - (10.0 - 2f32.sqrt())).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ray_hits_cylinder_cap_and_side() {
+        let cylinder = primitive(
+            CollisionShape::Cylinder {
+                radius: 1.0,
+                half_height: 2.0,
+            },
+            Affine3A::IDENTITY,
+        );
+
+        let from_above = Ray::new(vec3a(0.0, 10.0, 0.0), -Vec3A::Y);
+        let hit = cylinder.cast_ray(&from_above, 100.0).unwrap();
+        assert!((hit.distance - 8.0).abs() < 1e-4);
+        assert!(hit.normal.abs_diff_eq(Vec3A::Y, 1e-4));
+
+        let from_side = Ray::new(vec3a(-5.0, 1.5, 0.0), Vec3A::X);
+        let hit = cylinder.cast_ray(&from_side, 100.0).unwrap();
+        assert!((hit.distance - 4.0).abs() < 1e-4);
+        assert!(hit.normal.abs_diff_eq(-Vec3A::X, 1e-4));
+
+        let past_top = Ray::new(vec3a(-5.0, 2.5, 0.0), Vec3A::X);
+        assert!(cylinder.cast_ray(&past_top, 100.0).is_none());
+    }
+
+    #[test]
+    fn sphere_sweeps_against_sphere() {
+        let sphere = primitive(CollisionShape::Sphere { radius: 1.0 }, Affine3A::IDENTITY);
+
+        // Would miss as a ray, but not as a sphere of radius 1
+        let ray = Ray::new(vec3a(-10.0, 1.5, 0.0), Vec3A::X);
+        assert!(sphere.cast_ray(&ray, 100.0).is_none());
+
+        let hit = sphere.sweep_sphere(&ray, 1.0, 100.0).unwrap();
+        let expected = 10.0 - (2.0f32 * 2.0 - 1.5 * 1.5).sqrt();
+        assert!((hit.distance - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn sphere_sweeps_against_triangle_edge() {
+        let triangle = [
+            vec3a(0.0, 0.0, 0.0),
+            vec3a(1.0, 0.0, 0.0),
+            vec3a(0.0, 1.0, 0.0),
+        ];
+
+        // Passes beside the triangle, grazing its edge along the Y axis
+        let ray = Ray::new(vec3a(-0.5, 0.5, -10.0), Vec3A::Z);
+        assert!(sweep_sphere_triangle(&ray, 0.0, triangle).is_none());
+
+        let hit = sweep_sphere_triangle(&ray, 1.0, triangle).unwrap();
+        let expected = 10.0 - (1.0f32 - 0.5 * 0.5).sqrt();
+        assert!((hit.distance - expected).abs() < 1e-4);
+
+        // Head-on hit into the face
+        let ray = Ray::new(vec3a(0.25, 0.25, -10.0), Vec3A::Z);
+        let hit = sweep_sphere_triangle(&ray, 0.5, triangle).unwrap();
+        assert!((hit.distance - 9.5).abs() < 1e-4);
+        assert!(hit.normal.abs_diff_eq(-Vec3A::Z, 1e-4));
+    }
+}
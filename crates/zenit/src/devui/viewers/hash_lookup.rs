@@ -0,0 +1,68 @@
+use crate::{
+    devui::{imgui_ext::UiExt, DevUiWidget, WidgetResponse},
+    scene::EngineBorrow,
+};
+use imgui::Ui;
+use zenit_utils::fnv1a_hash;
+
+/// Looks up hashed names in the asset manager's hash dictionary, and hashes arbitrary names.
+pub struct HashLookup {
+    id: u64,
+    name: String,
+    hash: String,
+}
+
+impl Default for HashLookup {
+    fn default() -> Self {
+        Self {
+            id: zenit_utils::counter::next(),
+            name: String::new(),
+            hash: String::new(),
+        }
+    }
+}
+
+impl DevUiWidget for HashLookup {
+    fn process_ui(&mut self, ui: &mut Ui, engine: &mut EngineBorrow) -> WidgetResponse {
+        let mut opened = true;
+        let Some(_window_token) = ui
+            .window(format!("Hash Lookup##{}", self.id))
+            .opened(&mut opened)
+            .size([400.0, 200.0], imgui::Condition::Appearing)
+            .begin()
+        else {
+            return WidgetResponse::CLOSED;
+        };
+
+        let names = &mut engine.assets.names;
+
+        ui.text_disabled("Name to hash");
+        ui.separator();
+        ui.input_text("Name", &mut self.name).build();
+        let name_hash = fnv1a_hash(self.name.as_bytes());
+        ui.bullet_field("Hash", format!("0x{name_hash:08x}"));
+        let known = names.lookup(name_hash).is_some();
+        ui.disabled(known || self.name.is_empty(), || {
+            if ui.button("Add to dictionary") {
+                names.insert(&self.name);
+            }
+        });
+
+        ui.spacing();
+
+        ui.text_disabled("Hash to name");
+        ui.separator();
+        ui.input_text("Hash", &mut self.hash).build();
+        let hash = self.hash.trim();
+        let hash = hash.strip_prefix("0x").unwrap_or(hash);
+        match u32::from_str_radix(hash, 16) {
+            Ok(hash) => ui.bullet_field("Name", names.lookup(hash).unwrap_or("(unknown)")),
+            Err(_) => ui.bullet_field("Name", "(invalid hash)"),
+        }
+
+        ui.spacing();
+        ui.text_disabled(format!("{} known names", names.len()));
+
+        WidgetResponse::keep_opened(opened)
+    }
+}
@@ -1,5 +1,6 @@
 use super::{imgui_demo::imgui_demo, DevUiWidget};
 
+mod hash_lookup;
 mod model_preview;
 mod renderer_viewer;
 
@@ -8,5 +9,6 @@ pub const TOOLS: &[(&str, fn() -> Box<dyn DevUiWidget>)] = &[
     ("Renderer Tools", || {
         Box::new(renderer_viewer::RendererViewer::default())
     }),
+    ("Hash Lookup", || Box::new(hash_lookup::HashLookup::default())),
     ("Dear ImGui Demo", || Box::new(imgui_demo)),
 ];
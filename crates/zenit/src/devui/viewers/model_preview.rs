@@ -28,6 +28,7 @@ impl ModelPreview {
         let skybox = renderer.create_skybox(&SkyboxDescriptor {
             name: String::from("model preview skybox"),
             background: crate::graphics::SkyboxBackground::Solid(vec4(1.0, 0.0, 1.0, 1.0)),
+            fog: None,
         });
 
         Self {
@@ -39,6 +40,11 @@ impl ModelPreview {
         }
     }
 
+    /// Replaces the background of the preview.
+    pub fn set_skybox(&mut self, skybox: SkyboxHandle) {
+        self.skybox = skybox;
+    }
+
     pub fn display(&mut self, ui: &Ui, renderer: &mut Renderer, [width, height]: [f32; 2]) {
         let mut should_render_scene = true;
 
@@ -1,6 +1,6 @@
 use crate::{
     devui::{imgui_ext::UiExt, viewers::model_preview::ModelPreview},
-    graphics::{CubemapHandle, TextureHandle},
+    graphics::{CubemapHandle, SkyboxHandle, TextureHandle},
     scene::EngineBorrow,
 };
 use glam::*;
@@ -21,8 +21,11 @@ impl Default for TextureViewer {
 impl super::RendererViewerTab for TextureViewer {
     fn show_ui(&mut self, ui: &Ui, engine: &mut EngineBorrow) {
         let renderer = &mut engine.renderer;
+        let assets = &*engine.assets;
 
         let mut texture_to_select = None;
+        let mut skyboxes: Vec<_> = assets.skyboxes.iter().collect();
+        skyboxes.sort_by_key(|(&hash, _)| hash);
 
         ui.child_window("left")
             .border(true)
@@ -48,6 +51,16 @@ impl super::RendererViewerTab for TextureViewer {
                         texture_to_select = Some(SelectedTexture::Cubemap(handle));
                     }
                 }
+                ui.separator();
+                for &(&hash, handle) in &skyboxes {
+                    if ui
+                        .selectable_config(format!("sky {}", assets.names.display(hash)))
+                        .selected(self.selected_texture.matches_skybox(handle))
+                        .build()
+                    {
+                        texture_to_select = Some(SelectedTexture::Skybox(hash, handle.clone()));
+                    }
+                }
             });
 
         ui.same_line();
@@ -128,11 +141,32 @@ impl super::RendererViewerTab for TextureViewer {
 
                 model_preview.display(ui, renderer, preview_size);
             }
+            Some((SelectedTexture::Skybox(hash, handle), model_preview)) => {
+                ui.text(format!("sky {}", assets.names.display(*hash)));
+                ui.same_line();
+                ui.text_disabled("(level skybox)");
+                ui.separator();
+
+                match renderer.skyboxes.get(handle).fog {
+                    Some(fog) => {
+                        let [r, g, b, a] = fog.color.to_array();
+                        ui.text(format!("Fog color: {r:.2}, {g:.2}, {b:.2}, {a:.2}"));
+                        ui.text(format!("Fog range: {} - {}", fog.near, fog.far));
+                    }
+                    None => ui.text_disabled("(No fog)"),
+                }
+
+                model_preview.display(ui, renderer, preview_size);
+            }
             None => ui.text_disabled("(No texture selected)"),
         }
 
         if let Some(texture_to_select) = texture_to_select {
-            self.selected_texture = Some((texture_to_select, ModelPreview::new(renderer)));
+            let mut model_preview = ModelPreview::new(renderer);
+            if let SelectedTexture::Skybox(_, handle) = &texture_to_select {
+                model_preview.set_skybox(handle.clone());
+            }
+            self.selected_texture = Some((texture_to_select, model_preview));
         }
     }
 }
@@ -141,11 +175,14 @@ impl super::RendererViewerTab for TextureViewer {
 enum SelectedTexture {
     Texture(TextureHandle),
     Cubemap(CubemapHandle),
+    /// A skybox created from a level's `sky_` node, along with the hashed name of the sky
+    Skybox(u32, SkyboxHandle),
 }
 
 trait SelectedTextureExt {
     fn matches_texture(&self, other: &TextureHandle) -> bool;
     fn matches_cubemap(&self, other: &CubemapHandle) -> bool;
+    fn matches_skybox(&self, other: &SkyboxHandle) -> bool;
 }
 
 impl SelectedTextureExt for Option<(SelectedTexture, ModelPreview)> {
@@ -162,4 +199,11 @@ impl SelectedTextureExt for Option<(SelectedTexture, ModelPreview)> {
             _ => false,
         }
     }
+
+    fn matches_skybox(&self, other: &SkyboxHandle) -> bool {
+        match self {
+            Some((SelectedTexture::Skybox(_, handle), _)) => handle == other,
+            _ => false,
+        }
+    }
 }
@@ -0,0 +1,41 @@
+use crate::entities::Component;
+use glam::*;
+use zenit_utils::math::Radians;
+
+/// A light source. Its position and direction are determined by the entity's
+/// `TransformComponent`, with lights pointing towards the transform's -Z axis.
+#[derive(Debug, Clone)]
+pub struct LightComponent {
+    pub kind: LightKind,
+    /// Linear RGB color, with values in range [0; 1]
+    pub color: Vec3,
+    pub cast_shadow: bool,
+    /// Static lights are baked into the level, and are never expected to move.
+    pub is_static: bool,
+}
+
+impl Component for LightComponent {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Directional,
+    Omni {
+        range: f32,
+    },
+    Spot {
+        range: f32,
+        inner_cone: Radians,
+        outer_cone: Radians,
+    },
+}
+
+/// Hemispheric ambient lighting of a scene.
+#[derive(Debug, Clone)]
+pub struct AmbientLightComponent {
+    /// Linear RGB color of light coming from above, with values in range [0; 1]
+    pub top: Vec3,
+    /// Linear RGB color of light coming from below, with values in range [0; 1]
+    pub bottom: Vec3,
+}
+
+impl Component for AmbientLightComponent {}
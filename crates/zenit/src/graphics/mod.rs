@@ -56,6 +56,10 @@ mod interface;
 pub use scene_builder::*;
 mod scene_builder;
 
+#[doc(inline)]
+pub use components::*;
+mod components;
+
 #[doc(inline)]
 pub use device_context::*;
 mod device_context;
@@ -16,6 +16,8 @@ pub struct SkyboxHandle(pub(in crate::graphics) ArcPoolHandle);
 
 pub struct SkyboxResource {
     pub label: String,
+    /// Fog of the scene this skybox belongs to. Not drawn by the renderer yet.
+    pub fog: Option<FogSettings>,
     pub(in crate::graphics) gpu_resources: Arc<Mutex<SkyboxGpuResources>>,
 }
 
@@ -26,6 +28,7 @@ impl SkyboxResource {
         // That's a lot of indents but ehhhhhhh
         Self {
             label: desc.name.clone(),
+            fog: desc.fog,
             gpu_resources: Arc::new(Mutex::new(SkyboxGpuResources {
                 background: desc.background.clone(),
                 bind_group: match &desc.background {
@@ -249,6 +252,7 @@ impl SkyboxRenderer {
 pub struct SkyboxDescriptor {
     pub name: String,
     pub background: SkyboxBackground,
+    pub fog: Option<FogSettings>,
 }
 
 #[derive(Clone)]
@@ -257,6 +261,17 @@ pub enum SkyboxBackground {
     Solid(Vec4),
 }
 
+/// Distance fog parameters of a scene.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FogSettings {
+    /// Linear RGBA color, with values in range [0; 1]
+    pub color: Vec4,
+    /// Distance at which the fog starts
+    pub near: f32,
+    /// Distance at which the fog reaches full density
+    pub far: f32,
+}
+
 /// Contents of the skybox vertex buffer.
 /// Borrowed from learnopengl.com.
 #[rustfmt::skip]
@@ -1,4 +1,8 @@
-use super::{CameraGpuResources, CameraHandle, Renderer, SkyboxGpuResources, SkyboxHandle};
+use super::{
+    AmbientLightComponent, CameraGpuResources, CameraHandle, LightComponent, Renderer,
+    SkyboxGpuResources, SkyboxHandle,
+};
+use crate::entities::{components::TransformComponent, Universe};
 use glam::Affine3A;
 use parking_lot::Mutex;
 use std::sync::Arc;
@@ -22,6 +26,32 @@ impl<'a> SceneBuilder<'a> {
         self
     }
 
+    pub fn add_light(&mut self, light: &LightComponent, transform: Affine3A) -> &mut Self {
+        self.built.lights.push((light.clone(), transform));
+        self
+    }
+
+    pub fn set_ambient_light(&mut self, ambient: &AmbientLightComponent) -> &mut Self {
+        self.built.ambient_light = Some(ambient.clone());
+        self
+    }
+
+    /// Adds every entity with a [`LightComponent`] and a [`TransformComponent`] to the scene.
+    /// The first found [`AmbientLightComponent`] is used as the scene's ambient light.
+    pub fn add_lights_from(&mut self, universe: &Universe) -> &mut Self {
+        for (entity, light) in universe.get_components::<LightComponent>() {
+            if let Some(transform) = entity.get_component::<TransformComponent>() {
+                self.add_light(light, transform.0);
+            }
+        }
+
+        if let Some((_, ambient)) = universe.get_components::<AmbientLightComponent>().next() {
+            self.set_ambient_light(ambient);
+        }
+
+        self
+    }
+
     pub fn render_to(&mut self, camera: &CameraHandle, transform: Affine3A) -> &mut Self {
         self.built.targets.push((
             self.renderer.cameras.get(camera).gpu_resources.clone(),
@@ -39,4 +69,6 @@ impl<'a> SceneBuilder<'a> {
 pub(in crate::graphics) struct BuiltScene {
     pub targets: Vec<(Arc<Mutex<CameraGpuResources>>, Affine3A)>,
     pub skybox: Option<Arc<Mutex<SkyboxGpuResources>>>,
+    pub lights: Vec<(LightComponent, Affine3A)>,
+    pub ambient_light: Option<AmbientLightComponent>,
 }
@@ -5,29 +5,40 @@
 
 use crate::{
     assets::{AssetLoader, AssetManager, GameRoot},
+    audio::{AudioBackend, AudioSystem, NullBackend, WavBackend},
     entities::Universe,
     graphics::Renderer,
     scene::{system::SceneSystem, EngineBorrow},
 };
 use clap::Parser;
 use log::*;
-use std::sync::{atomic::Ordering, Arc};
+use std::{
+    fs::File,
+    io::BufWriter,
+    sync::{atomic::Ordering, Arc},
+};
 use winit::{dpi::LogicalSize, event::*, event_loop::*, window::WindowBuilder};
 
 #[cfg(feature = "crash-handler")]
 pub mod crash;
 
 pub mod assets;
+pub mod audio;
 pub mod cli;
+pub mod collision;
 pub mod devui;
 pub mod engine;
 pub mod entities;
 pub mod graphics;
+pub mod navigation;
 pub mod platform;
 pub mod scene;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Sample rate of the audio mixer
+pub const AUDIO_SAMPLE_RATE: u32 = 44100;
+
 // TODO: move this game loop code somewhere lmao
 
 pub fn main() -> ! {
@@ -53,6 +64,8 @@ pub fn main() -> ! {
     }
 
     let game_root = GameRoot::new(args.game_root.as_ref());
+    let audio_dump = args.audio_dump.clone();
+    let hash_dictionaries = args.hash_dictionary.clone();
 
     let eloop = EventLoop::new();
     let window = Arc::new(
@@ -70,6 +83,11 @@ pub fn main() -> ! {
         let globals = builder.global_state();
         let (mut renderer, render_system) = Renderer::new(&window);
         let mut assets = AssetManager::new(game_root.clone(), &mut renderer);
+        for path in &hash_dictionaries {
+            if let Err(error) = assets.names.load_word_list(path) {
+                error!("Couldn't load hash dictionary {path:?}: {error:#?}");
+            }
+        }
         let mut universe = Universe::new();
 
         let mut engine = EngineBorrow {
@@ -83,8 +101,21 @@ pub fn main() -> ! {
             .load_builtins()
             .expect("could not load built-in assets");
 
+        let audio_backend: Box<dyn AudioBackend> = match &audio_dump {
+            Some(path) => Box::new(
+                WavBackend::new(
+                    BufWriter::new(File::create(path).expect("couldn't create audio dump")),
+                    AUDIO_SAMPLE_RATE,
+                )
+                .expect("couldn't write audio dump header"),
+            ),
+            None => Box::new(NullBackend::new(AUDIO_SAMPLE_RATE)),
+        };
+        let (mixer, audio_system) = AudioSystem::new(audio_backend);
+
         builder
             .with_system(render_system)
+            .with_system(audio_system)
             .with_system(SceneSystem::new());
 
         let gc = builder.global_state();
@@ -92,6 +123,7 @@ pub fn main() -> ! {
         gc.add_any(game_root);
         gc.add_lockable(assets);
         gc.add_lockable(renderer);
+        gc.add_lockable(mixer);
         gc.add_rw_lockable(universe);
     });
 
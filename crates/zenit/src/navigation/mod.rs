@@ -0,0 +1,312 @@
+//! AI navigation graph queries
+//!
+//! Levels store their navigation data as a graph of hubs (circular areas) linked with
+//! connections, each specifying which kinds of units can traverse it. This module wraps that
+//! data in a [`NavigationGraph`], which can find paths between hubs with A*.
+
+use glam::*;
+use std::{cmp::Ordering, collections::BinaryHeap};
+use zenit_lvl::game::{LevelPlanning, PlanConnectionFlags};
+
+/// Kind of a unit looking for a path. Every kind maps to a connection flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnitKind {
+    Soldier,
+    Hover,
+    Small,
+    Medium,
+    Huge,
+    Flyer,
+}
+
+impl UnitKind {
+    /// Returns the connection flag allowing this unit to pass.
+    pub fn connection_flag(self) -> PlanConnectionFlags {
+        match self {
+            UnitKind::Soldier => PlanConnectionFlags::SOLDIER,
+            UnitKind::Hover => PlanConnectionFlags::HOVER,
+            UnitKind::Small => PlanConnectionFlags::SMALL,
+            UnitKind::Medium => PlanConnectionFlags::MEDIUM,
+            UnitKind::Huge => PlanConnectionFlags::HUGE,
+            UnitKind::Flyer => PlanConnectionFlags::FLYER,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NavigationHub {
+    pub name: String,
+    pub position: Vec3A,
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct NavigationEdge {
+    /// Index of the hub this edge leads to
+    pub target: usize,
+    /// Distance between both hubs' centers
+    pub cost: f32,
+    pub flags: PlanConnectionFlags,
+}
+
+/// A path found by [`NavigationGraph::find_path`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigationPath {
+    /// Indices of traversed hubs, including the start and the goal
+    pub hubs: Vec<usize>,
+    /// Total length of the path, measured between hub centers
+    pub cost: f32,
+}
+
+/// Navigation graph of a level, built from a `plan` node.
+#[derive(Debug, Clone, Default)]
+pub struct NavigationGraph {
+    hubs: Vec<NavigationHub>,
+    /// Adjacency lists, indexed by hub
+    edges: Vec<Vec<NavigationEdge>>,
+}
+
+impl From<&LevelPlanning> for NavigationGraph {
+    fn from(planning: &LevelPlanning) -> Self {
+        let mut graph = Self::default();
+        for hub in &planning.hubs {
+            graph.add_hub(hub.name.clone(), hub.position.into(), hub.radius);
+        }
+        for connection in &planning.connections {
+            graph.connect(
+                connection.start as usize,
+                connection.end as usize,
+                connection.flags,
+            );
+        }
+        graph
+    }
+}
+
+impl NavigationGraph {
+    /// Adds a new hub and returns its index.
+    pub fn add_hub(&mut self, name: String, position: Vec3A, radius: f32) -> usize {
+        self.hubs.push(NavigationHub {
+            name,
+            position,
+            radius,
+        });
+        self.edges.push(Vec::new());
+        self.hubs.len() - 1
+    }
+
+    /// Connects two hubs both ways.
+    ///
+    /// ## Panics
+    /// Panics if either index is out of bounds.
+    pub fn connect(&mut self, a: usize, b: usize, flags: PlanConnectionFlags) {
+        let cost = self.hubs[a].position.distance(self.hubs[b].position);
+        self.edges[a].push(NavigationEdge {
+            target: b,
+            cost,
+            flags,
+        });
+        self.edges[b].push(NavigationEdge {
+            target: a,
+            cost,
+            flags,
+        });
+    }
+
+    pub fn hubs(&self) -> &[NavigationHub] {
+        &self.hubs
+    }
+
+    /// Returns all edges leaving the given hub.
+    pub fn edges(&self, hub: usize) -> &[NavigationEdge] {
+        &self.edges[hub]
+    }
+
+    /// Finds a hub by its name.
+    pub fn find_hub(&self, name: &str) -> Option<usize> {
+        self.hubs.iter().position(|hub| hub.name == name)
+    }
+
+    /// Returns the hub closest to the given position, preferring hubs the position is within.
+    pub fn nearest_hub(&self, position: Vec3A) -> Option<usize> {
+        self.hubs
+            .iter()
+            .enumerate()
+            .map(|(index, hub)| {
+                let distance = (hub.position.distance(position) - hub.radius).max(0.0);
+                (index, distance)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+
+    /// Finds the shortest path between two hubs, only using connections passable by the
+    /// given unit kind. Uses A* with the straight line distance as the heuristic.
+    ///
+    /// ## Panics
+    /// Panics if either index is out of bounds.
+    pub fn find_path(&self, start: usize, goal: usize, unit: UnitKind) -> Option<NavigationPath> {
+        let filter = unit.connection_flag();
+        let goal_position = self.hubs[goal].position;
+        let heuristic = |hub: usize| self.hubs[hub].position.distance(goal_position);
+
+        let mut costs = vec![f32::INFINITY; self.hubs.len()];
+        let mut previous = vec![None; self.hubs.len()];
+        let mut open = BinaryHeap::new();
+
+        costs[start] = 0.0;
+        open.push(OpenHub {
+            estimate: heuristic(start),
+            hub: start,
+        });
+
+        while let Some(OpenHub { estimate, hub }) = open.pop() {
+            if hub == goal {
+                let mut hubs = vec![goal];
+                let mut current = goal;
+                while let Some(prev) = previous[current] {
+                    hubs.push(prev);
+                    current = prev;
+                }
+                hubs.reverse();
+
+                return Some(NavigationPath {
+                    hubs,
+                    cost: costs[goal],
+                });
+            }
+
+            // Skip stale heap entries
+            if estimate > costs[hub] + heuristic(hub) {
+                continue;
+            }
+
+            for edge in self.edges[hub].iter().filter(|e| e.flags.contains(filter)) {
+                let cost = costs[hub] + edge.cost;
+                if cost < costs[edge.target] {
+                    costs[edge.target] = cost;
+                    previous[edge.target] = Some(hub);
+                    open.push(OpenHub {
+                        estimate: cost + heuristic(edge.target),
+                        hub: edge.target,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Entry in the A* open set, ordered so that [`BinaryHeap`] pops the lowest estimate first.
+#[derive(Debug, Clone, Copy)]
+struct OpenHub {
+    estimate: f32,
+    hub: usize,
+}
+
+impl PartialEq for OpenHub {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenHub {}
+
+impl PartialOrd for OpenHub {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenHub {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenit_lvl::game::{PlanConnection, PlanHub};
+
+    /// A square of hubs, where the direct route (0 -> 1 -> 2) is soldier-only, and the long
+    /// way around (0 -> 3 -> 4 -> 2) is open to everyone.
+    fn square_graph() -> NavigationGraph {
+        let hub = |name: &str, position: [f32; 3]| PlanHub {
+            name: name.to_string(),
+            position,
+            radius: 1.0,
+            extra: vec![],
+        };
+        let connection = |start, end, flags| PlanConnection {
+            name: format!("{start}-{end}"),
+            start,
+            end,
+            flags,
+            extra: vec![],
+        };
+
+        let everyone = PlanConnectionFlags::all();
+        let soldier = PlanConnectionFlags::SOLDIER;
+
+        NavigationGraph::from(&LevelPlanning {
+            hubs: vec![
+                hub("a", [0.0, 0.0, 0.0]),
+                hub("b", [10.0, 0.0, 0.0]),
+                hub("c", [20.0, 0.0, 0.0]),
+                hub("d", [0.0, 0.0, 10.0]),
+                hub("e", [20.0, 0.0, 10.0]),
+                hub("island", [100.0, 0.0, 100.0]),
+            ],
+            connections: vec![
+                connection(0, 1, soldier),
+                connection(1, 2, soldier),
+                connection(0, 3, everyone),
+                connection(3, 4, everyone),
+                connection(4, 2, everyone),
+            ],
+        })
+    }
+
+    #[test]
+    fn soldiers_take_the_shortest_path() {
+        let graph = square_graph();
+        let path = graph.find_path(0, 2, UnitKind::Soldier).unwrap();
+        assert_eq!(path.hubs, vec![0, 1, 2]);
+        assert!((path.cost - 20.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn vehicles_respect_connection_flags() {
+        let graph = square_graph();
+        let path = graph.find_path(0, 2, UnitKind::Hover).unwrap();
+        assert_eq!(path.hubs, vec![0, 3, 4, 2]);
+        assert!((path.cost - 40.0).abs() < 1e-4);
+
+        // Reverse direction works too
+        let path = graph.find_path(2, 0, UnitKind::Huge).unwrap();
+        assert_eq!(path.hubs, vec![2, 4, 3, 0]);
+    }
+
+    #[test]
+    fn unreachable_hubs_have_no_path() {
+        let graph = square_graph();
+        let island = graph.find_hub("island").unwrap();
+        assert!(graph.find_path(0, island, UnitKind::Soldier).is_none());
+        assert_eq!(
+            graph.find_path(island, island, UnitKind::Soldier),
+            Some(NavigationPath {
+                hubs: vec![island],
+                cost: 0.0
+            })
+        );
+    }
+
+    #[test]
+    fn nearest_hub_accounts_for_radius() {
+        let graph = square_graph();
+        assert_eq!(graph.nearest_hub(vec3a(9.5, 0.0, 0.5)), Some(1));
+        assert_eq!(graph.nearest_hub(vec3a(90.0, 0.0, 90.0)), Some(5));
+    }
+}
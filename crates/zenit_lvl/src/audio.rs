@@ -0,0 +1,428 @@
+//! Decoders for audio encodings used in level files
+//!
+//! All decoders output signed 16-bit PCM with interleaved channels.
+
+use crate::{
+    game::{LevelSoundSampleInfo, LevelStreamInfo, LevelStreamSegment, SoundFormat},
+    node::LazyData,
+};
+use anyhow::{bail, ensure};
+use std::io::{Read, Seek, SeekFrom};
+use zenit_utils::{ok, AnyResult};
+
+/// Step sizes of the IMA ADPCM algorithm
+const IMA_STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// Step index adjustments, indexed by the nibble's magnitude bits
+const IMA_INDEX_TABLE: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+/// Amount of frames decoded at once from PCM streams
+const PCM_FRAMES_PER_CHUNK: usize = 4096;
+
+/// Decodes a whole sample into interleaved 16-bit PCM.
+pub fn decode_samples(info: &LevelSoundSampleInfo, data: &[u8]) -> AnyResult<Vec<i16>> {
+    let mut output = Vec::with_capacity(info.frame_count as usize * info.channels as usize);
+    match info.format {
+        SoundFormat::Pcm16 => decode_pcm16(data, &mut output)?,
+        SoundFormat::ImaAdpcm => {
+            let mut decoder = ImaAdpcmDecoder::new(info.channels, info.block_align)?;
+            for block in data.chunks(info.block_align as usize) {
+                decoder.decode_block(block, &mut output)?;
+            }
+        }
+    }
+
+    // ADPCM blocks are always decoded whole, so the last one may contain padding
+    output.truncate(info.frame_count as usize * info.channels as usize);
+    Ok(output)
+}
+
+/// Decodes little endian 16-bit PCM, appending it to the output.
+pub fn decode_pcm16(data: &[u8], output: &mut Vec<i16>) -> AnyResult {
+    ensure!(data.len().is_multiple_of(2), "PCM16 data has an odd length");
+    output.extend(
+        data.chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]])),
+    );
+    ok()
+}
+
+/// Block-based IMA ADPCM decoder, compatible with the layout used in WAV files.
+///
+/// Every block starts with a 4-byte header for each channel (initial sample and step index),
+/// followed by interleaved 4-byte groups of nibbles (8 samples) per channel. Blocks are
+/// independent of each other, which allows decoding them one at a time, as they're streamed in.
+#[derive(Debug, Clone)]
+pub struct ImaAdpcmDecoder {
+    channels: usize,
+    block_align: usize,
+    /// Scratch buffer for deinterleaving channels
+    scratch: Vec<Vec<i16>>,
+}
+
+impl ImaAdpcmDecoder {
+    pub fn new(channels: u16, block_align: u16) -> AnyResult<Self> {
+        let channels = channels as usize;
+        let block_align = block_align as usize;
+        ensure!(channels > 0, "ADPCM data must have at least one channel");
+        ensure!(
+            block_align > 4 * channels && (block_align - 4 * channels).is_multiple_of(4 * channels),
+            "invalid ADPCM block alignment ({block_align} for {channels} channels)"
+        );
+
+        Ok(Self {
+            channels,
+            block_align,
+            scratch: vec![Vec::new(); channels],
+        })
+    }
+
+    /// Amount of samples per channel in a full block.
+    pub fn frames_per_block(&self) -> usize {
+        (self.block_align - 4 * self.channels) * 2 / self.channels + 1
+    }
+
+    pub fn block_align(&self) -> usize {
+        self.block_align
+    }
+
+    /// Decodes a single block, appending interleaved samples to the output. The block may be
+    /// shorter than the block alignment, which happens at the end of a stream.
+    pub fn decode_block(&mut self, block: &[u8], output: &mut Vec<i16>) -> AnyResult {
+        let channels = self.channels;
+        ensure!(block.len() <= self.block_align, "ADPCM block is too large");
+        ensure!(block.len() >= 4 * channels, "ADPCM block is too small");
+
+        let mut states = Vec::with_capacity(channels);
+        for (channel, header) in block.chunks_exact(4).take(channels).enumerate() {
+            let predictor = i16::from_le_bytes([header[0], header[1]]) as i32;
+            let step_index = (header[2] as i32).clamp(0, 88);
+            self.scratch[channel].clear();
+            self.scratch[channel].push(predictor as i16);
+            states.push((predictor, step_index));
+        }
+
+        // Incomplete groups at the end of a truncated block are ignored
+        let body = &block[4 * channels..];
+        for (index, group) in body.chunks_exact(4).enumerate() {
+            let channel = index % channels;
+            let (predictor, step_index) = &mut states[channel];
+            for byte in group {
+                for nibble in [byte & 0x0f, byte >> 4] {
+                    let sample = decode_nibble(nibble, predictor, step_index);
+                    self.scratch[channel].push(sample);
+                }
+            }
+        }
+
+        let frames = self.scratch.iter().map(Vec::len).min().unwrap_or(0);
+        output.reserve(frames * channels);
+        for frame in 0..frames {
+            for channel in &self.scratch {
+                output.push(channel[frame]);
+            }
+        }
+
+        ok()
+    }
+}
+
+fn decode_nibble(nibble: u8, predictor: &mut i32, step_index: &mut i32) -> i16 {
+    let step = IMA_STEP_TABLE[*step_index as usize];
+    let nibble = nibble as i32;
+
+    let mut difference = step >> 3;
+    if nibble & 1 != 0 {
+        difference += step >> 2;
+    }
+    if nibble & 2 != 0 {
+        difference += step >> 1;
+    }
+    if nibble & 4 != 0 {
+        difference += step;
+    }
+    if nibble & 8 != 0 {
+        difference = -difference;
+    }
+
+    *predictor = (*predictor + difference).clamp(i16::MIN as i32, i16::MAX as i32);
+    *step_index = (*step_index + IMA_INDEX_TABLE[(nibble & 7) as usize]).clamp(0, 88);
+    *predictor as i16
+}
+
+/// Incremental decoder of a single stream segment.
+///
+/// Only a single ADPCM block (or a few thousand PCM frames) is kept in memory at once, so even
+/// long music tracks can be decoded as they're played.
+pub struct StreamDecoder<R: Read + Seek> {
+    reader: R,
+    channels: usize,
+    adpcm: Option<ImaAdpcmDecoder>,
+    /// Position of the segment's data in the reader
+    data_start: u64,
+    data_size: u64,
+    /// Read position within the segment's data
+    data_position: u64,
+    frame_count: u64,
+    frames_decoded: u64,
+    /// Frames to drop from the next chunk, used when seeking into the middle of an ADPCM block
+    frames_to_skip: usize,
+    buffer: Vec<u8>,
+    decoded: Vec<i16>,
+}
+
+impl<R: Read + Seek> StreamDecoder<R> {
+    pub fn new(reader: R, info: &LevelStreamInfo, segment: &LevelStreamSegment) -> AnyResult<Self> {
+        let LazyData::Read(header) = &segment.data else {
+            bail!("segment data must be read from a stream file");
+        };
+        ensure!(info.channels > 0, "streams must have at least one channel");
+
+        let adpcm = match info.format {
+            SoundFormat::Pcm16 => None,
+            SoundFormat::ImaAdpcm => Some(ImaAdpcmDecoder::new(info.channels, info.block_align)?),
+        };
+
+        Ok(Self {
+            reader,
+            channels: info.channels as usize,
+            adpcm,
+            data_start: header.header_position + 8,
+            data_size: header.size as u64,
+            data_position: 0,
+            frame_count: segment.info.frame_count as u64,
+            frames_decoded: 0,
+            frames_to_skip: 0,
+            buffer: Vec::new(),
+            decoded: Vec::new(),
+        })
+    }
+
+    /// Total amount of frames in the segment.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Amount of frames left to decode.
+    pub fn remaining_frames(&self) -> u64 {
+        self.frame_count - self.frames_decoded
+    }
+
+    /// Moves the decoder to the specified frame. Seeking past the end finishes the stream.
+    pub fn seek_to_frame(&mut self, frame: u64) {
+        let frame = frame.min(self.frame_count);
+        match &self.adpcm {
+            Some(adpcm) => {
+                let frames_per_block = adpcm.frames_per_block() as u64;
+                let block = frame / frames_per_block;
+                self.data_position = block * adpcm.block_align() as u64;
+                self.frames_to_skip = (frame % frames_per_block) as usize;
+            }
+            None => {
+                self.data_position = frame * self.channels as u64 * 2;
+                self.frames_to_skip = 0;
+            }
+        }
+        self.frames_decoded = frame;
+    }
+
+    /// Decodes the next chunk of the stream, appending interleaved samples to the output.
+    /// Returns the amount of decoded frames, which is 0 once the stream ends.
+    pub fn decode_next(&mut self, output: &mut Vec<i16>) -> AnyResult<usize> {
+        let remaining_data = self.data_size.saturating_sub(self.data_position);
+        if self.remaining_frames() == 0 || remaining_data == 0 {
+            return Ok(0);
+        }
+
+        let chunk_size = match &self.adpcm {
+            Some(adpcm) => adpcm.block_align(),
+            None => PCM_FRAMES_PER_CHUNK * self.channels * 2,
+        };
+        let chunk_size = (chunk_size as u64).min(remaining_data) as usize;
+
+        self.buffer.resize(chunk_size, 0);
+        self.reader
+            .seek(SeekFrom::Start(self.data_start + self.data_position))?;
+        self.reader.read_exact(&mut self.buffer)?;
+        self.data_position += chunk_size as u64;
+
+        self.decoded.clear();
+        match &mut self.adpcm {
+            Some(adpcm) => adpcm.decode_block(&self.buffer, &mut self.decoded)?,
+            None => decode_pcm16(&self.buffer, &mut self.decoded)?,
+        }
+
+        let skipped = (self.frames_to_skip * self.channels).min(self.decoded.len());
+        self.frames_to_skip = 0;
+
+        let frames =
+            ((self.decoded.len() - skipped) / self.channels).min(self.remaining_frames() as usize);
+        output.extend_from_slice(&self.decoded[skipped..skipped + frames * self.channels]);
+        self.frames_decoded += frames as u64;
+
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::LevelStreamSegmentInfo;
+    use crate::node::{NodeHeader, NodeName};
+    use std::io::Cursor;
+
+    fn sample_info(
+        format: SoundFormat,
+        channels: u16,
+        block_align: u16,
+        frame_count: u32,
+    ) -> LevelSoundSampleInfo {
+        LevelSoundSampleInfo {
+            name_hash: 0,
+            format,
+            sample_rate: 22050,
+            channels,
+            block_align,
+            frame_count,
+        }
+    }
+
+    #[test]
+    fn pcm16_samples() {
+        let info = sample_info(SoundFormat::Pcm16, 2, 4, 2);
+        let data = [0x01, 0x00, 0xff, 0xff, 0x00, 0x80, 0xff, 0x7f];
+        assert_eq!(
+            decode_samples(&info, &data).unwrap(),
+            [1, -1, i16::MIN, i16::MAX]
+        );
+
+        assert!(decode_samples(&info, &data[..3]).is_err());
+    }
+
+    #[test]
+    fn ima_adpcm_samples() {
+        // Header: initial sample 100, step index 0, followed by 8 nibbles
+        let block = [0x64, 0x00, 0x00, 0x00, 0x77, 0x07, 0x80, 0xf1];
+        let expected = [100, 111, 141, 204, 213, 221, 214, 233, 140];
+
+        let info = sample_info(SoundFormat::ImaAdpcm, 1, 8, 9);
+        assert_eq!(decode_samples(&info, &block).unwrap(), expected);
+
+        // Padding at the end of the last block is dropped
+        let info = sample_info(SoundFormat::ImaAdpcm, 1, 8, 15);
+        let samples = decode_samples(&info, &[block, block].concat()).unwrap();
+        assert_eq!(samples, [&expected[..], &expected[..6]].concat());
+    }
+
+    #[test]
+    fn ima_adpcm_stereo_samples() {
+        // Left: initial sample 100, step index 0; right: initial sample -100, step index 10
+        let block = [
+            0x64, 0x00, 0x00, 0x00, 0x9c, 0xff, 0x0a, 0x00, // headers
+            0x74, 0x00, 0x00, 0x00, // left
+            0x0c, 0x33, 0x21, 0x98, // right
+        ];
+        let left = [100, 107, 123, 125, 127, 129, 130, 131, 132];
+        let right = [-100, -121, -119, -102, -87, -81, -71, -72, -76];
+
+        let info = sample_info(SoundFormat::ImaAdpcm, 2, 16, 9);
+        let samples = decode_samples(&info, &block).unwrap();
+        let expected: Vec<i16> = left
+            .into_iter()
+            .zip(right)
+            .flat_map(|(l, r)| [l, r])
+            .collect();
+        assert_eq!(samples, expected);
+
+        assert!(ImaAdpcmDecoder::new(2, 12).is_err());
+        assert!(ImaAdpcmDecoder::new(0, 8).is_err());
+    }
+
+    /// Decodes the whole segment with a [`StreamDecoder`], optionally seeking first.
+    fn decode_stream(format: SoundFormat, data: &[u8], frame_count: u32, seek: u64) -> Vec<i16> {
+        let info = LevelStreamInfo {
+            name_hash: 0,
+            format,
+            sample_rate: 22050,
+            channels: 2,
+            block_align: 32,
+        };
+        let segment = LevelStreamSegment {
+            info: LevelStreamSegmentInfo {
+                name_hash: 0,
+                frame_count,
+            },
+            data: LazyData::Read(NodeHeader {
+                header_position: 0,
+                name: NodeName::from_str("DATA"),
+                size: data.len() as u32,
+            }),
+        };
+
+        let mut file = b"DATA".to_vec();
+        file.extend((data.len() as u32).to_le_bytes());
+        file.extend(data);
+
+        let mut decoder = StreamDecoder::new(Cursor::new(file), &info, &segment).unwrap();
+        decoder.seek_to_frame(seek);
+
+        let mut output = Vec::new();
+        while decoder.decode_next(&mut output).unwrap() > 0 {}
+        assert_eq!(decoder.remaining_frames(), 0);
+        output
+    }
+
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x1234_5678u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stream_adpcm_matches_whole_segment() {
+        // Two full blocks of 17 frames, followed by a block cut off after its first group
+        let mut data = noise(32 * 2 + 24);
+        for header in data.chunks_mut(32) {
+            header[2] %= 89;
+            header[6] %= 89;
+        }
+        let frame_count = 17 * 2 + 9;
+
+        let info = sample_info(SoundFormat::ImaAdpcm, 2, 32, frame_count);
+        let whole = decode_samples(&info, &data).unwrap();
+        assert_eq!(whole.len(), frame_count as usize * 2);
+
+        let streamed = decode_stream(SoundFormat::ImaAdpcm, &data, frame_count, 0);
+        assert_eq!(streamed, whole);
+
+        // Seeking into the middle of a block skips the frames before it
+        let streamed = decode_stream(SoundFormat::ImaAdpcm, &data, frame_count, 20);
+        assert_eq!(streamed, whole[20 * 2..]);
+    }
+
+    #[test]
+    fn stream_pcm16_matches_whole_segment() {
+        // Spans multiple chunks, with the last one only partially filled
+        let frame_count = PCM_FRAMES_PER_CHUNK as u32 * 2 + 100;
+        let data = noise(frame_count as usize * 4);
+
+        let info = sample_info(SoundFormat::Pcm16, 2, 4, frame_count);
+        let whole = decode_samples(&info, &data).unwrap();
+
+        let streamed = decode_stream(SoundFormat::Pcm16, &data, frame_count, 0);
+        assert_eq!(streamed, whole);
+
+        let streamed = decode_stream(SoundFormat::Pcm16, &data, frame_count, 5000);
+        assert_eq!(streamed, whole[5000 * 2..]);
+    }
+}
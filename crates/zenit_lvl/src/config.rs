@@ -104,7 +104,9 @@
 //! Does that make sense? Hopefully :3
 //!
 
-use crate::node::{read_node_children, NodeHeader, NodeRead, NodeWrite, NodeWriter};
+use crate::node::{
+    read_node_children, NodeData, NodeHeader, NodeRead, NodeResultExt, NodeWrite, NodeWriter,
+};
 use anyhow::{bail, ensure};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use std::{
@@ -128,7 +130,7 @@ impl NodeRead for LevelConfig {
     fn read_node_payload<R: Read + Seek>(r: &mut R, meta: NodeHeader) -> AnyResult<Self> {
         let mut name_hash = None;
         let raw_children = read_node_children(r, meta)?;
-        let mut children = Vec::with_capacity(raw_children.len() - 1);
+        let mut children = Vec::with_capacity(raw_children.len().saturating_sub(1));
 
         for child in raw_children {
             if child.name == b"NAME" {
@@ -136,15 +138,8 @@ impl NodeRead for LevelConfig {
                 ensure!(child.size == 4, "invalid config node name");
                 child.seek_to_payload(r)?;
                 name_hash = Some(r.read_u32::<LE>()?);
-            } else if child.name == b"DATA" {
-                children.push(ConfigExpr::Data(ConfigData::read_node_at(r, meta)?));
-            } else if child.name == b"SCOP" {
-                children.push(ConfigExpr::Scope(ConfigScope::read_node_at(r, meta)?));
             } else {
-                bail!(
-                    "unexpected config node: `{}`",
-                    AsciiDisplay(child.name.as_ref())
-                );
+                children.push(ConfigExpr::read_expr(r, child)?);
             }
         }
 
@@ -167,34 +162,34 @@ impl NodeWrite for LevelConfig {
 }
 
 /// Represents a config expression, either data or a scope.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, NodeData)]
 pub enum ConfigExpr {
+    #[node("DATA")]
     Data(ConfigData),
+    #[node("SCOP")]
     Scope(ConfigScope),
 }
 
-impl NodeRead for ConfigExpr {
-    fn read_node_payload<R: Read + Seek>(r: &mut R, meta: NodeHeader) -> AnyResult<Self> {
-        if meta.name == b"DATA" {
-            Ok(Self::Data(ConfigData::read_node_payload(r, meta)?))
-        } else if meta.name == b"SCOP" {
-            Ok(Self::Scope(ConfigScope::read_node_payload(r, meta)?))
+impl ConfigExpr {
+    /// Reads an expression directly from its `DATA` or `SCOP` node. Config nodes and scopes
+    /// list their expressions as is, instead of wrapping each one in another node like the
+    /// derived [`NodeRead`] implementation expects.
+    fn read_expr<R: Read + Seek>(r: &mut R, node: NodeHeader) -> AnyResult<Self> {
+        // Scopes nest recursively, so `read_node_at` can't be used here, as every level
+        // would wrap the reader in yet another `SeekableTake`, which the compiler can't
+        // monomorphize.
+        node.seek_to_payload(r)?;
+        if node.name == b"DATA" {
+            ConfigData::read_node_payload(r, node).map(ConfigExpr::Data)
+        } else if node.name == b"SCOP" {
+            ConfigScope::read_node_payload(r, node).map(ConfigExpr::Scope)
         } else {
             bail!(
-                "unexpected config scope node: `{}`",
-                AsciiDisplay(meta.name.as_ref())
-            )
-        }
-    }
-}
-
-impl NodeWrite for ConfigExpr {
-    fn write_node<W: Write + Seek>(&self, writer: &mut NodeWriter<W>) -> AnyResult {
-        // TODO: get rid of the clone once PackedNode is spread into separate Read/Write traits
-        match self.clone() {
-            ConfigExpr::Data(data) => writer.write_node(b"DATA", data),
-            ConfigExpr::Scope(scope) => writer.write_node(b"SCOP", scope),
+                "unexpected config node: `{}`",
+                AsciiDisplay(node.name.as_ref())
+            );
         }
+        .at_node(node)
     }
 }
 
@@ -203,12 +198,60 @@ pub struct ConfigScope {
     pub children: Vec<ConfigExpr>,
 }
 
+impl ConfigScope {
+    /// Iterates over all data expressions directly within this scope.
+    pub fn iter_data(&self) -> impl Iterator<Item = &ConfigData> {
+        self.children.iter().filter_map(|child| match child {
+            ConfigExpr::Data(data) => Some(data),
+            ConfigExpr::Scope(_) => None,
+        })
+    }
+
+    /// Iterates over all data expressions directly within this scope, alongside the scopes
+    /// that immediately follow them (if any).
+    ///
+    /// In other words, for a config like `Spawner() { ... } MaxParticles(5)`, this yields
+    /// `(Spawner, Some({ ... }))` and `(MaxParticles, None)`.
+    pub fn iter_scoped(&self) -> impl Iterator<Item = (&ConfigData, Option<&ConfigScope>)> {
+        self.children
+            .iter()
+            .enumerate()
+            .filter_map(|(index, child)| match child {
+                ConfigExpr::Data(data) => Some((
+                    data,
+                    match self.children.get(index + 1) {
+                        Some(ConfigExpr::Scope(scope)) => Some(scope),
+                        _ => None,
+                    },
+                )),
+                ConfigExpr::Scope(_) => None,
+            })
+    }
+
+    /// Returns the first data expression with the given name hash.
+    pub fn find_data(&self, name_hash: u32) -> Option<&ConfigData> {
+        self.iter_data().find(|data| data.name_hash == name_hash)
+    }
+
+    /// Reads `N` float values of the first data expression with the given name hash.
+    pub fn find_floats<const N: usize>(&self, name_hash: u32) -> Option<[f32; N]> {
+        self.find_data(name_hash)?.get_floats()
+    }
+
+    /// Returns the scope following the first data expression with the given name hash.
+    pub fn find_scope(&self, name_hash: u32) -> Option<&ConfigScope> {
+        self.iter_scoped()
+            .find(|(data, _)| data.name_hash == name_hash)
+            .and_then(|(_, scope)| scope)
+    }
+}
+
 impl NodeRead for ConfigScope {
     fn read_node_payload<R: Read + Seek>(r: &mut R, meta: NodeHeader) -> AnyResult<Self> {
         Ok(Self {
             children: read_node_children(r, meta)?
                 .into_iter()
-                .map(|child| ConfigExpr::read_node_at(r, child))
+                .map(|child| ConfigExpr::read_expr(r, child))
                 .collect::<AnyResult<_>>()?,
         })
     }
@@ -240,6 +283,20 @@ impl ConfigData {
         T::get(self, idx)
     }
 
+    /// Reads a string value and converts it into a [`String`].
+    pub fn get_string(&self, idx: u32) -> Option<String> {
+        self.get::<CString>(idx)?.into_string().ok()
+    }
+
+    /// Reads `N` consecutive float values, starting at index 0.
+    pub fn get_floats<const N: usize>(&self) -> Option<[f32; N]> {
+        let mut result = [0.0; N];
+        for (index, value) in result.iter_mut().enumerate() {
+            *value = self.get(index as u32)?;
+        }
+        Some(result)
+    }
+
     /// Calculates the amount of bytes in the header before the tail.
     pub fn size_before_tail(&self) -> usize {
         // sizeof(name_hash) + sizeof(value_count) + sizeof(tail_length)
@@ -321,10 +378,10 @@ impl ConfigValue for CString {
         }
         let in_tail_offset = absolute_offset - header_size;
 
-        // Convert it to a string, and verify the presence of a nul byte at the end.
-        // (Hopefully no code depends on a different string layout)
+        // Convert it to a string, reading until the first nul byte. The tail may contain several
+        // strings one after another, if the expression has multiple string parameters.
         Some(
-            CStr::from_bytes_with_nul(&data.tail[in_tail_offset..])
+            CStr::from_bytes_until_nul(data.tail.get(in_tail_offset..)?)
                 .ok()?
                 .into(),
         )
@@ -339,3 +396,43 @@ impl ConfigValue for f32 {
         })
     }
 }
+
+/// Builders of config trees, for testing decoders of specific config nodes.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+    use zenit_utils::fnv1a_hash;
+
+    /// A property with float values.
+    pub fn floats(name: &str, values: &[f32]) -> ConfigExpr {
+        ConfigExpr::Data(ConfigData {
+            name_hash: fnv1a_hash(name.as_bytes()),
+            values: values.iter().map(|value| value.to_bits()).collect(),
+            tail: vec![],
+        })
+    }
+
+    /// A property with a single string value.
+    pub fn string(name: &str, value: &str) -> ConfigExpr {
+        // String values are offsets into the `DATA` chunk, minus 9
+        let mut data = ConfigData {
+            name_hash: fnv1a_hash(name.as_bytes()),
+            values: vec![0],
+            tail: format!("{value}\0").into_bytes(),
+        };
+        data.values[0] = (data.size_before_tail() - 9) as u32;
+        ConfigExpr::Data(data)
+    }
+
+    /// A scope, which belongs to the property right before it.
+    pub fn scope(children: Vec<ConfigExpr>) -> ConfigExpr {
+        ConfigExpr::Scope(ConfigScope { children })
+    }
+
+    pub fn config(name: &str, children: Vec<ConfigExpr>) -> LevelConfig {
+        LevelConfig {
+            name_hash: fnv1a_hash(name.as_bytes()),
+            root: ConfigScope { children },
+        }
+    }
+}
@@ -0,0 +1,524 @@
+use crate::node::{read_node_children, NodeHeader, NodeRead, NodeWrite, NodeWriter};
+use anyhow::{anyhow, bail, ensure};
+use bitflags::bitflags;
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::{
+    ffi::CString,
+    io::{Read, Seek, Write},
+};
+use zenit_proc::PackedData;
+use zenit_utils::{ok, packed::PackedData, AnyResult, AsciiDisplay};
+
+bitflags! {
+    /// Specifies which kinds of objects a collision shape collides with.
+    #[derive(Default)]
+    pub struct CollisionMask: u32 {
+        const SOLDIER = 1 << 0;
+        const VEHICLE = 1 << 1;
+        const BUILDING = 1 << 2;
+        const TERRAIN = 1 << 3;
+        const ORDNANCE = 1 << 4;
+        const FLYER = 1 << 5;
+    }
+}
+
+impl PackedData for CollisionMask {
+    fn read_packed<R: Read>(r: &mut R) -> AnyResult<Self> {
+        Ok(Self::from_bits_truncate(r.read_u32::<LE>()?))
+    }
+
+    fn write_packed<W: Write>(&self, w: &mut W) -> AnyResult {
+        w.write_u32::<LE>(self.bits())?;
+        ok()
+    }
+}
+
+/// Collision primitives of a model, stored in `prim` nodes.
+///
+/// Each primitive is stored as a sequence of `NAME`, `MASK` (optional), `PRNT`, `XFRM` and `DATA`
+/// nodes, directly within the `prim` node, so the structure can't be derived.
+#[derive(Debug, Clone)]
+pub struct LevelCollisionPrimitives {
+    /// Proceed with caution, its contents aren't really known. Likely includes the primitive
+    /// count and the model name.
+    pub info: Vec<u8>,
+    pub primitives: Vec<LevelCollisionPrimitive>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelCollisionPrimitive {
+    pub name: CString,
+    pub mask: Option<CollisionMask>,
+    /// Name of the model node the primitive is attached to
+    pub parent: CString,
+    pub transform: CollisionTransform,
+    pub shape: CollisionShape,
+}
+
+/// Transform of a collision shape, relative to its parent node.
+#[derive(Debug, Clone, PackedData)]
+pub struct CollisionTransform {
+    /// Rows of a 3x3 rotation matrix
+    pub rotation: [[f32; 3]; 3],
+    pub position: [f32; 3],
+}
+
+/// Primitive collision shape, stored in `prim:DATA` as a `u32` shape kind followed by 3 floats.
+///
+/// The values seem to be half-sizes, centered around the primitive's transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollisionShape {
+    Sphere { radius: f32 },
+    Cylinder { radius: f32, half_height: f32 },
+    Cube { half_extents: [f32; 3] },
+}
+
+impl PackedData for CollisionShape {
+    fn read_packed<R: Read>(r: &mut R) -> AnyResult<Self> {
+        let kind = r.read_u32::<LE>()?;
+        let [x, y, z] = <[f32; 3]>::read_packed(r)?;
+        Ok(match kind {
+            1 => Self::Sphere { radius: x },
+            2 => Self::Cylinder {
+                radius: x,
+                half_height: y,
+            },
+            4 => Self::Cube {
+                half_extents: [x, y, z],
+            },
+            _ => bail!("unknown collision primitive kind: {kind}"),
+        })
+    }
+
+    fn write_packed<W: Write>(&self, w: &mut W) -> AnyResult {
+        let (kind, values) = match *self {
+            Self::Sphere { radius } => (1, [radius, 0.0, 0.0]),
+            Self::Cylinder {
+                radius,
+                half_height,
+            } => (2, [radius, half_height, 0.0]),
+            Self::Cube { half_extents } => (4, half_extents),
+        };
+        w.write_u32::<LE>(kind)?;
+        values.write_packed(w)?;
+        ok()
+    }
+}
+
+impl NodeRead for LevelCollisionPrimitives {
+    fn read_node_payload<R: Read + Seek>(r: &mut R, meta: NodeHeader) -> AnyResult<Self> {
+        /// A primitive in the process of being read
+        struct Partial {
+            name: CString,
+            mask: Option<CollisionMask>,
+            parent: Option<CString>,
+            transform: Option<CollisionTransform>,
+            shape: Option<CollisionShape>,
+        }
+
+        let mut info = None;
+        let mut partials: Vec<Partial> = Vec::new();
+
+        for child in read_node_children(r, meta)? {
+            if child.name == b"INFO" {
+                ensure!(info.is_none(), "duplicate primitive info");
+                info = Some(Vec::<u8>::read_node_at(r, child)?);
+                continue;
+            }
+
+            if child.name == b"NAME" {
+                partials.push(Partial {
+                    name: CString::read_node_at(r, child)?,
+                    mask: None,
+                    parent: None,
+                    transform: None,
+                    shape: None,
+                });
+                continue;
+            }
+
+            let Some(current) = partials.last_mut() else {
+                bail!("primitive data before its name");
+            };
+
+            if child.name == b"MASK" {
+                current.mask = Some(CollisionMask::read_node_at(r, child)?);
+            } else if child.name == b"PRNT" {
+                current.parent = Some(CString::read_node_at(r, child)?);
+            } else if child.name == b"XFRM" {
+                current.transform = Some(CollisionTransform::read_node_at(r, child)?);
+            } else if child.name == b"DATA" {
+                current.shape = Some(CollisionShape::read_node_at(r, child)?);
+            } else {
+                bail!(
+                    "unexpected collision primitive node: `{}`",
+                    AsciiDisplay(child.name.as_ref())
+                );
+            }
+        }
+
+        Ok(Self {
+            info: info.ok_or(anyhow!("missing primitive info"))?,
+            primitives: partials
+                .into_iter()
+                .map(|partial| {
+                    Ok(LevelCollisionPrimitive {
+                        name: partial.name,
+                        mask: partial.mask,
+                        parent: partial.parent.ok_or(anyhow!("missing primitive parent"))?,
+                        transform: partial
+                            .transform
+                            .ok_or(anyhow!("missing primitive transform"))?,
+                        shape: partial.shape.ok_or(anyhow!("missing primitive shape"))?,
+                    })
+                })
+                .collect::<AnyResult<_>>()?,
+        })
+    }
+}
+
+impl NodeWrite for LevelCollisionPrimitives {
+    fn write_node<W: Write + Seek>(&self, writer: &mut NodeWriter<W>) -> AnyResult {
+        writer.write_node(b"INFO", self.info.clone())?;
+        for primitive in &self.primitives {
+            writer.write_node(b"NAME", primitive.name.clone())?;
+            if let Some(mask) = primitive.mask {
+                writer.write_node(b"MASK", mask)?;
+            }
+            writer.write_node(b"PRNT", primitive.parent.clone())?;
+            writer.write_node(b"XFRM", primitive.transform.clone())?;
+            writer.write_node(b"DATA", primitive.shape)?;
+        }
+        ok()
+    }
+}
+
+/// Triangle collision mesh, stored in `coll` nodes.
+#[derive(Debug, Clone)]
+pub struct LevelCollisionMesh {
+    pub name: CString,
+    pub mask: Option<CollisionMask>,
+    /// Name of the model node the mesh is attached to
+    pub node: CString,
+    pub info: CollisionMeshInfo,
+    /// Vertex positions, stored in `POSI`
+    pub positions: Vec<[f32; 3]>,
+    /// Flattened collision tree, stored in `TREE`
+    pub tree: Vec<CollisionTreeNode>,
+}
+
+/// Proceed with caution, the layout may not be what it appears to be.
+#[derive(Debug, Clone, PackedData)]
+pub struct CollisionMeshInfo {
+    pub vertex_count: u32,
+    pub node_count: u32,
+    pub leaf_count: u32,
+    pub index_count: u32,
+    pub aabb_min: [f32; 3],
+    pub aabb_max: [f32; 3],
+}
+
+/// Entry of a collision tree, stored in file order (depth-first).
+#[derive(Debug, Clone, PartialEq)]
+pub enum CollisionTreeNode {
+    /// `TREE:NODE` - a bounding box of all entries that follow it in its subtree
+    Node {
+        aabb_min: [f32; 3],
+        aabb_max: [f32; 3],
+    },
+    /// `TREE:LEAF` - a convex polygon, stored as a list of vertex indices
+    Leaf { indices: Vec<u16> },
+}
+
+impl LevelCollisionMesh {
+    /// Iterates over all triangles of the mesh, triangulating leaf polygons as fans.
+    pub fn triangles(&self) -> impl Iterator<Item = [u16; 3]> + '_ {
+        self.tree
+            .iter()
+            .filter_map(|node| match node {
+                CollisionTreeNode::Leaf { indices } => Some(indices),
+                CollisionTreeNode::Node { .. } => None,
+            })
+            .flat_map(|indices| {
+                (2..indices.len()).map(move |i| [indices[0], indices[i - 1], indices[i]])
+            })
+    }
+}
+
+impl NodeRead for LevelCollisionMesh {
+    fn read_node_payload<R: Read + Seek>(r: &mut R, meta: NodeHeader) -> AnyResult<Self> {
+        let mut name = None;
+        let mut mask = None;
+        let mut node = None;
+        let mut info = None;
+        let mut positions = None;
+        let mut tree = None;
+
+        for child in read_node_children(r, meta)? {
+            if child.name == b"NAME" {
+                name = Some(CString::read_node_at(r, child)?);
+            } else if child.name == b"MASK" {
+                mask = Some(CollisionMask::read_node_at(r, child)?);
+            } else if child.name == b"NODE" {
+                node = Some(CString::read_node_at(r, child)?);
+            } else if child.name == b"INFO" {
+                info = Some(CollisionMeshInfo::read_node_at(r, child)?);
+            } else if child.name == b"POSI" {
+                let raw = Vec::<u8>::read_node_at(r, child)?;
+                ensure!(raw.len() % 12 == 0, "invalid collision vertex buffer size");
+                positions = Some(
+                    raw.chunks_exact(12)
+                        .map(|mut vertex| <[f32; 3]>::read_packed(&mut vertex))
+                        .collect::<AnyResult<Vec<_>>>()?,
+                );
+            } else if child.name == b"TREE" {
+                tree = Some(read_collision_tree(r, child)?);
+            } else {
+                bail!(
+                    "unexpected collision mesh node: `{}`",
+                    AsciiDisplay(child.name.as_ref())
+                );
+            }
+        }
+
+        Ok(Self {
+            name: name.ok_or(anyhow!("missing collision mesh name"))?,
+            mask,
+            node: node.ok_or(anyhow!("missing collision mesh node"))?,
+            info: info.ok_or(anyhow!("missing collision mesh info"))?,
+            positions: positions.ok_or(anyhow!("missing collision mesh positions"))?,
+            tree: tree.ok_or(anyhow!("missing collision mesh tree"))?,
+        })
+    }
+}
+
+fn read_collision_tree<R: Read + Seek>(
+    r: &mut R,
+    header: NodeHeader,
+) -> AnyResult<Vec<CollisionTreeNode>> {
+    read_node_children(r, header)?
+        .into_iter()
+        .map(|child| {
+            child.seek_to_payload(r)?;
+            if child.name == b"NODE" {
+                Ok(CollisionTreeNode::Node {
+                    aabb_min: <[f32; 3]>::read_packed(r)?,
+                    aabb_max: <[f32; 3]>::read_packed(r)?,
+                })
+            } else if child.name == b"LEAF" {
+                let count = r.read_u8()?;
+                let mut indices = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    indices.push(r.read_u16::<LE>()?);
+                }
+                Ok(CollisionTreeNode::Leaf { indices })
+            } else {
+                bail!(
+                    "unexpected collision tree node: `{}`",
+                    AsciiDisplay(child.name.as_ref())
+                )
+            }
+        })
+        .collect()
+}
+
+impl NodeWrite for LevelCollisionMesh {
+    fn write_node<W: Write + Seek>(&self, writer: &mut NodeWriter<W>) -> AnyResult {
+        writer.write_node(b"NAME", self.name.clone())?;
+        if let Some(mask) = self.mask {
+            writer.write_node(b"MASK", mask)?;
+        }
+        writer.write_node(b"NODE", self.node.clone())?;
+        writer.write_node(b"INFO", self.info.clone())?;
+        writer.build_node(b"POSI", |writer| {
+            for position in &self.positions {
+                position.write_packed(writer)?;
+            }
+            ok()
+        })?;
+        writer.build_node(b"TREE", |writer| {
+            for entry in &self.tree {
+                match entry {
+                    CollisionTreeNode::Node { aabb_min, aabb_max } => {
+                        writer.build_node(b"NODE", |writer| {
+                            aabb_min.write_packed(writer)?;
+                            aabb_max.write_packed(writer)
+                        })?;
+                    }
+                    CollisionTreeNode::Leaf { indices } => {
+                        ensure!(indices.len() <= u8::MAX as usize, "leaf polygon too big");
+                        writer.build_node(b"LEAF", |writer| {
+                            writer.write_u8(indices.len() as u8)?;
+                            for &index in indices {
+                                writer.write_u16::<LE>(index)?;
+                            }
+                            ok()
+                        })?;
+                    }
+                }
+            }
+            ok()
+        })?;
+        ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{raw_node, NodeSlice};
+
+    fn floats(values: &[f32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn transform(position: [f32; 3]) -> Vec<u8> {
+        let mut data = floats(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+        data.extend(floats(&position));
+        data
+    }
+
+    fn shape(kind: u32, values: [f32; 3]) -> Vec<u8> {
+        [&kind.to_le_bytes()[..], &floats(&values)].concat()
+    }
+
+    fn leaf(indices: &[u16]) -> Vec<u8> {
+        let mut payload = vec![indices.len() as u8];
+        payload.extend(indices.iter().flat_map(|index| index.to_le_bytes()));
+        let padding = (4 - payload.len() % 4) % 4;
+        raw_node(b"LEAF", &payload, padding)
+    }
+
+    #[test]
+    fn primitives_from_nodes() {
+        let payload = [
+            raw_node(b"INFO", &[2, 0, 0, 0], 0),
+            raw_node(b"NAME", b"p_box\0", 2),
+            raw_node(b"MASK", &CollisionMask::SOLDIER.bits().to_le_bytes(), 0),
+            raw_node(b"PRNT", b"root\0", 3),
+            raw_node(b"XFRM", &transform([1.0, 2.0, 3.0]), 0),
+            raw_node(b"DATA", &shape(4, [0.5, 1.0, 1.5]), 0),
+            raw_node(b"NAME", b"p_cyl\0", 2),
+            raw_node(b"PRNT", b"root\0", 3),
+            raw_node(b"XFRM", &transform([0.0; 3]), 0),
+            raw_node(b"DATA", &shape(2, [0.25, 2.0, 0.0]), 0),
+        ]
+        .concat();
+        let file = raw_node(b"prim", &payload, 0);
+
+        let prim: LevelCollisionPrimitives = NodeSlice::root(&file).unwrap().read().unwrap();
+        assert_eq!(prim.info, [2, 0, 0, 0]);
+
+        let [cube, cylinder] = &prim.primitives[..] else {
+            panic!("expected 2 primitives, got {:?}", prim.primitives);
+        };
+        assert_eq!(cube.name.to_str(), Ok("p_box"));
+        assert_eq!(cube.mask, Some(CollisionMask::SOLDIER));
+        assert_eq!(cube.parent.to_str(), Ok("root"));
+        assert_eq!(cube.transform.position, [1.0, 2.0, 3.0]);
+        assert_eq!(
+            cube.shape,
+            CollisionShape::Cube {
+                half_extents: [0.5, 1.0, 1.5]
+            }
+        );
+
+        assert_eq!(cylinder.name.to_str(), Ok("p_cyl"));
+        assert_eq!(cylinder.mask, None);
+        assert_eq!(
+            cylinder.shape,
+            CollisionShape::Cylinder {
+                radius: 0.25,
+                half_height: 2.0
+            }
+        );
+
+        // Primitive data has to follow a name, and unknown shapes are rejected
+        let orphan = [
+            raw_node(b"INFO", &[1, 0, 0, 0], 0),
+            raw_node(b"DATA", &shape(1, [1.0, 0.0, 0.0]), 0),
+        ]
+        .concat();
+        let orphan = raw_node(b"prim", &orphan, 0);
+        assert!(NodeSlice::root(&orphan)
+            .unwrap()
+            .read::<LevelCollisionPrimitives>()
+            .is_err());
+        let unknown = [
+            raw_node(b"INFO", &[1, 0, 0, 0], 0),
+            raw_node(b"NAME", b"p\0", 2),
+            raw_node(b"PRNT", b"root\0", 3),
+            raw_node(b"XFRM", &transform([0.0; 3]), 0),
+            raw_node(b"DATA", &shape(3, [0.0; 3]), 0),
+        ]
+        .concat();
+        let unknown = raw_node(b"prim", &unknown, 0);
+        assert!(NodeSlice::root(&unknown)
+            .unwrap()
+            .read::<LevelCollisionPrimitives>()
+            .is_err());
+    }
+
+    #[test]
+    fn mesh_from_nodes() {
+        let positions: Vec<f32> = (0..7).flat_map(|i| [i as f32, 0.0, 0.0]).collect();
+        let info = [
+            &[7u32, 1, 2, 7].map(u32::to_le_bytes).concat()[..],
+            &floats(&[0.0, 0.0, 0.0, 6.0, 0.0, 0.0]),
+        ]
+        .concat();
+        let tree = [
+            raw_node(b"NODE", &floats(&[0.0, 0.0, 0.0, 6.0, 0.0, 0.0]), 0),
+            leaf(&[0, 1, 2, 3]),
+            leaf(&[4, 5, 6]),
+        ]
+        .concat();
+        let payload = [
+            raw_node(b"NAME", b"terrain\0", 0),
+            raw_node(b"MASK", &CollisionMask::TERRAIN.bits().to_le_bytes(), 0),
+            raw_node(b"NODE", b"root\0", 3),
+            raw_node(b"INFO", &info, 0),
+            raw_node(b"POSI", &floats(&positions), 0),
+            raw_node(b"TREE", &tree, 0),
+        ]
+        .concat();
+        let file = raw_node(b"coll", &payload, 0);
+
+        let mesh: LevelCollisionMesh = NodeSlice::root(&file).unwrap().read().unwrap();
+        assert_eq!(mesh.name.to_str(), Ok("terrain"));
+        assert_eq!(mesh.mask, Some(CollisionMask::TERRAIN));
+        assert_eq!(mesh.node.to_str(), Ok("root"));
+        assert_eq!(mesh.info.vertex_count, 7);
+        assert_eq!(mesh.info.aabb_max, [6.0, 0.0, 0.0]);
+        assert_eq!(mesh.positions.len(), 7);
+        assert_eq!(mesh.positions[3], [3.0, 0.0, 0.0]);
+        assert_eq!(
+            mesh.tree[1],
+            CollisionTreeNode::Leaf {
+                indices: vec![0, 1, 2, 3]
+            }
+        );
+
+        // Leaf polygons are triangulated as fans
+        let triangles: Vec<_> = mesh.triangles().collect();
+        assert_eq!(triangles, [[0, 1, 2], [0, 2, 3], [4, 5, 6]]);
+
+        // Positions must be made of whole vectors
+        let truncated = [
+            raw_node(b"NAME", b"terrain\0", 0),
+            raw_node(b"NODE", b"root\0", 3),
+            raw_node(b"INFO", &info, 0),
+            raw_node(b"POSI", &floats(&positions)[..20], 0),
+            raw_node(b"TREE", &tree, 0),
+        ]
+        .concat();
+        let truncated = raw_node(b"coll", &truncated, 0);
+        assert!(NodeSlice::root(&truncated)
+            .unwrap()
+            .read::<LevelCollisionMesh>()
+            .is_err());
+    }
+}
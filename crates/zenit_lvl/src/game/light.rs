@@ -0,0 +1,265 @@
+use crate::config::{ConfigData, ConfigScope, LevelConfig};
+use bitflags::bitflags;
+use glam::{Quat, Vec3};
+
+/// Hashes of known `lght` config properties.
+pub mod light_properties {
+    property_hashes! {
+        LIGHT = "Light";
+        ROTATION = "Rotation";
+        POSITION = "Position";
+        TYPE = "Type";
+        COLOR = "Color";
+        RANGE = "Range";
+        CONE = "Cone";
+        CAST_SHADOW = "CastShadow";
+        STATIC = "Static";
+        CAST_SPECULAR = "CastSpecular";
+
+        GLOBAL_LIGHTS = "GlobalLights";
+        LIGHT1 = "Light1";
+        LIGHT2 = "Light2";
+        TOP = "Top";
+        BOTTOM = "Bottom";
+    }
+}
+
+/// Typed representation of static level lighting, stored in `lght` config nodes.
+///
+/// The config tree is structured roughly like this:
+/// ```c
+/// Light("sun")
+/// {
+///     Rotation(0.0, 0.0, 0.0, 1.0);
+///     Position(0.0, 10.0, 0.0);
+///     Type(1);
+///     Color(1.0, 0.9, 0.8);
+///     CastShadow();
+///     Static();
+/// }
+/// GlobalLights()
+/// {
+///     Light1("sun");
+///     Top(100, 100, 120);
+///     Bottom(40, 40, 50);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LevelLighting {
+    /// Hashed name of the lighting node, usually derived from the source file name
+    pub name_hash: u32,
+    pub lights: Vec<LevelLight>,
+    pub global: Option<GlobalLighting>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LevelLight {
+    pub name: String,
+    pub kind: LevelLightKind,
+    pub position: Vec3,
+    pub rotation: Quat,
+    /// RGB color, with values in range [0; 1]
+    pub color: Vec3,
+    pub flags: LevelLightFlags,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LevelLightKind {
+    /// Infinitely far light, pointing along the light's rotation
+    Directional,
+    /// Point light
+    Omni { range: f32 },
+    /// Cone light, pointing along the light's rotation. Cone angles are in radians.
+    Spot {
+        range: f32,
+        inner_cone: f32,
+        outer_cone: f32,
+    },
+}
+
+bitflags! {
+    #[derive(Default)]
+    pub struct LevelLightFlags: u32 {
+        const CAST_SHADOW = 1 << 0;
+        const STATIC = 1 << 1;
+        const CAST_SPECULAR = 1 << 2;
+    }
+}
+
+/// Global lighting settings of the level.
+#[derive(Debug, Clone, Default)]
+pub struct GlobalLighting {
+    /// Names of the main directional lights
+    pub lights: [Option<String>; 2],
+    /// RGB ambient color coming from above, in range [0; 255]
+    pub ambient_top: Option<Vec3>,
+    /// RGB ambient color coming from below, in range [0; 255]
+    pub ambient_bottom: Option<Vec3>,
+}
+
+impl From<&LevelConfig> for LevelLighting {
+    fn from(config: &LevelConfig) -> Self {
+        use light_properties::*;
+
+        let root = &config.root;
+
+        Self {
+            name_hash: config.name_hash,
+            lights: root
+                .iter_scoped()
+                .filter(|(data, _)| data.name_hash == LIGHT)
+                .filter_map(|(data, scope)| LevelLight::from_config(data, scope?))
+                .collect(),
+            global: root.find_scope(GLOBAL_LIGHTS).map(|scope| GlobalLighting {
+                lights: [
+                    scope.find_data(LIGHT1).and_then(|data| data.get_string(0)),
+                    scope.find_data(LIGHT2).and_then(|data| data.get_string(0)),
+                ]
+                .map(|name| name.filter(|name| !name.is_empty())),
+                ambient_top: scope.find_floats(TOP).map(Vec3::from),
+                ambient_bottom: scope.find_floats(BOTTOM).map(Vec3::from),
+            }),
+        }
+    }
+}
+
+impl LevelLight {
+    /// Decodes a single `Light("name") { ... }` expression. Returns [`None`] if the light's type
+    /// isn't known.
+    fn from_config(light: &ConfigData, scope: &ConfigScope) -> Option<Self> {
+        use light_properties::*;
+
+        let get_float = |name_hash| scope.find_data(name_hash).and_then(|data| data.get(0));
+        let has_flag = |name_hash| match scope.find_data(name_hash) {
+            // Flags can be either present without parameters, or have a 0/1 parameter
+            Some(data) => data.get::<f32>(0).map(|value| value != 0.0).unwrap_or(true),
+            None => false,
+        };
+
+        let range = get_float(RANGE).unwrap_or(0.0);
+        let kind = match get_float(TYPE)? as u32 {
+            1 => LevelLightKind::Directional,
+            2 => LevelLightKind::Omni { range },
+            3 => {
+                let [inner_cone, outer_cone] = scope.find_floats(CONE).unwrap_or([0.0; 2]);
+                LevelLightKind::Spot {
+                    range,
+                    inner_cone,
+                    outer_cone,
+                }
+            }
+            _ => return None,
+        };
+
+        let mut flags = LevelLightFlags::empty();
+        flags.set(LevelLightFlags::CAST_SHADOW, has_flag(CAST_SHADOW));
+        flags.set(LevelLightFlags::STATIC, has_flag(STATIC));
+        flags.set(LevelLightFlags::CAST_SPECULAR, has_flag(CAST_SPECULAR));
+
+        Some(Self {
+            name: light.get_string(0).unwrap_or_default(),
+            kind,
+            position: scope
+                .find_floats(POSITION)
+                .map(Vec3::from)
+                .unwrap_or(Vec3::ZERO),
+            rotation: scope
+                .find_floats(ROTATION)
+                .map(|[x, y, z, w]| Quat::from_xyzw(x, y, z, w))
+                .unwrap_or(Quat::IDENTITY),
+            color: scope
+                .find_floats(COLOR)
+                .map(Vec3::from)
+                .unwrap_or(Vec3::ONE),
+            flags,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{config, floats, scope, string};
+    use zenit_utils::fnv1a_hash;
+
+    #[test]
+    fn lighting_from_config() {
+        let config = config(
+            "kam1",
+            vec![
+                string("Light", "sun"),
+                scope(vec![
+                    floats("Rotation", &[0.0, 0.0, 0.0, 1.0]),
+                    floats("Position", &[0.0, 10.0, 0.0]),
+                    floats("Type", &[1.0]),
+                    floats("Color", &[1.0, 0.9, 0.8]),
+                    floats("CastShadow", &[]),
+                    floats("Static", &[]),
+                ]),
+                string("Light", "lamp"),
+                scope(vec![
+                    floats("Position", &[1.0, 2.0, 3.0]),
+                    floats("Type", &[2.0]),
+                    floats("Range", &[15.0]),
+                    // Flags with a 0 parameter are disabled
+                    floats("CastShadow", &[0.0]),
+                    floats("CastSpecular", &[1.0]),
+                ]),
+                string("Light", "spot"),
+                scope(vec![
+                    floats("Type", &[3.0]),
+                    floats("Range", &[20.0]),
+                    floats("Cone", &[0.5, 0.75]),
+                    floats("Static", &[1.0]),
+                ]),
+                // Unknown light types are skipped
+                string("Light", "area"),
+                scope(vec![floats("Type", &[4.0])]),
+                floats("GlobalLights", &[]),
+                scope(vec![
+                    string("Light1", "sun"),
+                    string("Light2", ""),
+                    floats("Top", &[100.0, 100.0, 120.0]),
+                    floats("Bottom", &[40.0, 40.0, 50.0]),
+                ]),
+            ],
+        );
+
+        let lighting = LevelLighting::from(&config);
+        assert_eq!(lighting.name_hash, fnv1a_hash(b"kam1"));
+
+        let [sun, lamp, spot] = &lighting.lights[..] else {
+            panic!("expected 3 lights, got {:?}", lighting.lights);
+        };
+
+        assert_eq!(sun.name, "sun");
+        assert_eq!(sun.kind, LevelLightKind::Directional);
+        assert_eq!(sun.position, Vec3::new(0.0, 10.0, 0.0));
+        assert_eq!(sun.color, Vec3::new(1.0, 0.9, 0.8));
+        assert_eq!(
+            sun.flags,
+            LevelLightFlags::CAST_SHADOW | LevelLightFlags::STATIC
+        );
+
+        assert_eq!(lamp.kind, LevelLightKind::Omni { range: 15.0 });
+        assert_eq!(lamp.position, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(lamp.rotation, Quat::IDENTITY);
+        assert_eq!(lamp.color, Vec3::ONE);
+        assert_eq!(lamp.flags, LevelLightFlags::CAST_SPECULAR);
+
+        assert_eq!(
+            spot.kind,
+            LevelLightKind::Spot {
+                range: 20.0,
+                inner_cone: 0.5,
+                outer_cone: 0.75,
+            }
+        );
+        assert_eq!(spot.flags, LevelLightFlags::STATIC);
+
+        let global = lighting.global.unwrap();
+        assert_eq!(global.lights, [Some(String::from("sun")), None]);
+        assert_eq!(global.ambient_top, Some(Vec3::new(100.0, 100.0, 120.0)));
+        assert_eq!(global.ambient_bottom, Some(Vec3::new(40.0, 40.0, 50.0)));
+    }
+}
@@ -2,12 +2,37 @@
 
 use crate::node::*;
 
+/// Defines a `const` hash for every listed config property name, along with a `NAMES` list of
+/// all of them.
+macro_rules! property_hashes {
+    ($($(#[$meta:meta])* $hash:ident = $name:literal;)*) => {
+        $($(#[$meta])* pub const $hash: u32 = ::zenit_utils::fnv1a_hash($name.as_bytes());)*
+
+        /// Every property name hashed in this module.
+        pub const NAMES: &[&str] = &[$($name),*];
+    };
+}
+
+mod collision;
+pub use collision::*;
+mod light;
+pub use light::*;
 mod model;
 pub use model::*;
 mod pack;
 pub use pack::*;
+mod particle;
+pub use particle::*;
+mod plan;
+pub use plan::*;
 mod script;
 pub use script::*;
+mod sky;
+pub use sky::*;
+mod sound;
+pub use sound::*;
+mod stream;
+pub use stream::*;
 mod texture;
 pub use texture::*;
 
@@ -22,8 +47,45 @@ pub struct LevelData {
     pub scripts: Vec<LevelScript>,
     #[nodes("tex_")]
     pub textures: Vec<LevelTexture>,
+    #[nodes("sky_")]
+    pub skies: Vec<crate::config::LevelConfig>,
+    #[nodes("lght")]
+    pub lighting: Vec<crate::config::LevelConfig>,
+    #[nodes("fx__")]
+    pub effects: Vec<crate::config::LevelConfig>,
+    #[nodes("prim")]
+    pub collision_primitives: Vec<LevelCollisionPrimitives>,
+    #[nodes("coll")]
+    pub collision_meshes: Vec<LevelCollisionMesh>,
+    #[nodes("plan")]
+    pub planning: Vec<LevelPlanning>,
+    #[nodes("emo_")]
+    pub sound_banks: Vec<LevelSoundBank>,
 
     #[cfg(feature = "zenit_extensions")]
     #[nodes("WGSL")]
     pub wgsl_shaders: Vec<crate::zext::LevelWgslShader>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zenit_utils::{fnv1a_hash, HashDictionary};
+
+    #[test]
+    fn property_names_are_builtin() {
+        let dictionary = HashDictionary::with_builtin_names();
+        let names = [
+            light_properties::NAMES,
+            particle_properties::NAMES,
+            sky_properties::NAMES,
+        ];
+        for &name in names.concat().iter() {
+            assert_eq!(
+                dictionary.lookup(fnv1a_hash(name.as_bytes())),
+                Some(name),
+                "{name} is missing from the builtin names"
+            );
+        }
+    }
+}
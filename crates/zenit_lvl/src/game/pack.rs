@@ -1,5 +1,7 @@
 use super::LevelData;
-use crate::node::{read_node_children, NodeHeader, NodeRead, NodeWrite, NodeWriter};
+use crate::node::{
+    read_node_children, NodeHeader, NodeRead, NodeResultExt, NodeWrite, NodeWriter, ReadSeek,
+};
 use anyhow::bail;
 use std::io::{Read, Seek, Write};
 use zenit_utils::{ok, AnyResult};
@@ -23,9 +25,12 @@ impl NodeRead for LevelDataPack {
             bail!("invalid level data pack node contents")
         };
 
+        // Packs contain level data, which contains more packs (see [`ReadSeek`] for details)
+        let mut r: &mut dyn ReadSeek = r;
+
         Ok(Self {
             name_hash: root.name.into(),
-            contents: LevelData::read_node_payload(r, root)?,
+            contents: LevelData::read_node_payload(&mut r, root).at_node(root)?,
         })
     }
 }
@@ -0,0 +1,495 @@
+use crate::config::{ConfigData, ConfigScope, LevelConfig};
+use glam::{Vec3, Vec4};
+
+/// Hashes of known `fx__` config properties.
+///
+/// Some names, like `Size` or `LifeTime`, are reused in different scopes with different meanings.
+pub mod particle_properties {
+    property_hashes! {
+        PARTICLE_EMITTER = "ParticleEmitter";
+        MAX_PARTICLES = "MaxParticles";
+        START_DELAY = "StartDelay";
+        BURST_DELAY = "BurstDelay";
+        BURST_COUNT = "BurstCount";
+        MAX_LOD_DIST = "MaxLodDist";
+        MIN_LOD_DIST = "MinLodDist";
+        BOUNDING_RADIUS = "BoundingRadius";
+        SOUND_NAME = "SoundName";
+        NO_REGISTER_STEP = "NoRegisterStep";
+
+        SPAWNER = "Spawner";
+        SPREAD = "Spread";
+        OFFSET = "Offset";
+        POSITION_X = "PositionX";
+        POSITION_Y = "PositionY";
+        POSITION_Z = "PositionZ";
+        POSITION_SCALE = "PositionScale";
+        VELOCITY_SCALE = "VelocityScale";
+        INHERIT_VELOCITY_FACTOR = "InheritVelocityFactor";
+        SIZE = "Size";
+        RED = "Red";
+        GREEN = "Green";
+        BLUE = "Blue";
+        ALPHA = "Alpha";
+        START_ROTATION = "StartRotation";
+        ROTATION_VELOCITY = "RotationVelocity";
+        FADE_IN_TIME = "FadeInTime";
+
+        TRANSFORMER = "Transformer";
+        LIFE_TIME = "LifeTime";
+        POSITION = "Position";
+        COLOR = "Color";
+        NEXT = "Next";
+        ACCELERATE = "Accelerate";
+        SCALE = "Scale";
+        REACH = "Reach";
+
+        GEOMETRY = "Geometry";
+        BLEND_MODE = "BlendMode";
+        TYPE = "Type";
+        TEXTURE = "Texture";
+    }
+}
+
+/// Typed representation of a particle effect, stored in `fx__` config nodes.
+///
+/// Properties Zenit doesn't know about are kept in `unknown` lists of every scope, so that
+/// newer code can still get to them. Colors are kept in the 0-255 range they're stored with.
+///
+/// The config tree is structured roughly like this:
+/// ```c
+/// ParticleEmitter("Sparks")
+/// {
+///     MaxParticles(30.0, 30.0);
+///     BurstCount(1.0, 1.0);
+///     Spawner()
+///     {
+///         Spread()
+///         {
+///             PositionX(-1.0, 1.0);
+///         }
+///         Size(0, 0.3, 0.4);
+///         Red(0, 255.0, 255.0);
+///     }
+///     Transformer()
+///     {
+///         LifeTime(1.0);
+///         Position()
+///         {
+///             LifeTime(1.0);
+///             Accelerate(0.0, -9.8, 0.0);
+///         }
+///     }
+///     Geometry()
+///     {
+///         BlendMode("ADDITIVE");
+///         Type("PARTICLE");
+///         Texture("fx_spark");
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ParticleEffect {
+    /// Hashed name of the effect, usually derived from the source file name
+    pub name_hash: u32,
+    pub emitters: Vec<ParticleEmitter>,
+    pub unknown: Vec<UnknownParticleProperty>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParticleEmitter {
+    pub name: String,
+    pub max_particles: Option<ParticleRange>,
+    pub start_delay: Option<ParticleRange>,
+    pub burst_delay: Option<ParticleRange>,
+    pub burst_count: Option<ParticleRange>,
+    pub max_lod_distance: Option<f32>,
+    pub min_lod_distance: Option<f32>,
+    pub bounding_radius: Option<f32>,
+    pub sound_name: Option<String>,
+    pub no_register_step: bool,
+    pub spawner: Option<ParticleSpawner>,
+    pub transformer: Option<ParticleTransformer>,
+    pub geometry: Option<ParticleGeometry>,
+    /// Emitters nested within this one
+    pub children: Vec<ParticleEmitter>,
+    pub unknown: Vec<UnknownParticleProperty>,
+}
+
+/// Range of values, from which a random one is picked.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ParticleRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+/// Describes how new particles are created.
+#[derive(Debug, Clone, Default)]
+pub struct ParticleSpawner {
+    /// Random spread of the initial velocity
+    pub spread: Option<ParticleVolume>,
+    /// Random offset of the initial position
+    pub offset: Option<ParticleVolume>,
+    pub position_scale: Option<ParticleRange>,
+    pub velocity_scale: Option<ParticleRange>,
+    pub inherit_velocity_factor: Option<ParticleRange>,
+    pub size: Option<ParticleRange>,
+    pub color: ParticleColorRange,
+    pub start_rotation: Option<ParticleRange>,
+    pub rotation_velocity: Option<ParticleRange>,
+    pub fade_in_time: Option<f32>,
+    pub unknown: Vec<UnknownParticleProperty>,
+}
+
+/// Per-axis value ranges.
+#[derive(Debug, Clone, Default)]
+pub struct ParticleVolume {
+    pub x: Option<ParticleRange>,
+    pub y: Option<ParticleRange>,
+    pub z: Option<ParticleRange>,
+    pub unknown: Vec<UnknownParticleProperty>,
+}
+
+/// Per-channel color ranges, in range [0; 255].
+#[derive(Debug, Clone, Default)]
+pub struct ParticleColorRange {
+    pub red: Option<ParticleRange>,
+    pub green: Option<ParticleRange>,
+    pub blue: Option<ParticleRange>,
+    pub alpha: Option<ParticleRange>,
+}
+
+/// Describes how particles change during their lifetime.
+#[derive(Debug, Clone, Default)]
+pub struct ParticleTransformer {
+    /// Lifetime of particles, in seconds
+    pub lifetime: Option<f32>,
+    pub position: Vec<ParticleStage>,
+    pub size: Vec<ParticleStage>,
+    pub color: Vec<ParticleStage>,
+    pub unknown: Vec<UnknownParticleProperty>,
+}
+
+/// A single transformer stage. Consecutive stages are chained with `Next()` scopes in the
+/// config, but here they're flattened into a list.
+#[derive(Debug, Clone, Default)]
+pub struct ParticleStage {
+    /// Duration of the stage, in seconds
+    pub lifetime: Option<f32>,
+    pub operation: Option<ParticleStageOperation>,
+    pub unknown: Vec<UnknownParticleProperty>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParticleStageOperation {
+    /// Acceleration applied to the particle's velocity
+    Accelerate(Vec3),
+    /// Multiplier the particle's size reaches by the end of the stage
+    Scale(f32),
+    /// RGBA color the particle reaches by the end of the stage, in range [0; 255]
+    Reach(Vec4),
+}
+
+/// Describes how particles are drawn.
+#[derive(Debug, Clone, Default)]
+pub struct ParticleGeometry {
+    pub blend_mode: Option<String>,
+    /// Geometry kind, like `PARTICLE`, `STREAK` or `GEOMETRY`
+    pub kind: Option<String>,
+    pub texture: Option<String>,
+    pub unknown: Vec<UnknownParticleProperty>,
+}
+
+/// A property that wasn't recognized, alongside the scope following it (if any).
+#[derive(Debug, Clone)]
+pub struct UnknownParticleProperty {
+    pub data: ConfigData,
+    pub scope: Option<ConfigScope>,
+}
+
+impl From<&LevelConfig> for ParticleEffect {
+    fn from(config: &LevelConfig) -> Self {
+        let (emitters, unknown) = ParticleEmitter::list_from_scope(&config.root);
+        Self {
+            name_hash: config.name_hash,
+            emitters,
+            unknown,
+        }
+    }
+}
+
+impl ParticleEmitter {
+    /// Decodes all `ParticleEmitter` expressions in the scope. Anything else is returned as
+    /// unknown properties.
+    fn list_from_scope(scope: &ConfigScope) -> (Vec<Self>, Vec<UnknownParticleProperty>) {
+        let mut emitters = Vec::new();
+        let mut unknown = Vec::new();
+        for (data, inner) in scope.iter_scoped() {
+            match (data.name_hash, inner) {
+                (particle_properties::PARTICLE_EMITTER, Some(inner)) => {
+                    emitters.push(Self::from_config(data, inner))
+                }
+                _ => unknown.push(UnknownParticleProperty::new(data, inner)),
+            }
+        }
+        (emitters, unknown)
+    }
+
+    fn from_config(emitter: &ConfigData, scope: &ConfigScope) -> Self {
+        use particle_properties::*;
+
+        let mut result = Self {
+            name: emitter.get_string(0).unwrap_or_default(),
+            ..Default::default()
+        };
+
+        for (data, inner) in scope.iter_scoped() {
+            match (data.name_hash, inner) {
+                (MAX_PARTICLES, _) => result.max_particles = ParticleRange::from_data(data, 0),
+                (START_DELAY, _) => result.start_delay = ParticleRange::from_data(data, 0),
+                (BURST_DELAY, _) => result.burst_delay = ParticleRange::from_data(data, 0),
+                (BURST_COUNT, _) => result.burst_count = ParticleRange::from_data(data, 0),
+                (MAX_LOD_DIST, _) => result.max_lod_distance = data.get(0),
+                (MIN_LOD_DIST, _) => result.min_lod_distance = data.get(0),
+                (BOUNDING_RADIUS, _) => result.bounding_radius = data.get(0),
+                (SOUND_NAME, _) => {
+                    result.sound_name = data.get_string(0).filter(|name| !name.is_empty())
+                }
+                (NO_REGISTER_STEP, _) => result.no_register_step = true,
+                (SPAWNER, Some(inner)) => result.spawner = Some(ParticleSpawner::from_scope(inner)),
+                (TRANSFORMER, Some(inner)) => {
+                    result.transformer = Some(ParticleTransformer::from_scope(inner))
+                }
+                (GEOMETRY, Some(inner)) => {
+                    result.geometry = Some(ParticleGeometry::from_scope(inner))
+                }
+                (PARTICLE_EMITTER, Some(inner)) => {
+                    result.children.push(Self::from_config(data, inner))
+                }
+                _ => result
+                    .unknown
+                    .push(UnknownParticleProperty::new(data, inner)),
+            }
+        }
+
+        result
+    }
+}
+
+impl ParticleRange {
+    /// Reads a range starting at the given value index. A single value is treated as a range
+    /// with equal bounds.
+    fn from_data(data: &ConfigData, start: u32) -> Option<Self> {
+        let min = data.get(start)?;
+        let max = data.get(start + 1).unwrap_or(min);
+        Some(Self { min, max })
+    }
+}
+
+impl ParticleSpawner {
+    fn from_scope(scope: &ConfigScope) -> Self {
+        use particle_properties::*;
+
+        // Spawner properties with three values (like `Size(0, 0.3, 0.4)`) seem to have a leading
+        // mode parameter, which is skipped here.
+        let mut result = Self::default();
+        for (data, inner) in scope.iter_scoped() {
+            match (data.name_hash, inner) {
+                (SPREAD, Some(inner)) => result.spread = Some(ParticleVolume::from_scope(inner)),
+                (OFFSET, Some(inner)) => result.offset = Some(ParticleVolume::from_scope(inner)),
+                (POSITION_SCALE, _) => result.position_scale = ParticleRange::from_data(data, 0),
+                (VELOCITY_SCALE, _) => result.velocity_scale = ParticleRange::from_data(data, 0),
+                (INHERIT_VELOCITY_FACTOR, _) => {
+                    result.inherit_velocity_factor = ParticleRange::from_data(data, 0)
+                }
+                (SIZE, _) => result.size = ParticleRange::from_data(data, 1),
+                (RED, _) => result.color.red = ParticleRange::from_data(data, 1),
+                (GREEN, _) => result.color.green = ParticleRange::from_data(data, 1),
+                (BLUE, _) => result.color.blue = ParticleRange::from_data(data, 1),
+                (ALPHA, _) => result.color.alpha = ParticleRange::from_data(data, 1),
+                (START_ROTATION, _) => result.start_rotation = ParticleRange::from_data(data, 1),
+                (ROTATION_VELOCITY, _) => {
+                    result.rotation_velocity = ParticleRange::from_data(data, 1)
+                }
+                (FADE_IN_TIME, _) => result.fade_in_time = data.get(0),
+                _ => result
+                    .unknown
+                    .push(UnknownParticleProperty::new(data, inner)),
+            }
+        }
+        result
+    }
+}
+
+impl ParticleVolume {
+    fn from_scope(scope: &ConfigScope) -> Self {
+        use particle_properties::*;
+
+        let mut result = Self::default();
+        for (data, inner) in scope.iter_scoped() {
+            match (data.name_hash, inner) {
+                (POSITION_X, _) => result.x = ParticleRange::from_data(data, 0),
+                (POSITION_Y, _) => result.y = ParticleRange::from_data(data, 0),
+                (POSITION_Z, _) => result.z = ParticleRange::from_data(data, 0),
+                _ => result
+                    .unknown
+                    .push(UnknownParticleProperty::new(data, inner)),
+            }
+        }
+        result
+    }
+}
+
+impl ParticleTransformer {
+    fn from_scope(scope: &ConfigScope) -> Self {
+        use particle_properties::*;
+
+        let mut result = Self::default();
+        for (data, inner) in scope.iter_scoped() {
+            match (data.name_hash, inner) {
+                (LIFE_TIME, _) => result.lifetime = data.get(0),
+                (POSITION, Some(inner)) => result.position = ParticleStage::chain_from_scope(inner),
+                (SIZE, Some(inner)) => result.size = ParticleStage::chain_from_scope(inner),
+                (COLOR, Some(inner)) => result.color = ParticleStage::chain_from_scope(inner),
+                _ => result
+                    .unknown
+                    .push(UnknownParticleProperty::new(data, inner)),
+            }
+        }
+        result
+    }
+}
+
+impl ParticleStage {
+    /// Decodes a stage, alongside all stages chained after it with `Next()`.
+    fn chain_from_scope(scope: &ConfigScope) -> Vec<Self> {
+        use particle_properties::*;
+
+        let mut stages = Vec::new();
+        let mut current = Some(scope);
+
+        while let Some(scope) = current.take() {
+            let mut stage = Self::default();
+            for (data, inner) in scope.iter_scoped() {
+                match (data.name_hash, inner) {
+                    (LIFE_TIME, _) => stage.lifetime = data.get(0),
+                    (ACCELERATE, _) => {
+                        stage.operation = data
+                            .get_floats()
+                            .map(|values| ParticleStageOperation::Accelerate(Vec3::from(values)))
+                    }
+                    (SCALE, _) => stage.operation = data.get(0).map(ParticleStageOperation::Scale),
+                    (REACH, _) => {
+                        stage.operation = data
+                            .get_floats()
+                            .map(|values| ParticleStageOperation::Reach(Vec4::from(values)))
+                    }
+                    (NEXT, Some(inner)) => current = Some(inner),
+                    _ => stage
+                        .unknown
+                        .push(UnknownParticleProperty::new(data, inner)),
+                }
+            }
+            stages.push(stage);
+        }
+
+        stages
+    }
+}
+
+impl ParticleGeometry {
+    fn from_scope(scope: &ConfigScope) -> Self {
+        use particle_properties::*;
+
+        let mut result = Self::default();
+        for (data, inner) in scope.iter_scoped() {
+            match data.name_hash {
+                BLEND_MODE => result.blend_mode = data.get_string(0),
+                TYPE => result.kind = data.get_string(0),
+                TEXTURE => result.texture = data.get_string(0).filter(|name| !name.is_empty()),
+                _ => result
+                    .unknown
+                    .push(UnknownParticleProperty::new(data, inner)),
+            }
+        }
+        result
+    }
+}
+
+impl UnknownParticleProperty {
+    fn new(data: &ConfigData, scope: Option<&ConfigScope>) -> Self {
+        Self {
+            data: data.clone(),
+            scope: scope.cloned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{config, floats, scope, string};
+    use zenit_utils::fnv1a_hash;
+
+    /// The example from the `zenit_lvl::config` docs, with an additional unknown property.
+    #[test]
+    fn emitter_from_config_docs() {
+        let config = config(
+            "something",
+            vec![
+                string("ParticleEmitter", "Something"),
+                scope(vec![
+                    floats("MaxParticles", &[-1.0, -1.0]),
+                    floats("NoRegisterStep", &[]),
+                    string("SoundName", "something"),
+                    floats("Spawner", &[]),
+                    scope(vec![
+                        floats("Spread", &[]),
+                        scope(vec![
+                            floats("PositionX", &[-0.1, 0.0]),
+                            floats("PositionW", &[1.0]),
+                        ]),
+                    ]),
+                ]),
+            ],
+        );
+
+        let effect = ParticleEffect::from(&config);
+        assert!(effect.unknown.is_empty());
+        let [emitter] = &effect.emitters[..] else {
+            panic!("expected a single emitter, got {:?}", effect.emitters);
+        };
+
+        assert_eq!(emitter.name, "Something");
+        assert_eq!(
+            emitter.max_particles,
+            Some(ParticleRange {
+                min: -1.0,
+                max: -1.0
+            })
+        );
+        assert!(emitter.no_register_step);
+        assert_eq!(emitter.sound_name.as_deref(), Some("something"));
+        assert!(emitter.unknown.is_empty());
+
+        let spread = emitter.spawner.as_ref().unwrap().spread.as_ref().unwrap();
+        assert_eq!(
+            spread.x,
+            Some(ParticleRange {
+                min: -0.1,
+                max: 0.0
+            })
+        );
+        assert_eq!(spread.y, None);
+
+        // Unknown properties are kept around
+        let [unknown] = &spread.unknown[..] else {
+            panic!(
+                "expected a single unknown property, got {:?}",
+                spread.unknown
+            );
+        };
+        assert_eq!(unknown.data.name_hash, fnv1a_hash(b"PositionW"));
+        assert_eq!(unknown.data.get::<f32>(0), Some(1.0));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::node::{read_node_children, NodeHeader, NodeRead, NodeWrite, NodeWriter};
+use anyhow::{anyhow, bail, ensure};
+use bitflags::bitflags;
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::io::{Read, Seek, Write};
+use zenit_utils::{ok, packed::PackedData, AnyResult, AsciiDisplay};
+
+bitflags! {
+    /// Specifies which kinds of units may use a planning connection.
+    #[derive(Default)]
+    pub struct PlanConnectionFlags: u32 {
+        const SOLDIER = 1 << 0;
+        const HOVER = 1 << 1;
+        const SMALL = 1 << 2;
+        const MEDIUM = 1 << 3;
+        const HUGE = 1 << 4;
+        const FLYER = 1 << 5;
+    }
+}
+
+/// AI path planning graph, stored in `plan` nodes.
+///
+/// The node contains an `INFO` node with hub and connection counts, followed by `NODE` with
+/// the hub array and `ARCS` with the connection array. Both arrays consist of fixed size
+/// records, only partially understood. The known prefix of every record is decoded, while
+/// the rest is kept around as raw bytes, so that the node can be written back unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct LevelPlanning {
+    pub hubs: Vec<PlanHub>,
+    pub connections: Vec<PlanConnection>,
+}
+
+/// A navigation graph node - a circular area units can move through.
+#[derive(Debug, Clone)]
+pub struct PlanHub {
+    pub name: String,
+    pub position: [f32; 3],
+    pub radius: f32,
+    /// Remaining data of the hub record. Likely contains precomputed branch weights.
+    pub extra: Vec<u8>,
+}
+
+/// A navigation graph edge between two hubs. Connections are traversable both ways.
+#[derive(Debug, Clone)]
+pub struct PlanConnection {
+    pub name: String,
+    /// Index of the starting hub
+    pub start: u8,
+    /// Index of the ending hub
+    pub end: u8,
+    pub flags: PlanConnectionFlags,
+    /// Remaining data of the connection record
+    pub extra: Vec<u8>,
+}
+
+/// Size of the names stored in hub and connection records
+const PLAN_NAME_SIZE: usize = 16;
+/// Size of the known part of a hub record (name, position, radius)
+const HUB_HEADER_SIZE: usize = PLAN_NAME_SIZE + 16;
+/// Size of the known part of a connection record (name, start, end, flags)
+const CONNECTION_HEADER_SIZE: usize = PLAN_NAME_SIZE + 6;
+
+impl NodeRead for LevelPlanning {
+    fn read_node_payload<R: Read + Seek>(r: &mut R, meta: NodeHeader) -> AnyResult<Self> {
+        let mut counts = None;
+        let mut hub_data = None;
+        let mut connection_data = None;
+
+        for child in read_node_children(r, meta)? {
+            if child.name == b"INFO" {
+                child.seek_to_payload(r)?;
+                counts = Some((r.read_u16::<LE>()?, r.read_u16::<LE>()?));
+            } else if child.name == b"NODE" {
+                hub_data = Some(Vec::<u8>::read_node_at(r, child)?);
+            } else if child.name == b"ARCS" {
+                connection_data = Some(Vec::<u8>::read_node_at(r, child)?);
+            } else {
+                bail!(
+                    "unexpected planning node: `{}`",
+                    AsciiDisplay(child.name.as_ref())
+                );
+            }
+        }
+
+        let (hub_count, connection_count) = counts.ok_or(anyhow!("missing planning info"))?;
+        let hub_data = hub_data.unwrap_or_default();
+        let connection_data = connection_data.unwrap_or_default();
+
+        let hubs = split_records(&hub_data, hub_count, HUB_HEADER_SIZE)?
+            .map(|record| -> AnyResult<PlanHub> {
+                let (name, mut rest) = read_name(record);
+                Ok(PlanHub {
+                    name,
+                    position: <[f32; 3]>::read_packed(&mut rest)?,
+                    radius: rest.read_f32::<LE>()?,
+                    extra: rest.to_vec(),
+                })
+            })
+            .collect::<AnyResult<Vec<_>>>()?;
+
+        let connections =
+            split_records(&connection_data, connection_count, CONNECTION_HEADER_SIZE)?
+                .map(|record| -> AnyResult<PlanConnection> {
+                    let (name, mut rest) = read_name(record);
+                    let connection = PlanConnection {
+                        name,
+                        start: rest.read_u8()?,
+                        end: rest.read_u8()?,
+                        flags: PlanConnectionFlags::from_bits_truncate(rest.read_u32::<LE>()?),
+                        extra: rest.to_vec(),
+                    };
+                    ensure!(
+                        (connection.start as usize) < hubs.len()
+                            && (connection.end as usize) < hubs.len(),
+                        "connection `{}` references an invalid hub",
+                        connection.name
+                    );
+                    Ok(connection)
+                })
+                .collect::<AnyResult<Vec<_>>>()?;
+
+        Ok(Self { hubs, connections })
+    }
+}
+
+impl NodeWrite for LevelPlanning {
+    fn write_node<W: Write + Seek>(&self, writer: &mut NodeWriter<W>) -> AnyResult {
+        ensure!(self.hubs.len() <= u16::MAX as usize, "too many hubs");
+        ensure!(
+            self.connections.len() <= u16::MAX as usize,
+            "too many connections"
+        );
+
+        writer.build_node(b"INFO", |writer| {
+            writer.write_u16::<LE>(self.hubs.len() as u16)?;
+            writer.write_u16::<LE>(self.connections.len() as u16)?;
+            ok()
+        })?;
+
+        writer.build_node(b"NODE", |writer| {
+            for hub in &self.hubs {
+                write_name(writer, &hub.name)?;
+                hub.position.write_packed(writer)?;
+                writer.write_f32::<LE>(hub.radius)?;
+                writer.write_all(&hub.extra)?;
+            }
+            ok()
+        })?;
+
+        writer.build_node(b"ARCS", |writer| {
+            for connection in &self.connections {
+                write_name(writer, &connection.name)?;
+                writer.write_u8(connection.start)?;
+                writer.write_u8(connection.end)?;
+                writer.write_u32::<LE>(connection.flags.bits())?;
+                writer.write_all(&connection.extra)?;
+            }
+            ok()
+        })?;
+
+        ok()
+    }
+}
+
+/// Splits an array of equally sized records, each at least `min_size` bytes long.
+fn split_records(
+    data: &[u8],
+    count: u16,
+    min_size: usize,
+) -> AnyResult<impl Iterator<Item = &[u8]>> {
+    if count == 0 {
+        return Ok(data.chunks(1).take(0));
+    }
+
+    let count = count as usize;
+    ensure!(
+        data.len().is_multiple_of(count),
+        "record array size isn't a multiple of its count"
+    );
+
+    let size = data.len() / count;
+    ensure!(size >= min_size, "records are too small ({size} bytes)");
+
+    Ok(data.chunks(size).take(count))
+}
+
+/// Reads a fixed size, null padded name and returns it alongside the remaining data.
+fn read_name(record: &[u8]) -> (String, &[u8]) {
+    let (name, rest) = record.split_at(PLAN_NAME_SIZE);
+    let length = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    (String::from_utf8_lossy(&name[..length]).into_owned(), rest)
+}
+
+fn write_name(w: &mut impl Write, name: &str) -> AnyResult {
+    ensure!(
+        name.len() <= PLAN_NAME_SIZE,
+        "name `{name}` is too long for a planning record"
+    );
+    let mut buffer = [0u8; PLAN_NAME_SIZE];
+    buffer[..name.len()].copy_from_slice(name.as_bytes());
+    w.write_all(&buffer)?;
+    ok()
+}
@@ -0,0 +1,232 @@
+use crate::config::LevelConfig;
+use glam::{vec4, Vec2, Vec3, Vec4};
+
+/// Hashes of known `sky_` config properties.
+pub mod sky_properties {
+    property_hashes! {
+        SKY_INFO = "SkyInfo";
+        FOG_COLOR = "FogColor";
+        FOG_RANGE = "FogRange";
+        FAR_SCENE_RANGE = "FarSceneRange";
+        NEAR_SCENE_RANGE = "NearSceneRange";
+
+        SUN_INFO = "SunInfo";
+        ANGLE = "Angle";
+        COLOR = "Color";
+
+        DOME_INFO = "DomeInfo";
+        TEXTURE = "Texture";
+        AMBIENT = "Ambient";
+        DOME_MODEL = "DomeModel";
+        GEOMETRY = "Geometry";
+        OFFSET = "Offset";
+        MOVEMENT_SCALE = "MovementScale";
+    }
+}
+
+/// Typed representation of a sky definition, stored in `sky_` config nodes.
+///
+/// Only the properties Zenit knows about are decoded, anything else is ignored. Colors are kept
+/// in the 0-255 range they're stored with.
+///
+/// The config tree is structured roughly like this:
+/// ```c
+/// SkyInfo()
+/// {
+///     FogColor(170, 170, 170);
+///     FogRange(0.0, 400.0);
+///     FarSceneRange(1500);
+/// }
+/// SunInfo()
+/// {
+///     Angle(135.0, -20.0);
+///     Color(210, 210, 225);
+/// }
+/// DomeInfo()
+/// {
+///     Texture("kam1_sky");
+///     DomeModel()
+///     {
+///         Geometry("kam1_sky_dome");
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SkyDefinition {
+    /// Hashed name of the sky, usually derived from the source file name
+    pub name_hash: u32,
+    /// RGBA fog color, alpha defaults to 255 if not specified
+    pub fog_color: Option<Vec4>,
+    /// Near and far fog distances
+    pub fog_range: Option<Vec2>,
+    /// Distance of the far clipping plane
+    pub far_scene_range: Option<f32>,
+    pub sun: Option<SkySun>,
+    pub dome: Option<SkyDome>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SkySun {
+    /// Sun angles, in degrees
+    pub angle: Option<Vec2>,
+    /// RGB sun color
+    pub color: Option<Vec3>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SkyDome {
+    /// Name of the dome texture
+    pub texture: Option<String>,
+    /// RGB ambient color
+    pub ambient: Option<Vec3>,
+    pub models: Vec<SkyDomeModel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SkyDomeModel {
+    /// Name of the dome model
+    pub geometry: String,
+    pub offset: Option<f32>,
+    pub movement_scale: Option<f32>,
+}
+
+impl From<&LevelConfig> for SkyDefinition {
+    fn from(config: &LevelConfig) -> Self {
+        use sky_properties::*;
+
+        let root = &config.root;
+        let sky_info = root.find_scope(SKY_INFO);
+
+        Self {
+            name_hash: config.name_hash,
+            fog_color: sky_info
+                .and_then(|scope| scope.find_data(FOG_COLOR))
+                .and_then(|data| {
+                    let [r, g, b] = data.get_floats()?;
+                    Some(vec4(r, g, b, data.get(3).unwrap_or(255.0)))
+                }),
+            fog_range: sky_info
+                .and_then(|scope| scope.find_floats(FOG_RANGE))
+                .map(Vec2::from),
+            far_scene_range: sky_info
+                .and_then(|scope| scope.find_data(FAR_SCENE_RANGE))
+                .and_then(|data| data.get(0)),
+            sun: root.find_scope(SUN_INFO).map(|scope| SkySun {
+                angle: scope.find_floats(ANGLE).map(Vec2::from),
+                color: scope.find_floats(COLOR).map(Vec3::from),
+            }),
+            dome: root.find_scope(DOME_INFO).map(|scope| SkyDome {
+                texture: scope.find_data(TEXTURE).and_then(|data| data.get_string(0)),
+                ambient: scope.find_floats(AMBIENT).map(Vec3::from),
+                models: scope
+                    .iter_scoped()
+                    .filter(|(data, _)| data.name_hash == DOME_MODEL)
+                    .filter_map(|(_, model)| {
+                        let model = model?;
+                        Some(SkyDomeModel {
+                            geometry: model.find_data(GEOMETRY)?.get_string(0)?,
+                            offset: model.find_data(OFFSET).and_then(|data| data.get(0)),
+                            movement_scale: model
+                                .find_data(MOVEMENT_SCALE)
+                                .and_then(|data| data.get(0)),
+                        })
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::fixtures::{config, floats, scope, string};
+    use zenit_utils::fnv1a_hash;
+
+    #[test]
+    fn sky_from_config() {
+        let config = config(
+            "kam1",
+            vec![
+                floats("SkyInfo", &[]),
+                scope(vec![
+                    floats("FogColor", &[170.0, 160.0, 150.0]),
+                    floats("FogRange", &[10.0, 400.0]),
+                    floats("FarSceneRange", &[1500.0]),
+                    floats("Unused", &[1.0]),
+                ]),
+                floats("SunInfo", &[]),
+                scope(vec![
+                    floats("Angle", &[135.0, -20.0]),
+                    floats("Color", &[210.0, 210.0, 225.0]),
+                ]),
+                floats("DomeInfo", &[]),
+                scope(vec![
+                    string("Texture", "kam1_sky"),
+                    floats("Ambient", &[50.0, 60.0, 70.0]),
+                    floats("DomeModel", &[]),
+                    scope(vec![
+                        string("Geometry", "kam1_sky_dome"),
+                        floats("Offset", &[5.0]),
+                    ]),
+                    floats("DomeModel", &[]),
+                    scope(vec![
+                        string("Geometry", "kam1_sky_clouds"),
+                        floats("MovementScale", &[0.5]),
+                    ]),
+                    // Models without geometry are skipped
+                    floats("DomeModel", &[]),
+                    scope(vec![floats("Offset", &[1.0])]),
+                ]),
+            ],
+        );
+
+        let sky = SkyDefinition::from(&config);
+        assert_eq!(sky.name_hash, fnv1a_hash(b"kam1"));
+        assert_eq!(sky.fog_color, Some(vec4(170.0, 160.0, 150.0, 255.0)));
+        assert_eq!(sky.fog_range, Some(Vec2::new(10.0, 400.0)));
+        assert_eq!(sky.far_scene_range, Some(1500.0));
+
+        let sun = sky.sun.unwrap();
+        assert_eq!(sun.angle, Some(Vec2::new(135.0, -20.0)));
+        assert_eq!(sun.color, Some(Vec3::new(210.0, 210.0, 225.0)));
+
+        let dome = sky.dome.unwrap();
+        assert_eq!(dome.texture.as_deref(), Some("kam1_sky"));
+        assert_eq!(dome.ambient, Some(Vec3::new(50.0, 60.0, 70.0)));
+        let models: Vec<_> = dome
+            .models
+            .iter()
+            .map(|model| (model.geometry.as_str(), model.offset, model.movement_scale))
+            .collect();
+        assert_eq!(
+            models,
+            [
+                ("kam1_sky_dome", Some(5.0), None),
+                ("kam1_sky_clouds", None, Some(0.5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn sky_with_missing_properties() {
+        let config = config(
+            "empty",
+            vec![
+                floats("SkyInfo", &[]),
+                scope(vec![
+                    floats("FogColor", &[1.0, 2.0, 3.0, 4.0]),
+                    // Not enough values
+                    floats("FogRange", &[10.0]),
+                ]),
+            ],
+        );
+
+        let sky = SkyDefinition::from(&config);
+        assert_eq!(sky.fog_color, Some(vec4(1.0, 2.0, 3.0, 4.0)));
+        assert_eq!(sky.fog_range, None);
+        assert_eq!(sky.far_scene_range, None);
+        assert!(sky.sun.is_none());
+        assert!(sky.dome.is_none());
+    }
+}
@@ -0,0 +1,54 @@
+use crate::node::{LazyData, NodeData};
+use zenit_proc::{ext_repr, PackedData};
+
+/// A bank of sound effects, stored in `emo_` nodes.
+///
+/// Every sample is identified by a hashed name, so the original names are only recoverable if
+/// they're known up front (for example, from sound config files referencing them).
+#[derive(Debug, Clone, NodeData)]
+pub struct LevelSoundBank {
+    #[node("INFO")]
+    pub info: LevelSoundBankInfo,
+    #[nodes("SMPL")]
+    pub samples: Vec<LevelSoundSample>,
+}
+
+#[derive(Debug, Clone, PackedData)]
+pub struct LevelSoundBankInfo {
+    /// Hashed name of the sound bank
+    pub name_hash: u32,
+    pub sample_count: u32,
+}
+
+#[derive(Debug, Clone, NodeData)]
+pub struct LevelSoundSample {
+    #[node("INFO")]
+    pub info: LevelSoundSampleInfo,
+    /// Encoded sample data. Use [`crate::audio::decode_samples`] to turn it into PCM.
+    #[node("DATA")]
+    pub data: LazyData<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PackedData)]
+pub struct LevelSoundSampleInfo {
+    /// Hashed name of the sample
+    pub name_hash: u32,
+    pub format: SoundFormat,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Size of a single encoded block in bytes. Only meaningful for ADPCM data.
+    pub block_align: u16,
+    /// Amount of samples per channel
+    pub frame_count: u32,
+}
+
+/// Sample encodings used by PC versions of the game.
+#[ext_repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, PackedData)]
+#[parse_as(u32)]
+pub enum SoundFormat {
+    /// Signed 16-bit little endian PCM, channels interleaved
+    Pcm16 = 1,
+    /// 4-bit IMA ADPCM, in the block layout used by WAV files
+    ImaAdpcm = 2,
+}
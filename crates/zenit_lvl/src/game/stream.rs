@@ -0,0 +1,77 @@
+use super::SoundFormat;
+use crate::node::{read_node_header, LazyData, NodeData, NodeRead};
+use anyhow::ensure;
+use std::io::{Read, Seek};
+use zenit_proc::PackedData;
+use zenit_utils::AnyResult;
+
+/// Contents of a stream file, used for music and voice-over.
+///
+/// Unlike regular level files, stream files are meant to be read piece by piece, so all sample
+/// data is stored lazily. Use [`crate::audio::StreamDecoder`] to decode it.
+#[derive(Debug, Clone, NodeData)]
+pub struct LevelStreamFile {
+    #[nodes("strm")]
+    pub streams: Vec<LevelStream>,
+}
+
+/// A single stream, split into segments sharing the same encoding.
+#[derive(Debug, Clone, NodeData)]
+pub struct LevelStream {
+    #[node("INFO")]
+    pub info: LevelStreamInfo,
+    #[nodes("SEGM")]
+    pub segments: Vec<LevelStreamSegment>,
+}
+
+#[derive(Debug, Clone, PackedData)]
+pub struct LevelStreamInfo {
+    /// Hashed name of the stream
+    pub name_hash: u32,
+    pub format: SoundFormat,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Size of a single encoded block in bytes. Only meaningful for ADPCM data.
+    pub block_align: u16,
+}
+
+#[derive(Debug, Clone, NodeData)]
+pub struct LevelStreamSegment {
+    #[node("INFO")]
+    pub info: LevelStreamSegmentInfo,
+    #[node("DATA")]
+    pub data: LazyData<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, PackedData)]
+pub struct LevelStreamSegmentInfo {
+    /// Hashed name of the segment
+    pub name_hash: u32,
+    /// Amount of samples per channel
+    pub frame_count: u32,
+}
+
+impl LevelStreamFile {
+    /// Reads the stream file's metadata, starting at its `ucfb` root node.
+    pub fn read_from<R: Read + Seek>(r: &mut R) -> AnyResult<Self> {
+        let root = read_node_header(r)?;
+        ensure!(root.name == b"ucfb", "not a valid stream file");
+        Self::read_node_at(r, root)
+    }
+
+    /// Finds a stream by its name hash.
+    pub fn find_stream(&self, name_hash: u32) -> Option<&LevelStream> {
+        self.streams
+            .iter()
+            .find(|stream| stream.info.name_hash == name_hash)
+    }
+}
+
+impl LevelStream {
+    /// Finds a segment by its name hash.
+    pub fn find_segment(&self, name_hash: u32) -> Option<&LevelStreamSegment> {
+        self.segments
+            .iter()
+            .find(|segment| segment.info.name_hash == name_hash)
+    }
+}
@@ -33,14 +33,10 @@ pub struct LevelTextureFormat {
     /// Specifies whether linear texture filtering should be enabled, or not.
     /// Presence of a node means that the texture should be unfiltered.
     #[cfg(feature = "zenit_extensions")]
-    #[nodes("NFLT")]
-    pub unfiltered: Vec<ZLevelTextureFiltering>,
+    #[flag("NFLT")]
+    pub unfiltered: bool,
 }
 
-/// A bit sloppy, this is a marker struct for `FMT_:NFLT`
-#[derive(Debug, Clone, NodeData)]
-pub struct ZLevelTextureFiltering {}
-
 #[derive(Debug, Clone, NodeData)]
 pub struct LevelTextureFace {
     #[nodes("LVL_")]
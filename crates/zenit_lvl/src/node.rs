@@ -7,13 +7,25 @@ use anyhow::{bail, ensure};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 use zenit_utils::{
-    ok,
+    align, ok,
     packed::{PackedData, PackedWriteExt},
     AnyResult, SeekableTakeExt,
 };
 
 pub use zenit_lvl_proc::NodeData;
 
+mod error;
+pub use error::*;
+
+mod owned;
+pub use owned::*;
+
+mod policy;
+pub use policy::*;
+
+mod slice;
+pub use slice::*;
+
 /// Represents a 4-byte name of the chunk. The chunk can either be a short ASCII string, or
 /// an arbitrary 32-bit number (like a hash). In the latter case, the number is little endian
 /// encoded.
@@ -87,6 +99,15 @@ impl NodeHeader {
     }
 }
 
+/// Object safe combination of [`Read`] and [`Seek`].
+///
+/// Recursive node structures (like level data packs containing more level data) can't be read
+/// with generic readers directly, as [`NodeRead::read_node_at`] wraps the reader in another
+/// layer every time, which never ends at compile time. Erasing the reader into a
+/// `&mut dyn ReadSeek` at the recursion point breaks that cycle.
+pub trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek + ?Sized> ReadSeek for T {}
+
 /// Trait for node reading/writing node hierarchies. Can be derived.
 pub trait NodeData: NodeRead + NodeWrite {}
 impl<T: NodeRead + NodeWrite> NodeData for T {}
@@ -101,13 +122,11 @@ pub trait NodeRead: Sized + Clone {
     fn read_node_payload<R: Read + Seek>(r: &mut R, meta: NodeHeader) -> AnyResult<Self>;
 
     /// Wrapper around [`Self::read_node_payload`], that pre-seeks into the payload. Otherwise,
-    /// the behavior matches that function.
+    /// the behavior matches that function, except that errors are turned into [`NodeReadError`]s
+    /// pointing at this node.
     fn read_node_at<R: Read + Seek>(r: &mut R, meta: NodeHeader) -> AnyResult<Self> {
         meta.seek_to_payload(r)?;
-        Ok(Self::read_node_payload(
-            &mut r.seekable_take(meta.size as u64),
-            meta,
-        )?)
+        Self::read_node_payload(&mut r.seekable_take(meta.size as u64), meta).at_node(meta)
     }
 }
 
@@ -211,6 +230,23 @@ where
 
 /// Attempts to parse the given node as a parent node with a list of child nodes.
 pub fn read_node_children<R>(r: &mut R, header: NodeHeader) -> io::Result<Vec<NodeHeader>>
+where
+    R: Read + Seek,
+{
+    Ok(read_node_hierarchy(r, header)?.headers)
+}
+
+/// Child nodes of a parent node, as found by [`read_node_hierarchy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeChildren {
+    /// Value of the node count prefix, if the children are preceded by one
+    pub count_prefix: Option<u32>,
+    pub headers: Vec<NodeHeader>,
+}
+
+/// Like [`read_node_children`], but also reports whether the children are preceded by a node
+/// count prefix.
+pub fn read_node_hierarchy<R>(r: &mut R, header: NodeHeader) -> io::Result<NodeChildren>
 where
     R: Read + Seek,
 {
@@ -235,20 +271,22 @@ fn read_children_impl<R>(
     r: &mut R,
     header: NodeHeader,
     count_prefix: bool,
-) -> io::Result<Vec<NodeHeader>>
+) -> io::Result<NodeChildren>
 where
     R: Read + Seek,
 {
     r.seek(SeekFrom::Start(header.header_position + 8))?;
     let mut r = r.take(header.size as u64); // TODO: seekable take here
 
-    let mut remaining_nodes = match count_prefix {
+    let count_prefix = match count_prefix {
         true => Some(r.read_u32::<LE>()?),
         false => None,
     };
+    let mut remaining_nodes = count_prefix;
 
     let mut children = match remaining_nodes {
-        Some(nodes) => Vec::with_capacity(nodes as usize),
+        // The prefix may just as well be garbage, every node takes at least 8 bytes
+        Some(nodes) => Vec::with_capacity((nodes as usize).min(header.size as usize / 8)),
         None => Vec::new(),
     };
 
@@ -310,7 +348,10 @@ where
         })
     }
 
-    Ok(children)
+    Ok(NodeChildren {
+        count_prefix,
+        headers: children,
+    })
 }
 
 /// A builder-style node writer.
@@ -323,12 +364,23 @@ where
 ///     object.
 ///  4. Once you're finished, either call [`NodeWriter::finish`] manually or drop the writer. Note,
 ///     that the drop implementations unwraps any IO errors returned by `finish`.
+///
+/// ## Alignment
+/// Like in the game's files, every finished node is followed by zero padding, so that whatever
+/// comes after it starts at a multiple of [`DEFAULT_NODE_ALIGNMENT`] (counting from the start of
+/// the stream). The padding isn't included in the node's own size, but is included in its
+/// parent's. The alignment can be changed with [`NodeWriter::set_alignment`], and is inherited
+/// by nested node writers.
 pub struct NodeWriter<'w, W: Write + Seek> {
     w: &'w mut W,
     data_start: u64,
+    alignment: u64,
     finished: bool,
 }
 
+/// Alignment of nodes written by a [`NodeWriter`], unless specified otherwise.
+pub const DEFAULT_NODE_ALIGNMENT: u64 = 4;
+
 impl<'w, W: Write + Seek> NodeWriter<'w, W> {
     pub fn new(w: &'w mut W, name: impl Into<NodeName>) -> AnyResult<Self> {
         let data_start = w.stream_position()?;
@@ -338,10 +390,25 @@ impl<'w, W: Write + Seek> NodeWriter<'w, W> {
         Ok(Self {
             w,
             data_start,
+            alignment: DEFAULT_NODE_ALIGNMENT,
             finished: false,
         })
     }
 
+    /// Sets the alignment used for padding this node, and all nodes created by this writer
+    /// afterwards. An alignment of 1 disables padding.
+    ///
+    /// ## Panics
+    /// Panics if the alignment is 0.
+    pub fn set_alignment(&mut self, alignment: u64) {
+        assert!(alignment > 0, "alignment must be non-zero");
+        self.alignment = alignment;
+    }
+
+    pub fn alignment(&self) -> u64 {
+        self.alignment
+    }
+
     /// Creates a new node, with contents from a [`PackedData`] or [`NodeData`] object.
     pub fn write_node(&mut self, name: impl Into<NodeName>, data: impl NodeData + 'w) -> AnyResult {
         assert!(!self.finished);
@@ -362,13 +429,14 @@ impl<'w, W: Write + Seek> NodeWriter<'w, W> {
         assert!(!self.finished);
 
         let mut writer = NodeWriter::new(self.w, name.into())?;
+        writer.alignment = self.alignment;
         (f)(&mut writer)?;
         writer.finish()?;
 
         ok()
     }
 
-    /// Finishes writing the node, by marking its final size in the stream.
+    /// Finishes writing the node, by marking its final size in the stream and padding it.
     pub fn finish(&mut self) -> AnyResult {
         if self.finished {
             return ok();
@@ -382,6 +450,9 @@ impl<'w, W: Write + Seek> NodeWriter<'w, W> {
         self.w.write_u32::<LE>(data_size as u32)?;
         self.w.seek(SeekFrom::Start(data_end))?;
 
+        let padding = align(data_end, self.alignment) - data_end;
+        io::copy(&mut io::repeat(0).take(padding), self.w)?;
+
         self.finished = true;
         ok()
     }
@@ -429,3 +500,295 @@ impl<'w, W: Write + Seek> Drop for NodeWriter<'w, W> {
         }
     }
 }
+
+/// Builds a raw node, followed by `padding` zero bytes.
+#[cfg(test)]
+pub(crate) fn raw_node(name: &[u8; 4], payload: &[u8], padding: usize) -> Vec<u8> {
+    let mut node = Vec::with_capacity(8 + payload.len() + padding);
+    node.extend_from_slice(name);
+    node.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    node.extend_from_slice(payload);
+    node.resize(node.len() + padding, 0);
+    node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A level file resembling the built-in one: a texture with an unaligned name, and a shader
+    fn texture_fixture() -> Vec<u8> {
+        let format = [
+            raw_node(b"INFO", &[0x15, 0, 0, 0, 2, 0, 2, 0, 1, 0, 1, 0], 0),
+            raw_node(b"FACE", &raw_node(b"LVL_", &[0xff; 16], 0), 0),
+        ]
+        .concat();
+
+        let texture = [
+            raw_node(b"NAME", b"zenit_error\0", 0),
+            raw_node(b"INFO", &[1, 0, 0, 0, 0x15, 0, 0, 0], 0),
+            raw_node(b"FMT_", &format, 0),
+        ]
+        .concat();
+
+        let shader = [
+            raw_node(b"NAME", b"blit\0", 3),
+            raw_node(b"CODE", b"fn main() {}", 0),
+        ]
+        .concat();
+
+        let root = [
+            raw_node(b"tex_", &texture, 0),
+            raw_node(b"WGSL", &shader, 0),
+        ]
+        .concat();
+        raw_node(b"ucfb", &root, 0)
+    }
+
+    fn write_texture_fixture(w: &mut Cursor<Vec<u8>>) -> AnyResult {
+        let mut writer = NodeWriter::new(w, b"ucfb")?;
+        writer.build_node(b"tex_", |writer| {
+            writer.write_node(b"NAME", b"zenit_error\0".to_vec())?;
+            writer.write_node(b"INFO", [1u32, 0x15])?;
+            writer.build_node(b"FMT_", |writer| {
+                writer.write_node(b"INFO", [0x15u32, 0x0002_0002, 0x0001_0001])?;
+                writer.build_node(b"FACE", |writer| writer.write_node(b"LVL_", [0xffu8; 16]))
+            })
+        })?;
+        writer.build_node(b"WGSL", |writer| {
+            writer.write_node(b"NAME", b"blit\0".to_vec())?;
+            writer.write_node(b"CODE", b"fn main() {}".to_vec())
+        })?;
+        writer.finish()
+    }
+
+    #[test]
+    fn writer_pads_nodes_to_alignment() {
+        let mut w = Cursor::new(Vec::new());
+        write_texture_fixture(&mut w).unwrap();
+        assert_eq!(w.into_inner(), texture_fixture());
+    }
+
+    #[test]
+    fn writer_alignment_is_configurable() {
+        let mut w = Cursor::new(Vec::new());
+        let mut writer = NodeWriter::new(&mut w, b"ucfb").unwrap();
+        writer.set_alignment(8);
+        writer.write_node(b"NAME", b"abc\0".to_vec()).unwrap();
+        writer
+            .build_node(b"DATA", |writer| {
+                writer.set_alignment(1);
+                writer.write_all(b"xyz")?;
+                ok()
+            })
+            .unwrap();
+        writer.write_node(b"END_", b"!".to_vec()).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let expected = raw_node(
+            b"ucfb",
+            &[
+                raw_node(b"NAME", b"abc\0", 4),
+                raw_node(b"DATA", b"xyz", 0),
+                raw_node(b"END_", b"!", 4),
+            ]
+            .concat(),
+            0,
+        );
+        assert_eq!(w.into_inner(), expected);
+    }
+
+    #[test]
+    fn owned_nodes_follow_writer_alignment() {
+        let node = OwnedNode::read_from(&mut Cursor::new(texture_fixture())).unwrap();
+        let mut expected = Cursor::new(Vec::new());
+        write_texture_fixture(&mut expected).unwrap();
+
+        let mut w = Cursor::new(Vec::new());
+        let mut writer = NodeWriter::new(&mut w, b"ucfb").unwrap();
+        for child in node.children().unwrap() {
+            writer.write_node(child.name, child.clone()).unwrap();
+        }
+        writer.finish().unwrap();
+        drop(writer);
+
+        assert_eq!(w.into_inner(), expected.into_inner());
+    }
+
+    #[test]
+    fn read_errors_carry_node_path() {
+        #[derive(Debug, Clone, NodeData)]
+        struct Outer {
+            #[node("INNR")]
+            inner: Inner,
+        }
+
+        #[derive(Debug, Clone, NodeData)]
+        struct Inner {
+            #[node("DATA")]
+            data: u32,
+        }
+
+        let inner = raw_node(b"DATA", &[1, 2], 2);
+        let file = raw_node(b"ucfb", &raw_node(b"INNR", &inner, 0), 0);
+
+        let error = Outer::read_node_at(
+            &mut Cursor::new(&file),
+            NodeSlice::root(&file).unwrap().header(),
+        )
+        .unwrap_err();
+        let error = error.downcast_ref::<NodeReadError>().unwrap();
+
+        assert_eq!(error.offset(), Some(16));
+        assert_eq!(error.field(), Some("Inner::data"));
+        assert_eq!(
+            error.to_string(),
+            "failed to read node `ucfb/INNR/DATA` @ 0x10 (in Inner::data)"
+        );
+    }
+
+    #[test]
+    fn unknown_nodes_follow_policy() {
+        #[derive(Debug, Clone, NodeData)]
+        struct Lenient {
+            #[node("NAME")]
+            name: u32,
+        }
+
+        #[derive(Debug, Clone, NodeData)]
+        #[node_data(unknown = "error")]
+        struct Strict {
+            #[node("NAME")]
+            name: u32,
+        }
+
+        #[derive(Debug, Clone, NodeData)]
+        struct Collecting {
+            #[node("NAME")]
+            name: u32,
+            #[unknown_nodes]
+            unknown: Vec<(NodeName, Vec<u8>)>,
+        }
+
+        let file = raw_node(
+            b"ucfb",
+            &[
+                raw_node(b"NAME", &[1, 0, 0, 0], 0),
+                raw_node(b"XTRA", b"abc", 1),
+            ]
+            .concat(),
+            0,
+        );
+        let root = NodeSlice::root(&file).unwrap();
+
+        assert_eq!(root.read::<Lenient>().unwrap().name, 1);
+        assert!(UnknownNodePolicy::Error
+            .apply(|| root.read::<Lenient>())
+            .is_err());
+
+        let error = root.read::<Strict>().unwrap_err();
+        assert_eq!(error.to_string(), "failed to read node `ucfb/XTRA` @ 0x14");
+
+        let collected = UnknownNodePolicy::Error
+            .apply(|| root.read::<Collecting>())
+            .unwrap();
+        assert_eq!(collected.unknown, [(NodeName(*b"XTRA"), b"abc".to_vec())]);
+
+        let mut w = Cursor::new(Vec::new());
+        let mut writer = NodeWriter::new(&mut w, b"ucfb").unwrap();
+        collected.write_node(&mut writer).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+        assert_eq!(w.into_inner(), file);
+    }
+
+    #[test]
+    fn optional_nodes_round_trip() {
+        fn default_seven() -> u32 {
+            7
+        }
+
+        #[derive(Debug, Clone, PartialEq, NodeData)]
+        enum Expr {
+            #[node("NUMB")]
+            Number(u32),
+            #[node("BYTE")]
+            Byte(u8),
+        }
+
+        #[derive(Debug, Clone, PartialEq, NodeData)]
+        struct Optional {
+            #[node("OPTN")]
+            optional: Option<u32>,
+            #[node("DFLT", default)]
+            default: u32,
+            #[node("SEVN", default = "default_seven")]
+            seven: u32,
+            #[flag("FLAG")]
+            flag: bool,
+            #[node("EXPR")]
+            expr: Expr,
+            #[nodes("EXPS")]
+            exprs: Vec<Expr>,
+        }
+
+        fn round_trip(value: &Optional) -> Optional {
+            let mut w = Cursor::new(Vec::new());
+            let mut writer = NodeWriter::new(&mut w, b"ucfb").unwrap();
+            value.write_node(&mut writer).unwrap();
+            writer.finish().unwrap();
+            drop(writer);
+
+            let file = w.into_inner();
+            NodeSlice::root(&file).unwrap().read().unwrap()
+        }
+
+        // Missing nodes are replaced, and the variant is selected by the name of the child node
+        let file = raw_node(
+            b"ucfb",
+            &[
+                raw_node(b"FLAG", &[], 0),
+                raw_node(b"EXPR", &raw_node(b"BYTE", &[5], 3), 0),
+            ]
+            .concat(),
+            0,
+        );
+        let read = NodeSlice::root(&file).unwrap().read::<Optional>().unwrap();
+        assert_eq!(
+            read,
+            Optional {
+                optional: None,
+                default: 0,
+                seven: 7,
+                flag: true,
+                expr: Expr::Byte(5),
+                exprs: vec![],
+            }
+        );
+        assert_eq!(round_trip(&read), read);
+
+        let full = Optional {
+            optional: Some(1),
+            default: 2,
+            seven: 3,
+            flag: false,
+            expr: Expr::Number(4),
+            exprs: vec![Expr::Byte(5), Expr::Number(6)],
+        };
+        assert_eq!(round_trip(&full), full);
+
+        // An enum node must hold exactly one variant
+        let file = raw_node(
+            b"ucfb",
+            &raw_node(
+                b"EXPR",
+                &[raw_node(b"BYTE", &[5], 3), raw_node(b"BYTE", &[6], 3)].concat(),
+                0,
+            ),
+            0,
+        );
+        assert!(NodeSlice::root(&file).unwrap().read::<Optional>().is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use super::{NodeHeader, NodeName};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+use zenit_utils::{AnyResult, AsciiDisplay};
+
+/// Error that occurred while reading a node, carrying the location of the failure.
+///
+/// The error is built up while it's propagated through [`super::NodeRead::read_node_at`] calls,
+/// each adding the node it was reading to the front of the path. It displays as the path of node
+/// names, with the offset of the innermost node, for example:
+/// `ucfb/tex_/FMT_/FACE/LVL_/BODY @ 0x1234`. The original error is available as its source.
+///
+/// Since readers return [`anyhow::Error`]s, the error can be retrieved with
+/// [`anyhow::Error::downcast_ref`].
+#[derive(Debug)]
+pub struct NodeReadError {
+    /// Nodes leading up to the failure, from the outermost one.
+    pub path: Vec<NodePathSegment>,
+    pub source: anyhow::Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodePathSegment {
+    pub name: NodeName,
+    /// Offset of the node's header in the file
+    pub offset: u64,
+    /// Field of the parent structure the node was read into, like `LevelTexture::formats`,
+    /// if it's known.
+    pub field: Option<&'static str>,
+}
+
+impl NodeReadError {
+    /// Offset of the innermost node in the path.
+    pub fn offset(&self) -> Option<u64> {
+        self.path.last().map(|segment| segment.offset)
+    }
+
+    /// Innermost known field in the path.
+    pub fn field(&self) -> Option<&'static str> {
+        self.path.iter().rev().find_map(|segment| segment.field)
+    }
+
+    /// Adds the node to the front of the error's path. Errors other than [`NodeReadError`]
+    /// become the source of a new one.
+    pub fn at_node(error: anyhow::Error, header: NodeHeader) -> anyhow::Error {
+        let segment = NodePathSegment {
+            name: header.name,
+            offset: header.header_position,
+            field: None,
+        };
+
+        match error.downcast::<NodeReadError>() {
+            Ok(mut error) => {
+                error.path.insert(0, segment);
+                error.into()
+            }
+            Err(error) => NodeReadError {
+                path: vec![segment],
+                source: error,
+            }
+            .into(),
+        }
+    }
+
+    /// Marks the outermost node of the error's path as read into the given field. Errors other
+    /// than [`NodeReadError`] are returned as is.
+    pub fn in_field(error: anyhow::Error, field: &'static str) -> anyhow::Error {
+        match error.downcast::<NodeReadError>() {
+            Ok(mut error) => {
+                if let Some(segment) = error.path.first_mut() {
+                    segment.field.get_or_insert(field);
+                }
+                error.into()
+            }
+            Err(error) => error,
+        }
+    }
+}
+
+impl Display for NodeReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to read node `")?;
+        for (i, segment) in self.path.iter().enumerate() {
+            if i > 0 {
+                write!(f, "/")?;
+            }
+            write!(f, "{}", AsciiDisplay(segment.name.as_bytes()))?;
+        }
+        write!(f, "`")?;
+
+        if let Some(offset) = self.offset() {
+            write!(f, " @ {offset:#x}")?;
+        }
+        if let Some(field) = self.field() {
+            write!(f, " (in {field})")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for NodeReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// Shorthands for [`NodeReadError`]'s functions on results.
+pub trait NodeResultExt<T> {
+    /// See [`NodeReadError::at_node`].
+    fn at_node(self, header: NodeHeader) -> AnyResult<T>;
+
+    /// See [`NodeReadError::in_field`].
+    fn in_field(self, field: &'static str) -> AnyResult<T>;
+}
+
+impl<T> NodeResultExt<T> for AnyResult<T> {
+    fn at_node(self, header: NodeHeader) -> AnyResult<T> {
+        self.map_err(|error| NodeReadError::at_node(error, header))
+    }
+
+    fn in_field(self, field: &'static str) -> AnyResult<T> {
+        self.map_err(|error| NodeReadError::in_field(error, field))
+    }
+}
@@ -0,0 +1,264 @@
+use super::{
+    read_node_header, read_node_hierarchy, read_node_payload, NodeHeader, NodeName, NodeRead,
+    NodeWrite, NodeWriter, ReadSeek,
+};
+use byteorder::{WriteBytesExt, LE};
+use std::io::{self, Read, Seek, Write};
+use zenit_utils::{ok, AnyResult};
+
+/// A node loaded into memory without any knowledge of its schema.
+///
+/// Whether a node is a parent node is guessed with the same heuristic as
+/// [`super::read_node_children`], so leaf nodes whose payload happens to look like a list of
+/// nodes will be loaded as parent nodes. This doesn't affect writing, as everything needed to
+/// reproduce the original bytes exactly (count prefixes and padding) is kept around, so unknown
+/// nodes can be safely edited and written back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedNode {
+    pub name: NodeName,
+    pub contents: OwnedNodeContents,
+    /// Amount of zero bytes following this node inside its parent, usually aligning the next
+    /// node to 4 bytes.
+    pub padding: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedNodeContents {
+    Payload(Vec<u8>),
+    Children {
+        /// Node count prefix written before the children, if there is one. It's kept as is,
+        /// even if it doesn't match the actual amount of children.
+        count_prefix: Option<u32>,
+        /// Amount of zero bytes between the count prefix (or the start of the payload) and
+        /// the first child
+        leading_padding: u32,
+        children: Vec<OwnedNode>,
+    },
+}
+
+impl OwnedNode {
+    /// Creates a leaf node, with no padding.
+    pub fn new_payload(name: impl Into<NodeName>, payload: Vec<u8>) -> Self {
+        Self {
+            name: name.into(),
+            contents: OwnedNodeContents::Payload(payload),
+            padding: 0,
+        }
+    }
+
+    /// Creates a parent node without a count prefix, with no padding.
+    pub fn new_parent(name: impl Into<NodeName>, children: Vec<OwnedNode>) -> Self {
+        Self {
+            name: name.into(),
+            contents: OwnedNodeContents::Children {
+                count_prefix: None,
+                leading_padding: 0,
+                children,
+            },
+            padding: 0,
+        }
+    }
+
+    /// Reads the node starting at the current position of the reader.
+    pub fn read_from<R: Read + Seek>(r: &mut R) -> AnyResult<Self> {
+        let header = read_node_header(r)?;
+        Self::read_node_at(r, header)
+    }
+
+    /// Writes the node, followed by its padding.
+    pub fn write_into<W: Write + Seek>(&self, w: &mut W) -> AnyResult {
+        let mut writer = NodeWriter::new(w, self.name)?;
+        writer.set_alignment(1);
+        self.write_node(&mut writer)?;
+        writer.finish()?;
+        drop(writer);
+
+        write_padding(w, self.padding)?;
+        ok()
+    }
+
+    /// Returns the payload, if this is a leaf node.
+    pub fn payload(&self) -> Option<&[u8]> {
+        match &self.contents {
+            OwnedNodeContents::Payload(payload) => Some(payload),
+            OwnedNodeContents::Children { .. } => None,
+        }
+    }
+
+    /// Returns the children, if this is a parent node.
+    pub fn children(&self) -> Option<&[OwnedNode]> {
+        match &self.contents {
+            OwnedNodeContents::Payload(_) => None,
+            OwnedNodeContents::Children { children, .. } => Some(children),
+        }
+    }
+
+    /// Returns the children mutably, if this is a parent node.
+    pub fn children_mut(&mut self) -> Option<&mut Vec<OwnedNode>> {
+        match &mut self.contents {
+            OwnedNodeContents::Payload(_) => None,
+            OwnedNodeContents::Children { children, .. } => Some(children),
+        }
+    }
+
+    /// Returns the first child with the given name.
+    pub fn find_child(&self, name: impl Into<NodeName>) -> Option<&OwnedNode> {
+        let name = name.into();
+        self.children()?.iter().find(|child| child.name == name)
+    }
+
+    /// Size of the node's payload, as it'd be written in its header.
+    pub fn payload_size(&self) -> u64 {
+        match &self.contents {
+            OwnedNodeContents::Payload(payload) => payload.len() as u64,
+            OwnedNodeContents::Children {
+                count_prefix,
+                leading_padding,
+                children,
+            } => {
+                let prefix_size = if count_prefix.is_some() { 4 } else { 0 };
+                let children_size: u64 = children.iter().map(OwnedNode::total_size).sum();
+                prefix_size + *leading_padding as u64 + children_size
+            }
+        }
+    }
+
+    /// Size of the whole node, including its header and padding.
+    pub fn total_size(&self) -> u64 {
+        8 + self.payload_size() + self.padding as u64
+    }
+
+    // Recursion goes through a type erased reader (see [`ReadSeek`] for details)
+    fn read_erased(mut r: &mut dyn ReadSeek, header: NodeHeader) -> AnyResult<Self> {
+        let payload_start = header.header_position + 8;
+        let payload_end = payload_start + header.size as u64;
+
+        // Nodes without any children are always treated as leaf nodes, otherwise every empty
+        // node and every node with a single u32 would be considered a parent node
+        let hierarchy = read_node_hierarchy(&mut r, header)
+            .ok()
+            .filter(|hierarchy| !hierarchy.headers.is_empty());
+
+        let Some(hierarchy) = hierarchy else {
+            return Ok(Self {
+                name: header.name,
+                contents: OwnedNodeContents::Payload(read_node_payload(&mut r, header)?),
+                padding: 0,
+            });
+        };
+
+        let children_start = match hierarchy.count_prefix {
+            Some(_) => payload_start + 4,
+            None => payload_start,
+        };
+
+        let mut children = Vec::with_capacity(hierarchy.headers.len());
+        for (i, &child_header) in hierarchy.headers.iter().enumerate() {
+            let mut child = Self::read_erased(r, child_header)?;
+            let next_start = match hierarchy.headers.get(i + 1) {
+                Some(next) => next.header_position,
+                None => payload_end,
+            };
+            child.padding =
+                (next_start - child_header.header_position - 8 - child_header.size as u64) as u32;
+            children.push(child);
+        }
+
+        Ok(Self {
+            name: header.name,
+            contents: OwnedNodeContents::Children {
+                count_prefix: hierarchy.count_prefix,
+                leading_padding: (hierarchy.headers[0].header_position - children_start) as u32,
+                children,
+            },
+            padding: 0,
+        })
+    }
+}
+
+impl NodeRead for OwnedNode {
+    fn read_node_payload<R: Read + Seek>(r: &mut R, meta: NodeHeader) -> AnyResult<Self> {
+        Self::read_erased(r, meta)
+    }
+}
+
+/// Writes only the node's contents, its padding is up to the parent. Children are written with
+/// their recorded padding, regardless of the writer's alignment.
+impl NodeWrite for OwnedNode {
+    fn write_node<W: Write + Seek>(&self, writer: &mut NodeWriter<W>) -> AnyResult {
+        match &self.contents {
+            OwnedNodeContents::Payload(payload) => writer.write_all(payload)?,
+            OwnedNodeContents::Children {
+                count_prefix,
+                leading_padding,
+                children,
+            } => {
+                if let Some(count) = count_prefix {
+                    writer.write_u32::<LE>(*count)?;
+                }
+                write_padding(writer, *leading_padding)?;
+
+                for child in children {
+                    writer.build_node(child.name, |writer| {
+                        writer.set_alignment(1);
+                        child.write_node(writer)
+                    })?;
+                    write_padding(writer, child.padding)?;
+                }
+            }
+        }
+        ok()
+    }
+}
+
+fn write_padding(w: &mut impl Write, padding: u32) -> io::Result<()> {
+    io::copy(&mut io::repeat(0).take(padding as u64), w)?;
+    ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::raw_node;
+    use std::io::Cursor;
+
+    #[test]
+    fn owned_nodes_round_trip() {
+        let texture = [
+            raw_node(b"NAME", b"zenit_error\0", 0),
+            raw_node(b"INFO", &[1, 0, 0, 0, 0x15, 0, 0, 0], 0),
+        ]
+        .concat();
+        let shader = [
+            raw_node(b"NAME", b"blit\0", 3),
+            raw_node(b"CODE", b"fn main() {}", 0),
+        ]
+        .concat();
+
+        // Count prefixes, nodes named by hashes and uneven padding have to be preserved as well
+        let pack = [
+            3u32.to_le_bytes().to_vec(),
+            vec![0; 4],
+            raw_node(&0x266561d8u32.to_le_bytes(), &[1, 2, 3], 1),
+            raw_node(b"NAME", b"all\0", 8),
+            raw_node(b"DATA", &[0, 0, 0x80, 0x3f], 0),
+        ]
+        .concat();
+
+        let root = [
+            raw_node(b"tex_", &texture, 0),
+            raw_node(b"WGSL", &shader, 0),
+            raw_node(b"lvl_", &pack, 0),
+        ]
+        .concat();
+        let fixtures = [raw_node(b"tex_", &texture, 0), raw_node(b"ucfb", &root, 0)];
+
+        for fixture in fixtures {
+            let node = OwnedNode::read_from(&mut Cursor::new(&fixture)).unwrap();
+            let mut w = Cursor::new(Vec::new());
+            node.write_into(&mut w).unwrap();
+            assert_eq!(w.into_inner(), fixture);
+            assert_eq!(node.total_size(), fixture.len() as u64);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use super::{NodeHeader, NodeResultExt};
+use anyhow::anyhow;
+use log::warn;
+use std::cell::Cell;
+use zenit_utils::{ok, AnyResult, AsciiDisplay};
+
+thread_local! {
+    static CURRENT_POLICY: Cell<UnknownNodePolicy> = const { Cell::new(UnknownNodePolicy::Ignore) };
+}
+
+/// Decides what [`super::NodeData`] derived readers do with child nodes that don't match any of
+/// their fields.
+///
+/// Types can pick a policy with the `#[node_data(unknown = "...")]` attribute, or collect unknown
+/// nodes into an `#[unknown_nodes]` field. Otherwise, the current policy of the thread is used,
+/// which is [`UnknownNodePolicy::Ignore`] unless changed with [`UnknownNodePolicy::apply`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownNodePolicy {
+    /// Unknown nodes are skipped
+    #[default]
+    Ignore,
+    /// Unknown nodes are skipped, but logged as warnings
+    Warn,
+    /// Unknown nodes fail the read with a [`super::NodeReadError`] pointing at them
+    Error,
+}
+
+impl UnknownNodePolicy {
+    /// Returns the policy of the current thread.
+    pub fn current() -> Self {
+        CURRENT_POLICY.with(Cell::get)
+    }
+
+    /// Runs the function with this policy set for the current thread.
+    ///
+    /// ## Example
+    /// ```
+    /// # use zenit_lvl::node::UnknownNodePolicy;
+    /// UnknownNodePolicy::Error.apply(|| {
+    ///     assert_eq!(UnknownNodePolicy::current(), UnknownNodePolicy::Error);
+    /// });
+    /// assert_eq!(UnknownNodePolicy::current(), UnknownNodePolicy::Ignore);
+    /// ```
+    pub fn apply<T>(self, f: impl FnOnce() -> T) -> T {
+        struct Restore(UnknownNodePolicy);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT_POLICY.with(|policy| policy.set(self.0));
+            }
+        }
+
+        let _restore = Restore(CURRENT_POLICY.with(|policy| policy.replace(self)));
+        f()
+    }
+
+    /// Used by derived readers to handle an unknown child of a node read as `type_name`.
+    #[doc(hidden)]
+    pub fn handle(self, type_name: &str, child: NodeHeader) -> AnyResult {
+        match self {
+            UnknownNodePolicy::Ignore => ok(),
+            UnknownNodePolicy::Warn => {
+                warn!(
+                    "Unknown node `{}` @ {:#x} in {type_name}",
+                    AsciiDisplay(child.name.as_bytes()),
+                    child.header_position,
+                );
+                ok()
+            }
+            UnknownNodePolicy::Error => Err(anyhow!("unknown node in {type_name}")).at_node(child),
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use super::{LazyData, NodeData, NodeHeader, NodeName, NodeRead};
+use anyhow::bail;
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::{self, Cursor},
+    ops::Deref,
+    path::Path,
+};
+use zenit_utils::AnyResult;
+
+/// A level file mapped into memory, which can be read with [`NodeSlice`]s.
+pub struct MappedLevelFile {
+    map: Mmap,
+}
+
+impl MappedLevelFile {
+    /// Maps the file into memory.
+    ///
+    /// The file must not be modified by anyone while it's mapped, otherwise its contents may
+    /// change underneath any borrowed slices.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: see the above note about not modifying the file
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Self { map })
+    }
+
+    /// Returns the root node of the file.
+    pub fn root(&self) -> io::Result<NodeSlice<'_>> {
+        NodeSlice::root(&self.map)
+    }
+}
+
+impl Deref for MappedLevelFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+/// A node inside of an in-memory level file.
+///
+/// As opposed to the [`std::io::Read`] based functions, nothing here copies or allocates,
+/// payloads and children are borrowed straight from the file.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeSlice<'a> {
+    file: &'a [u8],
+    header: NodeHeader,
+}
+
+impl<'a> NodeSlice<'a> {
+    /// Returns the node at the start of the file.
+    pub fn root(file: &'a [u8]) -> io::Result<Self> {
+        Self::at(file, 0)
+    }
+
+    /// Returns the node whose header starts at given offset in the file.
+    pub fn at(file: &'a [u8], offset: u64) -> io::Result<Self> {
+        let header = file
+            .get(offset as usize..)
+            .and_then(|rest| rest.get(0..8))
+            .ok_or_else(|| invalid_data("node header out of bounds"))?;
+
+        let header = NodeHeader {
+            header_position: offset,
+            name: NodeName(header[0..4].try_into().unwrap()),
+            size: u32::from_le_bytes(header[4..8].try_into().unwrap()),
+        };
+        Self::new(file, header)
+    }
+
+    /// Wraps a header read from the same file, verifying it's in bounds.
+    pub fn new(file: &'a [u8], header: NodeHeader) -> io::Result<Self> {
+        let end = header.header_position + 8 + header.size as u64;
+        if end > file.len() as u64 {
+            return Err(invalid_data("node went out of bounds"));
+        }
+        Ok(Self { file, header })
+    }
+
+    pub fn header(&self) -> NodeHeader {
+        self.header
+    }
+
+    pub fn name(&self) -> NodeName {
+        self.header.name
+    }
+
+    /// The whole file this node is a part of.
+    pub fn file(&self) -> &'a [u8] {
+        self.file
+    }
+
+    pub fn payload(&self) -> &'a [u8] {
+        let start = self.header.header_position as usize + 8;
+        &self.file[start..start + self.header.size as usize]
+    }
+
+    /// Attempts to parse the node as a parent node, with the same logic as
+    /// [`super::read_node_children`].
+    pub fn children(&self) -> io::Result<NodeSliceChildren<'a>> {
+        // Attempt #1: assume there is no node count prefix
+        // Attempt #2: assume there is a node count prefix
+        [false, true]
+            .into_iter()
+            .filter_map(|count_prefix| NodeSliceChildren::new(*self, count_prefix))
+            .find(|children| children.validate())
+            .ok_or_else(|| invalid_data("node doesn't seem to have a hierarchy"))
+    }
+
+    /// Returns the first child with given name, if the node has a hierarchy.
+    pub fn find_child(&self, name: impl Into<NodeName>) -> Option<NodeSlice<'a>> {
+        let name = name.into();
+        self.children().ok()?.find(|child| child.name() == name)
+    }
+
+    /// Returns a reader over the whole file, usable with the [`std::io::Read`] based functions.
+    /// Headers of this node and its children are valid with it.
+    pub fn reader(&self) -> Cursor<&'a [u8]> {
+        Cursor::new(self.file)
+    }
+
+    /// Reads the node with its [`NodeRead`] implementation.
+    ///
+    /// Reading happens through an in-memory [`Cursor`], so no file IO is involved. Payloads of
+    /// any [`LazyData`] fields can be later borrowed with [`LazyData::read_slice`].
+    pub fn read<T: NodeRead>(&self) -> AnyResult<T> {
+        T::read_node_at(&mut self.reader(), self.header)
+    }
+}
+
+impl<T: NodeData> LazyData<T> {
+    /// Borrows the raw payload of the cached node from the in-memory level file it was read
+    /// from, instead of parsing it.
+    pub fn read_slice<'a>(&self, file: &'a [u8]) -> AnyResult<&'a [u8]> {
+        match self {
+            LazyData::Read(header) => Ok(NodeSlice::new(file, *header)?.payload()),
+            LazyData::Write(_) => bail!("node not cached for reading"),
+        }
+    }
+}
+
+/// Iterator over children of a [`NodeSlice`], see [`NodeSlice::children`].
+#[derive(Debug, Clone)]
+pub struct NodeSliceChildren<'a> {
+    file: &'a [u8],
+    /// Offset of the payload in the file
+    payload_start: usize,
+    payload: &'a [u8],
+    position: usize,
+    remaining_nodes: Option<u32>,
+}
+
+impl<'a> NodeSliceChildren<'a> {
+    fn new(parent: NodeSlice<'a>, count_prefix: bool) -> Option<Self> {
+        let payload = parent.payload();
+        let remaining_nodes = match count_prefix {
+            true => Some(u32::from_le_bytes(payload.get(0..4)?.try_into().unwrap())),
+            false => None,
+        };
+
+        Some(Self {
+            file: parent.file,
+            payload_start: parent.header.header_position as usize + 8,
+            payload,
+            position: if count_prefix { 4 } else { 0 },
+            remaining_nodes,
+        })
+    }
+
+    /// Checks if the whole payload consists of valid nodes.
+    fn validate(&self) -> bool {
+        let mut children = self.clone();
+        loop {
+            match children.next_header() {
+                Ok(Some(_)) => {}
+                Ok(None) => return true,
+                Err(()) => return false,
+            }
+        }
+    }
+
+    fn next_header(&mut self) -> Result<Option<NodeHeader>, ()> {
+        // Skip any padding of 0s
+        while self.payload.get(self.position) == Some(&0) {
+            self.position += 1;
+        }
+
+        let rest = &self.payload[self.position..];
+        if rest.is_empty() {
+            return Ok(None);
+        }
+
+        // Verify the remaining node count
+        if self.remaining_nodes == Some(0) {
+            return Err(());
+        }
+
+        if rest.len() < 8 {
+            return Err(());
+        }
+        let name = NodeName(rest[0..4].try_into().unwrap());
+        let size = u32::from_le_bytes(rest[4..8].try_into().unwrap());
+        if rest.len() - 8 < size as usize {
+            return Err(());
+        }
+
+        let header = NodeHeader {
+            header_position: (self.payload_start + self.position) as u64,
+            name,
+            size,
+        };
+
+        self.position += 8 + size as usize;
+        if let Some(remaining_nodes) = self.remaining_nodes.as_mut() {
+            *remaining_nodes -= 1;
+        }
+
+        Ok(Some(header))
+    }
+}
+
+impl<'a> Iterator for NodeSliceChildren<'a> {
+    type Item = NodeSlice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Validated beforehand, so errors can't happen here
+        let header = self.next_header().ok()??;
+        Some(NodeSlice {
+            file: self.file,
+            header,
+        })
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::LevelStreamSegment;
+    use crate::node::raw_node;
+
+    fn names(children: NodeSliceChildren) -> Vec<NodeName> {
+        children.map(|child| child.name()).collect()
+    }
+
+    #[test]
+    fn slice_children() {
+        let segment = [
+            raw_node(b"INFO", &[1, 0, 0, 0, 2, 0, 0, 0], 0),
+            raw_node(b"DATA", b"samples", 1),
+        ]
+        .concat();
+        let pack = [
+            &2u32.to_le_bytes()[..],
+            &raw_node(b"SEGM", &segment, 0),
+            &raw_node(b"NAME", b"pack\0", 3),
+        ]
+        .concat();
+        let file = raw_node(
+            b"ucfb",
+            &[raw_node(b"lvl_", &pack, 0), raw_node(b"scr_", b"", 0)].concat(),
+            0,
+        );
+
+        let root = NodeSlice::root(&file).unwrap();
+        assert_eq!(root.name(), NodeName::from_str("ucfb"));
+        assert_eq!(root.payload().len(), file.len() - 8);
+        assert_eq!(
+            names(root.children().unwrap()),
+            [b"lvl_", b"scr_"].map(NodeName::from)
+        );
+
+        // Count prefixed children
+        let pack = root.find_child(b"lvl_").unwrap();
+        assert_eq!(
+            names(pack.children().unwrap()),
+            [b"SEGM", b"NAME"].map(NodeName::from)
+        );
+        assert_eq!(pack.find_child(b"NAME").unwrap().payload(), b"pack\0");
+        assert!(pack.find_child(b"INFO").is_none());
+
+        // Headers stay valid with the whole file
+        let segment_node = pack.find_child(b"SEGM").unwrap();
+        let at = NodeSlice::at(&file, segment_node.header().header_position).unwrap();
+        assert_eq!(at.payload(), segment_node.payload());
+
+        let segment: LevelStreamSegment = segment_node.read().unwrap();
+        assert_eq!(segment.info.frame_count, 2);
+        assert_eq!(segment.data.read_slice(&file).unwrap(), b"samples");
+        let LazyData::Read(data) = &segment.data else {
+            unreachable!()
+        };
+        let data_end = data.header_position as usize + 8 + 7;
+        assert!(segment.data.read_slice(&file[..data_end]).is_ok());
+        assert!(segment.data.read_slice(&file[..data_end - 1]).is_err());
+        assert!(LazyData::Write(vec![0u8]).read_slice(&file).is_err());
+
+        // Payloads which aren't made of nodes don't have children
+        assert!(root.find_child(b"scr_").unwrap().children().is_ok());
+        assert!(segment_node
+            .find_child(b"INFO")
+            .unwrap()
+            .children()
+            .is_err());
+    }
+
+    #[test]
+    fn slice_bounds() {
+        let child = raw_node(b"DATA", &[1, 2, 3, 4], 0);
+        let file = raw_node(b"ucfb", &child, 0);
+
+        // Nodes running past the end of the buffer
+        assert!(NodeSlice::root(&file[..file.len() - 1]).is_err());
+        assert!(NodeSlice::root(&file[..6]).is_err());
+        assert!(NodeSlice::at(&file, file.len() as u64).is_err());
+        assert!(NodeSlice::at(&file, u64::MAX).is_err());
+
+        let mut oversized = file.clone();
+        oversized[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(NodeSlice::root(&oversized).is_err());
+
+        // A child larger than its parent
+        let oversized = raw_node(
+            b"ucfb",
+            &[b"DATA", &5u32.to_le_bytes()[..], &[1, 2, 3, 4]].concat(),
+            0,
+        );
+        assert!(NodeSlice::root(&oversized).unwrap().children().is_err());
+
+        // A truncated header of a child
+        let truncated = raw_node(b"ucfb", &[&child[..], b"DAT"].concat(), 0);
+        assert!(NodeSlice::root(&truncated).unwrap().children().is_err());
+
+        // More nodes than the count prefix says
+        let prefixed = [&1u32.to_le_bytes()[..], &child, &child].concat();
+        let prefixed = raw_node(b"ucfb", &prefixed, 0);
+        assert!(NodeSlice::root(&prefixed).unwrap().children().is_err());
+    }
+}
@@ -3,7 +3,10 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{parse_macro_input, DataStruct, DeriveInput, Ident, LitStr, Type};
+use syn::{
+    parse_macro_input, Attribute, DataEnum, DataStruct, DeriveInput, Ident, Lit, LitStr, Meta,
+    NestedMeta, Type,
+};
 
 // Believe me, I *tried* to make the NodeData macro code readable, but it's
 // just... not fucking doable. I want to get off MX RUST'S WILD RIDE
@@ -23,21 +26,40 @@ use syn::{parse_macro_input, DataStruct, DeriveInput, Ident, LitStr, Type};
 ///
 /// Every field must be marked with either attribute:
 ///  * `#[node("NAME")]` - Means that the node structure only expects a single node matching that
-///     name. The field type must implement `PackedParser` itself.
+///    name. The field type must implement `PackedParser` itself. If the field is an `Option<T>`,
+///    the node may be missing. Otherwise, a missing node can be replaced with a default value,
+///    with either `#[node("NAME", default)]` (using [`Default`]), or
+///    `#[node("NAME", default = "path::to::function")]`.
 ///  * `#[nodes("NAME")]` - Means that the node reader will expect a variable amount of nodes
-///     matching that name. The field's type must implement [`TryFrom<Vec<T>>`]. This allows for
-///     usage of types such as arrays, like `[T; 4]`, to require a specific amount of nodes to
-///     be read.
+///    matching that name. The field's type must implement [`TryFrom<Vec<T>>`]. This allows for
+///    usage of types such as arrays, like `[T; 4]`, to require a specific amount of nodes to
+///    be read.
+///  * `#[flag("NAME")]` - The field must be a `bool`, set to whether a node with that name is
+///    present. When writing, an empty node is written if it's `true`.
 ///
-/// The implementation currently only accepts non-tuple structs.
+/// Additionally, a single field can be marked with `#[unknown_nodes]`. Its type must be
+/// `Vec<(NodeName, Vec<u8>)>`, and it collects the names and raw payloads of every child node
+/// not matching any other field. They are written back at the position of the field.
+///
+/// Without such a field, unknown child nodes are handled according to the struct's
+/// `#[node_data(unknown = "ignore" | "warn" | "error")]` attribute, or if it isn't present,
+/// the current `UnknownNodePolicy` of the thread.
+///
+/// The implementation currently only accepts non-tuple structs, and enums (see below).
+///
+/// ## Enums
+/// Enums must consist of single field tuple variants, each marked with `#[node("NAME")]`. The
+/// node of an enum must contain a single child node, whose name selects the variant, and which
+/// holds the variant's data. While writing, the variant is written as such a child node.
 ///
 /// ## Implementation details
 /// The writer implementation follows field definition order while outputting the nodes.
 ///
 /// The reader implementation doesn't rely on any particular memory layout while reading node data.
-/// As such, completely mixed up and unordered layouts are accepted by it.
+/// As such, completely mixed up and unordered layouts are accepted by it. Errors returned while
+/// reading a field are `NodeReadError`s, marked with the name of that field.
 ///
-#[proc_macro_derive(NodeData, attributes(node, nodes))]
+#[proc_macro_derive(NodeData, attributes(node, nodes, flag, node_data, unknown_nodes))]
 pub fn node_data_derive(input: TokenStream) -> TokenStream {
     match node_data_derive_impl(parse_macro_input!(input as DeriveInput)) {
         Ok(ts) => ts.into(),
@@ -59,9 +81,16 @@ fn node_data_derive_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
     let name = &input.ident;
     let fields = extract_field_metadata(match &input.data {
         syn::Data::Struct(s) => s,
-        _ => return Err(syn::Error::new_spanned(&input, "expected struct")),
+        syn::Data::Enum(e) => return node_data_enum_impl(&zenit_lvl, name, e),
+        _ => return Err(syn::Error::new_spanned(&input, "expected struct or enum")),
     })?;
 
+    // How unknown child nodes should be handled, if they're not collected into a field
+    let unknown_policy = match extract_unknown_policy(&input.attrs)? {
+        Some(policy) => quote!(#zenit_lvl::node::UnknownNodePolicy::#policy),
+        None => quote!(#zenit_lvl::node::UnknownNodePolicy::current()),
+    };
+
     let read_impl = {
         // Inner structure of the NodeRead::read_node_payload impl
         //
@@ -80,12 +109,19 @@ fn node_data_derive_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
         //:         if child.name == "NAME" {
         //:             // Applies only for #[node("...")], aka. single fields
         //:             anyhow::ensure!(field.is_none());
-        //:             field = Some(parse_field()?);
+        //:             field = Some(parse_field().in_field("Type::field")?);
         //:
         //:             // Applies only for #[nodes("...")], aka. "multiple" fields
-        //:             field.push(parse_field()?);
+        //:             field.push(parse_field().in_field("Type::field")?);
+        //:
+        //:             // Applies only for #[flag("...")]
+        //:             field = true;
+        //:         }
+        //:         ... repeat above for every field, chained with `else`
+        //:         else {
+        //:             // <unknown_handler> - either pushes the node into the
+        //:             // #[unknown_nodes] field, or handles it with the UnknownNodePolicy
         //:         }
-        //:         ... repeat above for every field
         //:     }
         //:
         //:     Self {
@@ -97,25 +133,36 @@ fn node_data_derive_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
             NodeField::Single {
                 field_name,
                 field_type,
+                missing,
                 ..
-            } => quote! {
+            } => {
+                // Option<T> fields are read as T
+                let field_type = match missing {
+                    MissingNode::None(inner_type) => inner_type.as_ref(),
+                    _ => field_type,
+                };
+                quote! {
                 // We store it as an Option<T> to
                 //  - manage cases where the field doesn't exist
                 //    * (could be otherwise handled by a Default impl, but we don't demand it)
                 //  - manage cases where malformed input data provides the same field more than once
                 let mut #field_name: Option<#field_type> = None;
+                }
+            }
+            NodeField::Flag { field_name, .. } => quote! {
+                let mut #field_name = false;
             },
-            NodeField::Multiple { field_name, .. } => quote! {
+            NodeField::Multiple { field_name, .. } | NodeField::Unknown { field_name } => quote! {
                 let mut #field_name = Vec::new();
             },
         });
 
-        let conditionals = fields.iter().map(|field| match field {
+        let conditionals = fields.iter().filter_map(|field| match field {
             NodeField::Single {
                 node_name,
                 field_name,
                 ..
-            } => quote! {
+            } => Some(quote! {
                 if _child.name.as_ref() == #node_name.as_bytes() {
 
                     // Make sure the input data doesn't have the same single value twice.
@@ -127,45 +174,95 @@ fn node_data_derive_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
                     );
 
                     #field_name = Some(
-                        #zenit_lvl::node::NodeRead::read_node_at(
-                            _r,
-                            _child
+                        #zenit_lvl::node::NodeResultExt::in_field(
+                            #zenit_lvl::node::NodeRead::read_node_at(
+                                _r,
+                                _child
+                            ),
+                            concat!(stringify!(#name), "::", stringify!(#field_name)),
                         )?
                     );
                 }
-            },
+            }),
             NodeField::Multiple {
                 node_name,
                 field_name,
                 ..
-            } => quote! {
+            } => Some(quote! {
                 if _child.name.as_ref() == #node_name.as_bytes() {
                     #field_name.push(
-                        #zenit_lvl::node::NodeRead::read_node_at(
-                            _r,
-                            _child
+                        #zenit_lvl::node::NodeResultExt::in_field(
+                            #zenit_lvl::node::NodeRead::read_node_at(
+                                _r,
+                                _child
+                            ),
+                            concat!(stringify!(#name), "::", stringify!(#field_name)),
                         )?
                     );
                 }
-            },
+            }),
+            NodeField::Flag {
+                node_name,
+                field_name,
+            } => Some(quote! {
+                if _child.name.as_ref() == #node_name.as_bytes() {
+                    #field_name = true;
+                }
+            }),
+            NodeField::Unknown { .. } => None,
         });
 
+        let unknown_handler = match fields.iter().find_map(NodeField::as_unknown) {
+            Some(field_name) => quote! {
+                #field_name.push((
+                    _child.name,
+                    #zenit_lvl::node::read_node_payload(_r, _child)?,
+                ));
+            },
+            None => quote! {
+                #unknown_policy.handle(stringify!(#name), _child)?;
+            },
+        };
+
         let return_expressions = fields.iter().map(|field| match field {
-            NodeField::Single { field_name, .. } => quote! {
-                #field_name: #field_name.ok_or(
-                    ::anyhow::anyhow!(
-                        concat!(
-                            "missing field: ", stringify!(#field_name)
+            NodeField::Single {
+                field_name,
+                missing,
+                ..
+            } => match missing {
+                MissingNode::Error => quote! {
+                    #field_name: #field_name.ok_or(
+                        ::anyhow::anyhow!(
+                            concat!(
+                                "missing field: ", stringify!(#field_name)
+                            )
                         )
-                    )
-                )?,
+                    )?,
+                },
+                MissingNode::None(_) => quote! {
+                    #field_name,
+                },
+                MissingNode::Default => quote! {
+                    #field_name: #field_name.unwrap_or_default(),
+                },
+                MissingNode::DefaultWith(function) => quote! {
+                    #field_name: #field_name.unwrap_or_else(#function),
+                },
             },
             NodeField::Multiple {
                 field_name,
                 field_type,
                 ..
             } => quote! {
-                #field_name: TryInto::<#field_type>::try_into(#field_name).unwrap(),
+                #field_name: TryInto::<#field_type>::try_into(#field_name)
+                    .map_err(|_| ::anyhow::anyhow!(
+                        concat!(
+                            "invalid amount of nodes for field: ", stringify!(#field_name)
+                        )
+                    ))?,
+            },
+            NodeField::Flag { field_name, .. } | NodeField::Unknown { field_name } => quote! {
+                #field_name,
             },
         });
 
@@ -175,7 +272,9 @@ fn node_data_derive_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
             let _children = #zenit_lvl::node::read_node_children(_r, _header)?;
 
             for _child in _children {
-                #(#conditionals)*
+                #(#conditionals else)* {
+                    #unknown_handler
+                }
             }
 
             Ok(Self {
@@ -186,6 +285,19 @@ fn node_data_derive_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
 
     let write_impl = {
         let field_writers = fields.iter().map(|field| match field {
+            NodeField::Single {
+                node_name,
+                field_name,
+                missing: MissingNode::None(_),
+                ..
+            } => quote! {
+                if let Some(field) = self.#field_name.clone() {
+                    _writer.write_node(
+                        #zenit_lvl::node::NodeName::from_str(#node_name),
+                        field,
+                    )?;
+                }
+            },
             NodeField::Single {
                 node_name,
                 field_name,
@@ -196,6 +308,17 @@ fn node_data_derive_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
                     self.#field_name.clone(),
                 )?;
             },
+            NodeField::Flag {
+                node_name,
+                field_name,
+            } => quote! {
+                if self.#field_name {
+                    _writer.build_node(
+                        #zenit_lvl::node::NodeName::from_str(#node_name),
+                        |_| ::zenit_utils::ok(),
+                    )?;
+                }
+            },
             NodeField::Multiple {
                 node_name,
                 field_name,
@@ -208,6 +331,11 @@ fn node_data_derive_impl(input: DeriveInput) -> syn::Result<TokenStream2> {
                     )?;
                 }
             },
+            NodeField::Unknown { field_name } => quote! {
+                for (name, payload) in self.#field_name.iter().cloned() {
+                    _writer.write_node(name, payload)?;
+                }
+            },
         });
 
         quote! {
@@ -243,6 +371,7 @@ enum NodeField {
         node_name: String,
         field_name: Ident,
         field_type: Type,
+        missing: MissingNode,
     },
     /// Created via a `#[nodes("NAME")]` attribute
     Multiple {
@@ -250,6 +379,71 @@ enum NodeField {
         field_name: Ident,
         field_type: Type,
     },
+    /// Created via a `#[flag("NAME")]` attribute
+    Flag { node_name: String, field_name: Ident },
+    /// Created via an `#[unknown_nodes]` attribute
+    Unknown { field_name: Ident },
+}
+
+/// What happens when a `#[node("NAME")]` field is missing
+enum MissingNode {
+    /// An error is returned
+    Error,
+    /// The field is an `Option`, set to `None`. Contains the inner type.
+    None(Box<Type>),
+    /// Created via `#[node("NAME", default)]`
+    Default,
+    /// Created via `#[node("NAME", default = "function")]`
+    DefaultWith(syn::Path),
+}
+
+impl NodeField {
+    fn as_unknown(&self) -> Option<&Ident> {
+        match self {
+            NodeField::Unknown { field_name } => Some(field_name),
+            _ => None,
+        }
+    }
+}
+
+/// Parses the `#[node_data(unknown = "...")]` attribute, returning the name of the
+/// `UnknownNodePolicy` variant.
+fn extract_unknown_policy(attrs: &[Attribute]) -> syn::Result<Option<Ident>> {
+    let mut result = None;
+
+    for attribute in attrs.iter().filter(|a| a.path.is_ident("node_data")) {
+        let Meta::List(list) = attribute.parse_meta()? else {
+            return Err(syn::Error::new_spanned(attribute, "expected a list"));
+        };
+
+        for nested in &list.nested {
+            let error = || syn::Error::new_spanned(nested, "unknown node_data option");
+            let NestedMeta::Meta(Meta::NameValue(option)) = nested else {
+                return Err(error());
+            };
+            if !option.path.is_ident("unknown") {
+                return Err(error());
+            }
+            let Lit::Str(value) = &option.lit else {
+                return Err(error());
+            };
+
+            let variant = match value.value().as_str() {
+                "ignore" => "Ignore",
+                "warn" => "Warn",
+                "error" => "Error",
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "expected \"ignore\", \"warn\" or \"error\"",
+                    ))
+                }
+            };
+            result = Some(Ident::new(variant, value.span()));
+        }
+    }
+
+    Ok(result)
 }
 
 /// Goes through every field of the structure, collecting info about each field.
@@ -258,9 +452,11 @@ enum NodeField {
 /// Returns an error if:
 ///  * the struct is a tuple struct
 ///  * any field is unattributed
-///  * any field has duplicate node/nodes attributes
+///  * any field has duplicate node/nodes/flag attributes
+///  * an optional field has a default value
+///  * more than one field has an unknown_nodes attribute
 fn extract_field_metadata(st: &DataStruct) -> syn::Result<Vec<NodeField>> {
-    let mut result = Vec::with_capacity(st.fields.len());
+    let mut result: Vec<NodeField> = Vec::with_capacity(st.fields.len());
 
     for field in &st.fields {
         let field_error = |msg| Err(syn::Error::new_spanned(&field, msg));
@@ -272,38 +468,64 @@ fn extract_field_metadata(st: &DataStruct) -> syn::Result<Vec<NodeField>> {
         let mut result_field = None;
 
         for attribute in &field.attrs {
-            if let Some(attribute_ident) = attribute.path.get_ident() {
-                let Ok(node_name_lit) = attribute.parse_args::<LitStr>() else {
-                    continue
-                };
-                let node_name = node_name_lit.value();
+            if attribute.path.is_ident("unknown_nodes") {
+                if result_field.is_some() {
+                    return field_error("duplicate node attribute");
+                }
+                if result.iter().any(|field| field.as_unknown().is_some()) {
+                    return field_error("only one field can collect unknown nodes");
+                }
 
-                let field_name = field_ident.clone();
-                let field_type = field.ty.clone();
+                result_field = Some(NodeField::Unknown {
+                    field_name: field_ident.clone(),
+                });
+                continue;
+            }
 
-                let attribute_name = attribute_ident.to_string();
+            let Some(attribute_ident) = attribute.path.get_ident() else {
+                continue;
+            };
+            let attribute_name = attribute_ident.to_string();
+            if !matches!(attribute_name.as_str(), "node" | "nodes" | "flag") {
+                continue;
+            }
+            if result_field.is_some() {
+                return field_error("duplicate node attribute");
+            }
 
-                if attribute_name == "node" || attribute_name == "nodes" {
-                    if result_field.is_some() {
-                        return field_error("duplicate node attribute");
+            let field_name = field_ident.clone();
+            let field_type = field.ty.clone();
+
+            if attribute_name == "node" {
+                let (node_name, default) = parse_node_attribute(attribute)?;
+                let missing = match (default, option_inner_type(&field_type)) {
+                    (Some(_), Some(_)) => {
+                        return field_error("optional fields can't have default values")
                     }
-                }
+                    (Some(default), None) => default,
+                    (None, Some(inner_type)) => MissingNode::None(Box::new(inner_type)),
+                    (None, None) => MissingNode::Error,
+                };
 
-                if attribute_name == "node" {
-                    result_field = Some(NodeField::Single {
-                        node_name,
-                        field_name,
-                        field_type,
-                    });
-                } else if attribute_name == "nodes" {
-                    // TODO: check if field_type is a Vec, somehow
-
-                    result_field = Some(NodeField::Multiple {
-                        node_name,
-                        field_name,
-                        field_type,
-                    });
-                }
+                result_field = Some(NodeField::Single {
+                    node_name,
+                    field_name,
+                    field_type,
+                    missing,
+                });
+            } else if attribute_name == "nodes" {
+                // TODO: check if field_type is a Vec, somehow
+
+                result_field = Some(NodeField::Multiple {
+                    node_name: attribute.parse_args::<LitStr>()?.value(),
+                    field_name,
+                    field_type,
+                });
+            } else if attribute_name == "flag" {
+                result_field = Some(NodeField::Flag {
+                    node_name: attribute.parse_args::<LitStr>()?.value(),
+                    field_name,
+                });
             }
         }
 
@@ -315,3 +537,153 @@ fn extract_field_metadata(st: &DataStruct) -> syn::Result<Vec<NodeField>> {
 
     Ok(result)
 }
+
+/// Parses the arguments of a `#[node("NAME", ...)]` attribute, returning the node name and
+/// the default value, if there's one.
+fn parse_node_attribute(attribute: &Attribute) -> syn::Result<(String, Option<MissingNode>)> {
+    let error = |tokens: &dyn quote::ToTokens, msg| Err(syn::Error::new_spanned(tokens, msg));
+
+    let Meta::List(list) = attribute.parse_meta()? else {
+        return error(attribute, "expected a node name");
+    };
+
+    let mut nested = list.nested.iter();
+    let node_name = match nested.next() {
+        Some(NestedMeta::Lit(Lit::Str(name))) => name.value(),
+        _ => return error(attribute, "expected a node name"),
+    };
+
+    let mut default = None;
+    for option in nested {
+        if default.is_some() {
+            return error(option, "duplicate default value");
+        }
+
+        default = Some(match option {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => MissingNode::Default,
+            NestedMeta::Meta(Meta::NameValue(value)) if value.path.is_ident("default") => {
+                let Lit::Str(function) = &value.lit else {
+                    return error(&value.lit, "expected a function path string");
+                };
+                MissingNode::DefaultWith(function.parse()?)
+            }
+            _ => return error(option, "unknown node option"),
+        });
+    }
+
+    Ok((node_name, default))
+}
+
+/// Returns `T` if the type is an `Option<T>`.
+fn option_inner_type(ty: &Type) -> Option<Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    if path.qself.is_some() {
+        return None;
+    }
+
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        syn::GenericArgument::Type(inner) if arguments.args.len() == 1 => Some(inner.clone()),
+        _ => None,
+    }
+}
+
+/// Implements the traits for enums, see the main macro documentation.
+fn node_data_enum_impl(
+    zenit_lvl: &TokenStream2,
+    name: &Ident,
+    en: &DataEnum,
+) -> syn::Result<TokenStream2> {
+    let mut variants = Vec::with_capacity(en.variants.len());
+
+    for variant in &en.variants {
+        let error = |msg| Err(syn::Error::new_spanned(variant, msg));
+
+        if !matches!(&variant.fields, syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1) {
+            return error("expected a tuple variant with a single field");
+        }
+
+        let mut node_name = None;
+        for attribute in variant.attrs.iter().filter(|a| a.path.is_ident("node")) {
+            if node_name.is_some() {
+                return error("duplicate node attribute");
+            }
+            node_name = Some(attribute.parse_args::<LitStr>()?.value());
+        }
+
+        match node_name {
+            Some(node_name) => variants.push((&variant.ident, node_name)),
+            None => return error("unattributed variant"),
+        }
+    }
+
+    // The variant is selected by the name of the single child node, which holds its data.
+    // It's read via `read_node_payload` instead of `read_node_at`, so that recursive structures
+    // don't wrap the reader in more and more layers (see `ReadSeek`)
+    let readers = variants.iter().map(|(variant, node_name)| {
+        quote! {
+            if _child.name.as_ref() == #node_name.as_bytes() {
+                _child.seek_to_payload(_r)?;
+                return Ok(Self::#variant(#zenit_lvl::node::NodeResultExt::at_node(
+                    #zenit_lvl::node::NodeRead::read_node_payload(_r, _child),
+                    _child,
+                )?));
+            }
+        }
+    });
+
+    let writers = variants.iter().map(|(variant, node_name)| {
+        quote! {
+            Self::#variant(inner) => _writer.build_node(
+                #zenit_lvl::node::NodeName::from_str(#node_name),
+                |_writer| #zenit_lvl::node::NodeWrite::write_node(inner, _writer),
+            ),
+        }
+    });
+
+    Ok(quote! {
+        impl #zenit_lvl::node::NodeRead for #name {
+            fn read_node_payload<_R: ::std::io::Read + ::std::io::Seek>(
+                _r: &mut _R,
+                _header: #zenit_lvl::node::NodeHeader,
+            ) -> ::zenit_utils::AnyResult<Self> {
+                let _children = #zenit_lvl::node::read_node_children(_r, _header)?;
+                let [_child] = _children[..] else {
+                    ::anyhow::bail!(
+                        "expected a single {} node, found {}",
+                        stringify!(#name),
+                        _children.len(),
+                    );
+                };
+
+                #(#readers)*
+
+                ::anyhow::bail!(
+                    "unexpected {} node: `{}`",
+                    stringify!(#name),
+                    ::zenit_utils::AsciiDisplay(_child.name.as_ref()),
+                )
+            }
+        }
+
+        impl #zenit_lvl::node::NodeWrite for #name {
+            fn write_node<_W: ::std::io::Write + ::std::io::Seek>(
+                &self,
+                _writer: &mut #zenit_lvl::node::NodeWriter<'_, _W>,
+            ) -> ::zenit_utils::AnyResult {
+                match self {
+                    #(#writers)*
+                }
+            }
+        }
+    })
+}
@@ -1,6 +1,21 @@
+use super::{merge::ResourceKey, node_bytes};
+use crate::exporter::texture::{
+    mipmap::{MipmapFilter, PowerOfTwo},
+    TextureKind, TextureSpecification,
+};
+use anyhow::{bail, ensure};
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 use clap::{Args, Subcommand};
-use std::path::PathBuf;
-use zenit_lvl::game::D3DFormat;
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Cursor, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+use zenit_lvl::{
+    game::D3DFormat,
+    node::{NodeName, NodeWriter, OwnedNode, OwnedNodeContents},
+};
+use zenit_utils::{fnv1a_hash, ok, AnyResult};
 
 #[derive(Subcommand)]
 pub enum AppendCommand {
@@ -15,14 +30,300 @@ pub struct TextureInclude {
     /// If this file doesn't exist, it'll be created.
     pub output_path: PathBuf,
     /// Path to the texture within the data file.
+    ///
+    /// To append the texture into a `lvl_` node, path can be separated with a `/`. Missing
+    /// `lvl_` nodes are created. A texture with the same name is replaced.
     pub texture_path: String,
+    /// Path to the image to include.
+    pub image_path: PathBuf,
 
+    /// Texture formats to generate. Defaults to DXT1.
     #[arg(long, short = 'o')]
     pub formats: Vec<D3DFormat>,
+    /// Amount of mip levels to generate. By default, the whole mip chain is generated.
+    #[arg(long)]
+    pub mipmaps: Option<u16>,
+    /// Disables texture filtering.
+    #[arg(long)]
+    pub unfiltered: bool,
+    /// Enables ordered dithering for formats with less than 8 bits per channel.
+    #[arg(long)]
+    pub dithering: bool,
+    /// Filter used for generating the mip levels.
+    #[arg(long, value_enum, default_value_t = MipmapFilter::Box)]
+    pub mipmap_filter: MipmapFilter,
+    /// Preserves the alpha test coverage of mip levels, for the given alpha threshold (0 to 1).
+    #[arg(long)]
+    pub alpha_coverage: Option<f32>,
+    /// What to do with images whose dimensions aren't powers of two.
+    #[arg(long, value_enum, default_value_t = PowerOfTwo::Keep)]
+    pub power_of_two: PowerOfTwo,
 }
 
 impl crate::Command for AppendCommand {
-    fn run(self) -> zenit_utils::AnyResult {
-        todo!()
+    fn run(self) -> AnyResult {
+        match self {
+            AppendCommand::Texture(c) => c.run(),
+        }
+    }
+}
+
+impl crate::Command for TextureInclude {
+    fn run(self) -> AnyResult {
+        let (packs, name) = match self.texture_path.rsplit_once('/') {
+            Some((packs, name)) => (packs.split('/').collect(), name),
+            None => (vec![], self.texture_path.as_str()),
+        };
+
+        println!("  - Exporting {name}...");
+        let texture = TextureSpecification {
+            name: name.to_string(),
+            file: Some(self.image_path),
+            formats: match self.formats.is_empty() {
+                true => vec![D3DFormat::DXT1],
+                false => self.formats,
+            },
+            kind: TextureKind::Color,
+            mipmaps: self.mipmaps,
+            unfiltered: self.unfiltered,
+            dithering: self.dithering,
+            mipmap_filter: self.mipmap_filter,
+            linear: false,
+            alpha_coverage: self.alpha_coverage,
+            power_of_two: self.power_of_two,
+        }
+        .export()?;
+        let node = node_bytes(b"tex_", &texture)?;
+
+        if !self.output_path.exists() {
+            println!("  - Creating {}...", self.output_path.display());
+            let mut file = BufWriter::new(File::create(&self.output_path)?);
+            let mut writer = NodeWriter::new(&mut file, b"ucfb")?;
+            writer.finish()?;
+        }
+
+        let mut texture = OwnedNode::read_from(&mut Cursor::new(&node))?;
+        texture.padding = 0;
+
+        let mut root = OwnedNode::read_from(&mut BufReader::new(File::open(&self.output_path)?))?;
+        ensure!(root.name == b"ucfb", "not a valid data file");
+
+        let target = find_pack(&mut root, &packs)?;
+        let existing = find_resource(target, &texture);
+
+        if packs.is_empty() && existing.is_none() {
+            println!("  - Appending {name}...");
+            return append_to_root(&self.output_path, &node);
+        }
+
+        match existing {
+            Some(_) => println!("  - Replacing {name}..."),
+            None => println!("  - Appending {name}..."),
+        }
+        texture.padding = (node.len() - texture.total_size() as usize) as u32;
+        replace_or_push(target, texture);
+
+        // Write into a separate file first, so that the original isn't lost if anything fails
+        let temporary_path = self.output_path.with_extension("tmp");
+        root.write_into(&mut BufWriter::new(File::create(&temporary_path)?))?;
+        fs::rename(temporary_path, &self.output_path)?;
+
+        ok()
+    }
+}
+
+/// Returns the children of the data pack at given path, creating any missing packs.
+fn find_pack<'a>(root: &'a mut OwnedNode, packs: &[&str]) -> AnyResult<&'a mut Vec<OwnedNode>> {
+    let mut children = children_mut(root)?;
+
+    for &pack_name in packs {
+        let hash = NodeName::from(fnv1a_hash(pack_name.as_bytes()));
+        let position = children.iter().position(|node| {
+            node.name == b"lvl_"
+                && matches!(node.children(), Some([contents]) if contents.name == hash)
+        });
+
+        let position = match position {
+            Some(position) => position,
+            None => {
+                println!("  - Creating data pack {pack_name}...");
+                children.push(OwnedNode::new_parent(
+                    b"lvl_",
+                    vec![OwnedNode::new_parent(hash, vec![])],
+                ));
+                children.len() - 1
+            }
+        };
+
+        let contents = &mut children_mut(&mut children[position])?[0];
+        children = children_mut(contents)?;
+    }
+
+    Ok(children)
+}
+
+/// Returns the index of a resource with the same key as the node.
+fn find_resource(nodes: &[OwnedNode], node: &OwnedNode) -> Option<usize> {
+    let key = ResourceKey::of(node)?;
+    nodes
+        .iter()
+        .position(|other| ResourceKey::of(other).as_ref() == Some(&key))
+}
+
+/// Replaces a resource with the same key as the node, keeping its padding. If there's none, the
+/// node is appended instead.
+fn replace_or_push(nodes: &mut Vec<OwnedNode>, mut node: OwnedNode) {
+    match find_resource(nodes, &node) {
+        Some(index) => {
+            node.padding = nodes[index].padding;
+            nodes[index] = node;
+        }
+        None => nodes.push(node),
+    }
+}
+
+/// Returns the children of the node. Empty nodes, which are read as leaf nodes, are turned into
+/// parent nodes.
+fn children_mut(node: &mut OwnedNode) -> AnyResult<&mut Vec<OwnedNode>> {
+    if let OwnedNodeContents::Payload(payload) = &node.contents {
+        ensure!(payload.is_empty(), "node doesn't seem to have a hierarchy");
+        node.contents = OwnedNodeContents::Children {
+            count_prefix: None,
+            leading_padding: 0,
+            children: vec![],
+        };
+    }
+    Ok(node.children_mut().unwrap())
+}
+
+/// Appends a node at the end of the root node in place, rewriting the root's size.
+fn append_to_root(path: &Path, node: &[u8]) -> AnyResult {
+    let mut file = fs::OpenOptions::new().read(true).write(true).open(path)?;
+
+    file.seek(SeekFrom::Start(4))?;
+    let size = file.read_u32::<LE>()? as u64;
+    let end = 8 + size;
+    ensure!(file.metadata()?.len() >= end, "the data file is truncated");
+
+    let Ok(new_size) = u32::try_from(size + node.len() as u64) else {
+        bail!("the data file would be too large");
+    };
+
+    // Anything after the end of the root node is just padding, and gets overwritten
+    file.seek(SeekFrom::Start(end))?;
+    file.write_all(node)?;
+    file.set_len(end + node.len() as u64)?;
+
+    file.seek(SeekFrom::Start(4))?;
+    file.write_u32::<LE>(new_size)?;
+
+    ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::fixtures::{pack, texture};
+    use std::env;
+
+    #[test]
+    fn append_into_nested_packs() {
+        let mut root = OwnedNode::new_parent(
+            b"ucfb",
+            vec![texture("a", &[0]), pack("side", vec![texture("b", &[0])])],
+        );
+
+        // Existing packs are reused
+        let target = find_pack(&mut root, &["side"]).unwrap();
+        assert_eq!(target, &[texture("b", &[0])]);
+        replace_or_push(target, texture("c", &[0]));
+
+        // Missing packs are created, even inside of existing ones
+        let target = find_pack(&mut root, &["side", "inner"]).unwrap();
+        assert!(target.is_empty());
+        replace_or_push(target, texture("d", &[0]));
+
+        let expected = OwnedNode::new_parent(
+            b"ucfb",
+            vec![
+                texture("a", &[0]),
+                pack(
+                    "side",
+                    vec![
+                        texture("b", &[0]),
+                        texture("c", &[0]),
+                        pack("inner", vec![texture("d", &[0])]),
+                    ],
+                ),
+            ],
+        );
+        assert_eq!(root, expected);
+
+        // Packs must be parent nodes
+        let mut root = OwnedNode::new_parent(b"ucfb", vec![]);
+        root.children_mut().unwrap().push(OwnedNode::new_parent(
+            b"lvl_",
+            vec![OwnedNode::new_payload(fnv1a_hash(b"side"), vec![1])],
+        ));
+        assert!(find_pack(&mut root, &["side"]).is_err());
+    }
+
+    #[test]
+    fn append_replaces_same_name() {
+        let mut old = texture("b", &[0]);
+        old.padding = 3;
+        let mut nodes = vec![
+            texture("a", &[0]),
+            old,
+            OwnedNode::new_payload(b"sky_", vec![]),
+        ];
+
+        replace_or_push(&mut nodes, texture("b", &[1, 2]));
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(
+            nodes[1].find_child(b"INFO").unwrap().payload(),
+            Some(&[1, 2][..])
+        );
+        assert_eq!(nodes[1].padding, 3);
+
+        // Nodes without names are never replaced
+        replace_or_push(&mut nodes, OwnedNode::new_payload(b"sky_", vec![]));
+        assert_eq!(nodes.len(), 4);
+    }
+
+    #[test]
+    fn append_to_root_in_place() {
+        let path = env::temp_dir().join(format!("zenit_mdk_append_{}.lvl", std::process::id()));
+
+        // Trailing padding after the root node gets overwritten
+        let mut file = Cursor::new(Vec::new());
+        OwnedNode::new_parent(b"ucfb", vec![texture("a", &[0])])
+            .write_into(&mut file)
+            .unwrap();
+        let mut file = file.into_inner();
+        file.extend([0; 4]);
+        fs::write(&path, &file).unwrap();
+
+        let mut node = Cursor::new(Vec::new());
+        texture("b", &[1]).write_into(&mut node).unwrap();
+        append_to_root(&path, node.get_ref()).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), file.len() - 4 + node.get_ref().len());
+        assert_eq!(
+            u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize,
+            bytes.len() - 8
+        );
+
+        let root = OwnedNode::read_from(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(
+            root.children().unwrap(),
+            [texture("a", &[0]), texture("b", &[1])]
+        );
+
+        // Truncated files aren't touched
+        fs::write(&path, &file[..file.len() - 8]).unwrap();
+        assert!(append_to_root(&path, node.get_ref()).is_err());
+        fs::remove_file(&path).unwrap();
     }
 }
@@ -1,9 +1,11 @@
 use crate::exporter::{shader::ShaderSpecification, texture::TextureSpecification};
 use clap::Args;
+use rayon::prelude::*;
 use serde::Deserialize;
 use std::{
+    collections::HashSet,
     fs::{self, File},
-    io::BufWriter,
+    io::{BufWriter, Cursor, Write},
     path::{Path, PathBuf},
 };
 use zenit_lvl::node::NodeWriter;
@@ -16,11 +18,21 @@ pub struct BuildCommand {
     pub output: PathBuf,
     /// Specification file to use
     pub specification: PathBuf,
+    /// Directory for caching encoded textures between builds. Defaults to the output path with
+    /// a `.cache` extension.
+    ///
+    /// Entries not used by the build are removed, so the directory shouldn't be shared between
+    /// several outputs.
+    #[clap(long)]
+    pub cache_dir: Option<PathBuf>,
+    /// Encodes every texture from scratch, without reading or updating the cache
+    #[clap(long)]
+    pub no_cache: bool,
 }
 
 impl crate::Command for BuildCommand {
     fn run(self) -> AnyResult {
-        let spec_text = match fs::read_to_string(self.specification) {
+        let spec_text = match fs::read_to_string(&self.specification) {
             Ok(specs) => specs,
             Err(err) => {
                 eprintln!("An error occured while reading the specification: {err:#?}");
@@ -36,6 +48,25 @@ impl crate::Command for BuildCommand {
             }
         };
 
+        let cache = match self.no_cache {
+            true => None,
+            false => Some(BuildCache::new(
+                self.cache_dir
+                    .unwrap_or_else(|| self.output.with_extension("cache")),
+            )?),
+        };
+
+        println!(" : Encoding textures...");
+        let textures = spec
+            .textures
+            .into_par_iter()
+            .map(|texture| encode_texture(texture, cache.as_ref()))
+            .collect::<AnyResult<Vec<_>>>()?;
+
+        if let Some(cache) = &cache {
+            cache.prune(&textures)?;
+        }
+
         let mut file = BufWriter::new(
             File::options()
                 .create(true)
@@ -46,9 +77,9 @@ impl crate::Command for BuildCommand {
         let mut writer = NodeWriter::new(&mut file, b"ucfb")?;
 
         println!(" : Writing textures...");
-        for texture in spec.textures {
-            println!("  - Writing {}...", texture.name);
-            writer.write_node(b"tex_", texture.export()?)?;
+        for texture in &textures {
+            // Encoded nodes are already padded, so that they can be written as is
+            writer.write_all(&texture.node)?;
         }
 
         println!(" : Writing shaders...");
@@ -100,3 +131,185 @@ struct PackSpecification {
 struct ShaderPreprocessorSettings {
     shared: Vec<PathBuf>,
 }
+
+/// A texture encoded into a `tex_` node.
+struct EncodedTexture {
+    /// Key of the texture in the build cache, if it's enabled
+    key: Option<u64>,
+    /// The whole node, including its header and padding
+    node: Vec<u8>,
+}
+
+fn encode_texture(
+    texture: TextureSpecification,
+    cache: Option<&BuildCache>,
+) -> AnyResult<EncodedTexture> {
+    // Hashing reads all input files, so it's skipped when there's no cache
+    let key = match cache {
+        Some(_) => Some(BuildCache::key(&texture)?),
+        None => None,
+    };
+    if let Some(node) = cache.zip(key).and_then(|(cache, key)| cache.load(key)) {
+        println!("  - Reusing cached {}...", texture.name);
+        return Ok(EncodedTexture { key, node });
+    }
+
+    println!("  - Encoding {}...", texture.name);
+    let mut node = Cursor::new(Vec::new());
+    NodeWriter::new(&mut node, b"ucfb")?.write_node(b"tex_", texture.export()?)?;
+    // Skip the header of the temporary root node
+    let node = node.into_inner().split_off(8);
+
+    if let Some((cache, key)) = cache.zip(key) {
+        cache.store(key, &node);
+    }
+    Ok(EncodedTexture { key, node })
+}
+
+/// Directory with encoded texture nodes, keyed by a hash of the texture specification and
+/// contents of its input files.
+struct BuildCache {
+    directory: PathBuf,
+}
+
+impl BuildCache {
+    /// Bumped whenever the encoding changes in a way not reflected in the specification
+    const VERSION: &'static str = "2";
+    const EXTENSION: &'static str = "tex_";
+
+    fn new(directory: PathBuf) -> AnyResult<Self> {
+        fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    /// Computes the key of a texture. Changes to the specification or any of the input files
+    /// result in a different key.
+    fn key(texture: &TextureSpecification) -> AnyResult<u64> {
+        let mut hasher = Fnv1a64::default();
+        hasher.write(Self::VERSION.as_bytes());
+        hasher.write(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.write(&serde_json::to_vec(texture)?);
+        for path in texture.input_files() {
+            let contents = fs::read(path)?;
+            hasher.write(&(contents.len() as u64).to_le_bytes());
+            hasher.write(&contents);
+        }
+        Ok(hasher.0)
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.directory
+            .join(format!("{key:016x}"))
+            .with_extension(Self::EXTENSION)
+    }
+
+    /// Returns the cached node, unless it's missing or doesn't look like a valid `tex_` node.
+    fn load(&self, key: u64) -> Option<Vec<u8>> {
+        let node = fs::read(self.path(key)).ok()?;
+        let size = u32::from_le_bytes(node.get(4..8)?.try_into().unwrap()) as usize;
+        (node.starts_with(b"tex_") && 8 + size <= node.len()).then_some(node)
+    }
+
+    /// Stores the node in the cache. Failures only emit a warning, as the cache is optional.
+    fn store(&self, key: u64, node: &[u8]) {
+        // Written into a temporary file first, so that interrupted builds don't leave
+        // truncated entries behind
+        let path = self.path(key);
+        let temporary_path = path.with_extension(format!(
+            "tmp{}",
+            rayon::current_thread_index().unwrap_or_default()
+        ));
+        let result =
+            fs::write(&temporary_path, node).and_then(|_| fs::rename(&temporary_path, &path));
+        if let Err(err) = result {
+            eprintln!("    Warning: couldn't cache the texture: {err}");
+        }
+    }
+
+    /// Removes all cache entries which weren't used by the build, along with temporary files
+    /// left behind by interrupted builds.
+    fn prune(&self, textures: &[EncodedTexture]) -> AnyResult {
+        let used: HashSet<_> = textures
+            .iter()
+            .filter_map(|texture| Some(self.path(texture.key?)))
+            .collect();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let removable = path.extension().is_some_and(|ext| {
+                ext == Self::EXTENSION || ext.to_string_lossy().starts_with("tmp")
+            });
+            if removable && !used.contains(&path) {
+                fs::remove_file(path)?;
+            }
+        }
+        ok()
+    }
+}
+
+/// 64-bit FNV-1a hasher. Unlike the standard library's hasher, its output is stable across
+/// builds, so it can be used for persistent cache keys.
+struct Fnv1a64(u64);
+
+impl Default for Fnv1a64 {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv1a64 {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::fixtures::texture_spec;
+    use std::env;
+
+    #[test]
+    fn cache_keys_follow_inputs() {
+        let directory = env::temp_dir().join(format!("zenit_mdk_cache_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let image_path = directory.join("image.png");
+        let image = image::RgbaImage::from_pixel(4, 4, [255, 0, 0, 255].into());
+        image.save(&image_path).unwrap();
+
+        let spec = |unfiltered| TextureSpecification {
+            name: String::from("red"),
+            unfiltered,
+            ..texture_spec(&image_path)
+        };
+
+        let cache = BuildCache::new(directory.join("cache")).unwrap();
+        let encoded = encode_texture(spec(false), Some(&cache)).unwrap();
+        let key = encoded.key.unwrap();
+        assert_eq!(cache.load(key), Some(encoded.node.clone()));
+        assert_eq!(BuildCache::key(&spec(false)).unwrap(), key);
+        assert_ne!(BuildCache::key(&spec(true)).unwrap(), key);
+
+        // Without a cache, the inputs aren't hashed at all
+        let uncached = encode_texture(spec(false), None).unwrap();
+        assert_eq!(uncached.key, None);
+        assert_eq!(uncached.node, encoded.node);
+
+        image::RgbaImage::from_pixel(4, 4, [0, 255, 0, 255].into())
+            .save(&image_path)
+            .unwrap();
+        let changed = BuildCache::key(&spec(false)).unwrap();
+        assert_ne!(changed, key);
+
+        // Leftovers of interrupted builds are pruned as well
+        let temporary_path = cache.path(key).with_extension("tmp3");
+        fs::write(&temporary_path, b"tex_").unwrap();
+        cache.prune(&[]).unwrap();
+        assert_eq!(cache.load(key), None);
+        assert!(!temporary_path.exists());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
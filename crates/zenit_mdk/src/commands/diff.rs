@@ -0,0 +1,318 @@
+use super::merge::ResourceKey;
+use anyhow::ensure;
+use clap::Args;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    fs::File,
+    io::{BufReader, Cursor},
+    path::{Path, PathBuf},
+};
+use zenit_lvl::node::{NodeName, OwnedNode};
+use zenit_utils::{fnv1a_hash, ok, AnyResult, AsciiDisplay};
+
+#[derive(Args)]
+pub struct DiffCommand {
+    /// Path to the old data file.
+    pub old_path: PathBuf,
+    /// Path to the new data file.
+    pub new_path: PathBuf,
+}
+
+/// Identifies a top level node while diffing. Nodes which aren't resources with a name are
+/// identified by their type and the order they appear in.
+///
+/// Both variants carry an occurrence index, so that resources duplicated within the same file
+/// are compared with their counterparts in order, instead of being collapsed into one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DiffKey {
+    Resource(ResourceKey, usize),
+    Other(NodeName, usize),
+}
+
+impl Display for DiffKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffKey::Resource(key, 0) => write!(f, "{key}"),
+            DiffKey::Resource(key, index) => write!(f, "{key} (duplicate #{index})"),
+            DiffKey::Other(name, index) => write!(f, "{} #{index}", AsciiDisplay(name.as_ref())),
+        }
+    }
+}
+
+/// Size and hash of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeStats {
+    /// Size of the node's payload
+    pub size: u64,
+    /// FNV-1a hash of the whole node, excluding its padding
+    pub hash: u32,
+}
+
+impl NodeStats {
+    pub fn of(node: &OwnedNode) -> AnyResult<Self> {
+        let mut bytes = Cursor::new(Vec::new());
+        node.write_into(&mut bytes)?;
+        let size = node.payload_size();
+        Ok(Self {
+            size,
+            hash: fnv1a_hash(&bytes.into_inner()[..8 + size as usize]),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added(NodeStats),
+    Removed(NodeStats),
+    Modified { old: NodeStats, new: NodeStats },
+}
+
+/// A change of a single resource.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceDiff {
+    /// Keys of the data packs containing the resource, from the outermost one
+    pub packs: Vec<DiffKey>,
+    pub key: DiffKey,
+    pub change: Change,
+}
+
+impl Display for ResourceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self.change {
+            Change::Added(_) => '+',
+            Change::Removed(_) => '-',
+            Change::Modified { .. } => '~',
+        };
+        write!(f, "{symbol} ")?;
+        for pack in &self.packs {
+            write!(f, "{pack} / ")?;
+        }
+        write!(f, "{}", self.key)?;
+
+        match self.change {
+            Change::Added(stats) | Change::Removed(stats) => {
+                write!(f, " ({} bytes, hash 0x{:08x})", stats.size, stats.hash)
+            }
+            Change::Modified { old, new } => write!(
+                f,
+                ": {} -> {} bytes ({:+}), hash 0x{:08x} -> 0x{:08x}",
+                old.size,
+                new.size,
+                new.size as i64 - old.size as i64,
+                old.hash,
+                new.hash,
+            ),
+        }
+    }
+}
+
+/// Result of comparing two node lists.
+#[derive(Debug, Default)]
+pub struct Diff {
+    pub changes: Vec<ResourceDiff>,
+    /// Amount of identical resources
+    pub unchanged: usize,
+}
+
+impl Diff {
+    /// Compares children of two root nodes. Matching data packs are compared recursively.
+    pub fn new(old: &[OwnedNode], new: &[OwnedNode]) -> AnyResult<Self> {
+        let mut diff = Self::default();
+        diff.compare(&[], old, new)?;
+        Ok(diff)
+    }
+
+    fn compare(&mut self, packs: &[DiffKey], old: &[OwnedNode], new: &[OwnedNode]) -> AnyResult {
+        let old = keyed(old);
+        let new = keyed(new);
+        let new_lookup: HashMap<_, _> = new.iter().cloned().collect();
+        let old_lookup: HashMap<_, _> = old.iter().cloned().collect();
+
+        for (key, old_node) in &old {
+            let change = |change| ResourceDiff {
+                packs: packs.to_vec(),
+                key: key.clone(),
+                change,
+            };
+
+            let Some(new_node) = new_lookup.get(key) else {
+                self.changes
+                    .push(change(Change::Removed(NodeStats::of(old_node)?)));
+                continue;
+            };
+
+            if let (Some(old_pack), Some(new_pack)) =
+                (pack_contents(old_node), pack_contents(new_node))
+            {
+                let mut packs = packs.to_vec();
+                packs.push(key.clone());
+                self.compare(&packs, old_pack, new_pack)?;
+                continue;
+            }
+
+            let old_stats = NodeStats::of(old_node)?;
+            let new_stats = NodeStats::of(new_node)?;
+            if old_stats == new_stats {
+                self.unchanged += 1;
+            } else {
+                self.changes.push(change(Change::Modified {
+                    old: old_stats,
+                    new: new_stats,
+                }));
+            }
+        }
+
+        for (key, new_node) in &new {
+            if !old_lookup.contains_key(key) {
+                self.changes.push(ResourceDiff {
+                    packs: packs.to_vec(),
+                    key: key.clone(),
+                    change: Change::Added(NodeStats::of(new_node)?),
+                });
+            }
+        }
+
+        ok()
+    }
+}
+
+/// Assigns diff keys to the nodes.
+fn keyed(nodes: &[OwnedNode]) -> Vec<(DiffKey, &OwnedNode)> {
+    let mut resource_counts = HashMap::new();
+    let mut other_counts = HashMap::new();
+    nodes
+        .iter()
+        .map(|node| {
+            let key = match ResourceKey::of(node) {
+                Some(key) => {
+                    let count = resource_counts.entry(key.clone()).or_insert(0);
+                    *count += 1;
+                    DiffKey::Resource(key, *count - 1)
+                }
+                None => {
+                    let count = other_counts.entry(node.name).or_insert(0);
+                    *count += 1;
+                    DiffKey::Other(node.name, *count - 1)
+                }
+            };
+            (key, node)
+        })
+        .collect()
+}
+
+/// Returns the children of the level data inside a `lvl_` data pack.
+fn pack_contents(node: &OwnedNode) -> Option<&[OwnedNode]> {
+    if node.name != b"lvl_" {
+        return None;
+    }
+    match node.children()? {
+        // Empty level data is read as a leaf node
+        [contents] => Some(contents.children().unwrap_or_default()),
+        _ => None,
+    }
+}
+
+fn read_root(path: &Path) -> AnyResult<OwnedNode> {
+    let root = OwnedNode::read_from(&mut BufReader::new(File::open(path)?))?;
+    ensure!(
+        root.name == b"ucfb",
+        "{} is not a valid data file",
+        path.display()
+    );
+    Ok(root)
+}
+
+impl crate::Command for DiffCommand {
+    fn run(self) -> AnyResult {
+        let old = read_root(&self.old_path)?;
+        let new = read_root(&self.new_path)?;
+        let diff = Diff::new(
+            old.children().unwrap_or_default(),
+            new.children().unwrap_or_default(),
+        )?;
+
+        for change in &diff.changes {
+            println!("{change}");
+        }
+
+        let count = |f: fn(&Change) -> bool| diff.changes.iter().filter(|c| f(&c.change)).count();
+        println!(
+            "{} added, {} removed, {} modified, {} unchanged.",
+            count(|c| matches!(c, Change::Added(_))),
+            count(|c| matches!(c, Change::Removed(_))),
+            count(|c| matches!(c, Change::Modified { .. })),
+            diff.unchanged,
+        );
+
+        ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::fixtures::{pack, texture};
+
+    #[test]
+    fn diff_aligns_resources() {
+        let sky = OwnedNode::new_payload(b"sky_", vec![1]);
+        let old = [
+            texture("kept", &[0]),
+            texture("removed", &[0]),
+            texture("modified", &[0]),
+            sky.clone(),
+            pack("side", vec![texture("packed", &[0])]),
+        ];
+        let new = [
+            pack("side", vec![texture("packed", &[0, 1])]),
+            sky,
+            texture("modified", &[1, 2]),
+            texture("kept", &[0]),
+            texture("added", &[0]),
+        ];
+
+        let diff = Diff::new(&old, &new).unwrap();
+        assert_eq!(diff.unchanged, 2);
+
+        let changes: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|change| {
+                (
+                    change.packs.len(),
+                    change.key.to_string(),
+                    change.change.clone(),
+                )
+            })
+            .collect();
+        assert!(matches!(&changes[..], [
+            (0, removed, Change::Removed(_)),
+            (0, modified, Change::Modified { old, new }),
+            (1, packed, Change::Modified { .. }),
+            (0, added, Change::Added(_)),
+        ] if removed == "tex_ `removed`"
+            && modified == "tex_ `modified`"
+            && new.size == old.size + 1
+            && packed == "tex_ `packed`"
+            && added == "tex_ `added`"));
+    }
+
+    #[test]
+    fn diff_keeps_duplicates() {
+        let old = [texture("twice", &[0]), texture("twice", &[1])];
+        let new = [
+            texture("twice", &[0]),
+            texture("twice", &[2]),
+            texture("twice", &[3]),
+        ];
+
+        let diff = Diff::new(&old, &new).unwrap();
+        assert_eq!(diff.unchanged, 1);
+
+        let changes: Vec<_> = diff.changes.iter().map(ToString::to_string).collect();
+        assert_eq!(changes.len(), 2);
+        assert!(changes[0].starts_with("~ tex_ `twice` (duplicate #1): "));
+        assert!(changes[1].starts_with("+ tex_ `twice` (duplicate #2) ("));
+    }
+}
@@ -1,14 +1,40 @@
+use crate::exporter::texture::{
+    cubemap::{FACE_NAMES, HORIZONTAL_CROSS},
+    decoder::decode_texture,
+};
+use anyhow::{bail, ensure};
+use byteorder::{WriteBytesExt, LE};
 use clap::{Args, Subcommand};
-use std::path::PathBuf;
-use zenit_lvl::game::D3DFormat;
-use zenit_utils::AnyResult;
+use image::RgbaImage;
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Seek},
+    path::PathBuf,
+};
+use zenit_lvl::{
+    audio::{decode_samples, StreamDecoder},
+    game::{
+        D3DFormat, LevelData, LevelStreamFile, LevelTexture, LevelTextureFace, LevelTextureFormat,
+        LevelTextureKind,
+    },
+    node::{read_node_header, NodeRead},
+};
+use zenit_utils::{
+    fnv1a_hash, ok,
+    wav::{write_wav, write_wav_header},
+    AnyResult,
+};
 
 #[derive(Subcommand)]
 pub enum ExportCommand {
     /// Exports a 2D texture.
     Texture(TextureExport),
     /// Exports a 2D cubemap.
-    Cubemap,
+    Cubemap(CubemapExport),
+    /// Exports sound bank samples as WAV files.
+    Sound(SoundExport),
+    /// Exports stream segments (music, voice-over) as WAV files.
+    Stream(StreamExport),
 }
 
 #[derive(Args)]
@@ -24,10 +50,365 @@ pub struct TextureExport {
     /// Mip level to extract.
     #[arg(long, default_value_t = 0)]
     pub mipmap: u32,
+    /// Directory to write the PNG file into.
+    #[arg(long, short = 'o', default_value = ".")]
+    pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CubemapExport {
+    /// Path to the data file.
+    pub file_path: PathBuf,
+    /// Path to the cubemap.
+    ///
+    /// To export the cubemap from a `lvl_` node, path can be separated with a `/`.
+    pub path: String,
+    /// Texture format to use. If only one format is present, it can be omitted.
+    pub format: Option<D3DFormat>,
+    /// Mip level to extract.
+    #[arg(long, default_value_t = 0)]
+    pub mipmap: u32,
+    /// Directory to write the PNG files into.
+    #[arg(long, short = 'o', default_value = ".")]
+    pub output: PathBuf,
+    /// Exports the faces as a single horizontal cross sheet, instead of six separate files.
+    ///
+    /// The sheet is 4 faces wide and 3 faces tall, with +Y on top, followed by a row of -X, +Z,
+    /// +X and -Z, and -Y at the bottom.
+    #[arg(long)]
+    pub sheet: bool,
+}
+
+#[derive(Args)]
+pub struct SoundExport {
+    /// Path to the data file.
+    pub file_path: PathBuf,
+    /// Directory to write the WAV files into.
+    #[arg(long, short = 'o', default_value = ".")]
+    pub output: PathBuf,
+    /// Name of the sample to export. If omitted, all samples are exported.
+    ///
+    /// Since sample names are hashed, exported files are named after the hashes, unless the name
+    /// is specified here.
+    #[arg(long)]
+    pub name: Option<String>,
+}
+
+#[derive(Args)]
+pub struct StreamExport {
+    /// Path to the stream file.
+    pub file_path: PathBuf,
+    /// Directory to write the WAV files into.
+    #[arg(long, short = 'o', default_value = ".")]
+    pub output: PathBuf,
+    /// Name of the stream to export. If omitted, all streams are exported.
+    #[arg(long)]
+    pub stream: Option<String>,
+    /// Name of the segment to export. If omitted, all segments are exported.
+    #[arg(long)]
+    pub segment: Option<String>,
 }
 
 impl crate::Command for ExportCommand {
     fn run(self) -> AnyResult {
-        todo!()
+        match self {
+            ExportCommand::Texture(c) => c.run(),
+            ExportCommand::Cubemap(c) => c.run(),
+            ExportCommand::Sound(c) => c.run(),
+            ExportCommand::Stream(c) => c.run(),
+        }
     }
 }
+
+impl crate::Command for TextureExport {
+    fn run(self) -> AnyResult {
+        let mut file = BufReader::new(File::open(&self.file_path)?);
+        let level = read_level(&mut file)?;
+        let (texture, name) = find_texture(&level, &self.path)?;
+        let format = select_texture_format(texture, self.format)?;
+        ensure!(
+            format.info.kind == LevelTextureKind::D2,
+            "`{name}` is a cubemap, use `export cubemap` instead"
+        );
+        let Some(face) = format.faces.first() else {
+            bail!("texture `{name}` has no data");
+        };
+
+        let image = decode_face(&mut file, format, face, self.mipmap)?;
+
+        fs::create_dir_all(&self.output)?;
+        let path = self.output.join(format!("{name}.png"));
+        println!("  - Writing {}...", path.display());
+        image.save(path)?;
+
+        ok()
+    }
+}
+
+impl crate::Command for CubemapExport {
+    fn run(self) -> AnyResult {
+        let mut file = BufReader::new(File::open(&self.file_path)?);
+        let level = read_level(&mut file)?;
+        let (texture, name) = find_texture(&level, &self.path)?;
+        let format = select_texture_format(texture, self.format)?;
+        ensure!(
+            format.info.kind == LevelTextureKind::Cubemap,
+            "`{name}` isn't a cubemap, use `export texture` instead"
+        );
+        ensure!(
+            format.faces.len() == 6,
+            "cubemap `{name}` has {} faces instead of 6",
+            format.faces.len()
+        );
+
+        let faces = format
+            .faces
+            .iter()
+            .map(|face| decode_face(&mut file, format, face, self.mipmap))
+            .collect::<AnyResult<Vec<_>>>()?;
+
+        fs::create_dir_all(&self.output)?;
+
+        if self.sheet {
+            let sheet = HORIZONTAL_CROSS.join(&faces);
+            let path = self.output.join(format!("{name}.png"));
+            println!("  - Writing {}...", path.display());
+            sheet.save(path)?;
+        } else {
+            for (face, face_name) in faces.iter().zip(FACE_NAMES) {
+                let path = self.output.join(format!("{name}_{face_name}.png"));
+                println!("  - Writing {}...", path.display());
+                face.save(path)?;
+            }
+        }
+
+        ok()
+    }
+}
+
+impl crate::Command for SoundExport {
+    fn run(self) -> AnyResult {
+        let mut file = BufReader::new(File::open(&self.file_path)?);
+        let level = read_level(&mut file)?;
+
+        fs::create_dir_all(&self.output)?;
+
+        let name_hash = self.name.as_deref().map(|name| fnv1a_hash(name.as_bytes()));
+        let exported = export_sounds(&mut file, &level, &self, name_hash)?;
+
+        match (exported, &self.name) {
+            (0, Some(name)) => bail!("sample `{name}` not found"),
+            (0, None) => println!("No sound samples found."),
+            (n, _) => println!("Exported {n} sample(s)."),
+        }
+
+        ok()
+    }
+}
+
+impl crate::Command for StreamExport {
+    fn run(self) -> AnyResult {
+        let mut file = BufReader::new(File::open(&self.file_path)?);
+        let streams = LevelStreamFile::read_from(&mut file)?;
+
+        fs::create_dir_all(&self.output)?;
+
+        let stream_hash = self
+            .stream
+            .as_deref()
+            .map(|name| fnv1a_hash(name.as_bytes()));
+        let segment_hash = self
+            .segment
+            .as_deref()
+            .map(|name| fnv1a_hash(name.as_bytes()));
+        let mut exported = 0;
+
+        for stream in &streams.streams {
+            let info = &stream.info;
+            if stream_hash.is_some_and(|hash| hash != info.name_hash) {
+                continue;
+            }
+
+            for segment in &stream.segments {
+                if segment_hash.is_some_and(|hash| hash != segment.info.name_hash) {
+                    continue;
+                }
+
+                let stream_name = match &self.stream {
+                    Some(name) => name.clone(),
+                    None => format!("{:08x}", info.name_hash),
+                };
+                let segment_name = match &self.segment {
+                    Some(name) => name.clone(),
+                    None => format!("{:08x}", segment.info.name_hash),
+                };
+                let path = self
+                    .output
+                    .join(format!("{stream_name}_{segment_name}.wav"));
+                println!("  - Writing {}...", path.display());
+
+                // Decode chunk by chunk, never keeping the whole segment in memory
+                let mut decoder = StreamDecoder::new(&mut file, info, segment)?;
+                let mut output = BufWriter::new(File::create(path)?);
+                write_wav_header(
+                    &mut output,
+                    info.sample_rate,
+                    info.channels,
+                    (decoder.frame_count() * info.channels as u64) as u32,
+                )?;
+
+                let mut samples = Vec::new();
+                let mut written = 0;
+                while decoder.decode_next(&mut samples)? != 0 {
+                    for &sample in &samples {
+                        output.write_i16::<LE>(sample)?;
+                    }
+                    written += samples.len() as u64;
+                    samples.clear();
+                }
+                ensure!(
+                    written == decoder.frame_count() * info.channels as u64,
+                    "segment data ended early"
+                );
+
+                exported += 1;
+            }
+        }
+
+        match exported {
+            0 => bail!("no matching stream segments found"),
+            n => println!("Exported {n} segment(s)."),
+        }
+
+        ok()
+    }
+}
+
+fn read_level(r: &mut (impl Read + Seek)) -> AnyResult<LevelData> {
+    let root = read_node_header(r)?;
+    ensure!(root.name == b"ucfb", "not a valid data file");
+    LevelData::read_node_at(r, root)
+}
+
+/// Finds a texture by its path, where every segment but the last one is the name of a `lvl_`
+/// data pack. Returns the texture along with its name.
+fn find_texture<'a>(level: &'a LevelData, path: &'a str) -> AnyResult<(&'a LevelTexture, &'a str)> {
+    let (packs, name) = match path.rsplit_once('/') {
+        Some((packs, name)) => (Some(packs), name),
+        None => (None, path),
+    };
+
+    let mut level = level;
+    for pack_name in packs.into_iter().flat_map(|packs| packs.split('/')) {
+        let hash = fnv1a_hash(pack_name.as_bytes());
+        match level.packs.iter().find(|pack| pack.name_hash == hash) {
+            Some(pack) => level = &pack.contents,
+            None => bail!("data pack `{pack_name}` not found"),
+        }
+    }
+
+    match level
+        .textures
+        .iter()
+        .find(|texture| texture.name.as_bytes() == name.as_bytes())
+    {
+        Some(texture) => Ok((texture, name)),
+        None => bail!("texture `{name}` not found"),
+    }
+}
+
+fn select_texture_format(
+    texture: &LevelTexture,
+    format: Option<D3DFormat>,
+) -> AnyResult<&LevelTextureFormat> {
+    let available = || {
+        texture
+            .formats
+            .iter()
+            .map(|format| format!("{:?}", format.info.format))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match (format, texture.formats.as_slice()) {
+        (None, [format]) => Ok(format),
+        (None, []) => bail!("texture has no formats"),
+        (None, _) => bail!("texture has multiple formats ({}), pick one", available()),
+        (Some(format), formats) => match formats.iter().find(|f| f.info.format == format) {
+            Some(format) => Ok(format),
+            None => bail!(
+                "texture has no {format:?} format (available: {})",
+                available()
+            ),
+        },
+    }
+}
+
+/// Decodes a single mip level of a texture face into an image.
+fn decode_face(
+    r: &mut (impl Read + Seek),
+    format: &LevelTextureFormat,
+    face: &LevelTextureFace,
+    mip_level: u32,
+) -> AnyResult<RgbaImage> {
+    let info = &format.info;
+    let Some(mipmap) = face
+        .mipmaps
+        .iter()
+        .find(|mipmap| mipmap.info.mip_level == mip_level)
+    else {
+        bail!(
+            "mip level {mip_level} not found (texture has {} mip levels)",
+            face.mipmaps.len()
+        );
+    };
+
+    // Mip levels are read from the file, so they may be larger than the bit width
+    let width = info.width.checked_shr(mip_level).unwrap_or(0).max(1);
+    let height = info.height.checked_shr(mip_level).unwrap_or(0).max(1);
+    let body = mipmap.body.read_cached(r)?;
+    let rgba = decode_texture(info.format, &body, width, height)?;
+
+    Ok(
+        RgbaImage::from_raw(width as u32, height as u32, rgba)
+            .expect("decoded image size mismatch"),
+    )
+}
+
+/// Exports sound samples from the level and all of its data packs. Returns the amount of
+/// exported samples.
+fn export_sounds(
+    r: &mut (impl Read + Seek),
+    level: &LevelData,
+    command: &SoundExport,
+    name_hash: Option<u32>,
+) -> AnyResult<usize> {
+    let mut exported = 0;
+
+    for bank in &level.sound_banks {
+        for sample in &bank.samples {
+            let info = &sample.info;
+            if name_hash.is_some_and(|hash| hash != info.name_hash) {
+                continue;
+            }
+
+            let file_name = match &command.name {
+                Some(name) => format!("{name}.wav"),
+                None => format!("{:08x}.wav", info.name_hash),
+            };
+            let path = command.output.join(file_name);
+            println!("  - Writing {}...", path.display());
+
+            let samples = decode_samples(info, &sample.data.read_cached(r)?)?;
+            let mut output = BufWriter::new(File::create(path)?);
+            write_wav(&mut output, info.sample_rate, info.channels, &samples)?;
+            exported += 1;
+        }
+    }
+
+    for pack in &level.packs {
+        exported += export_sounds(r, &pack.contents, command, name_hash)?;
+    }
+
+    Ok(exported)
+}
@@ -0,0 +1,52 @@
+//! Node fixtures shared by tests of the commands
+
+use super::node_bytes;
+use crate::exporter::texture::{TextureKind, TextureSpecification};
+use std::{io::Cursor, path::Path};
+use zenit_lvl::{
+    game::D3DFormat,
+    node::{NodeName, NodeWrite, OwnedNode},
+};
+use zenit_utils::fnv1a_hash;
+
+/// A texture node with given name, and `data` in place of its format info.
+pub fn texture(name: &str, data: &[u8]) -> OwnedNode {
+    OwnedNode::new_parent(
+        b"tex_",
+        vec![
+            OwnedNode::new_payload(b"NAME", format!("{name}\0").into_bytes()),
+            OwnedNode::new_payload(b"INFO", data.to_vec()),
+        ],
+    )
+}
+
+/// A `lvl_` data pack with given name.
+pub fn pack(name: &str, contents: Vec<OwnedNode>) -> OwnedNode {
+    OwnedNode::new_parent(
+        b"lvl_",
+        vec![OwnedNode::new_parent(fnv1a_hash(name.as_bytes()), contents)],
+    )
+}
+
+/// An uncompressed color texture specification, exporting the image at `path`.
+pub fn texture_spec(path: &Path) -> TextureSpecification {
+    TextureSpecification {
+        name: String::from("texture"),
+        file: Some(path.to_path_buf()),
+        formats: vec![D3DFormat::A8R8G8B8],
+        kind: TextureKind::Color,
+        mipmaps: None,
+        unfiltered: false,
+        dithering: false,
+        mipmap_filter: Default::default(),
+        linear: false,
+        alpha_coverage: None,
+        power_of_two: Default::default(),
+    }
+}
+
+/// Writes the value as a node with the given name, and loads it back as an [`OwnedNode`].
+pub fn encode_node(name: impl Into<NodeName>, value: &impl NodeWrite) -> OwnedNode {
+    let node = node_bytes(name, value).unwrap();
+    OwnedNode::read_from(&mut Cursor::new(node)).unwrap()
+}
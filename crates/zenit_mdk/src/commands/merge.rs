@@ -1,10 +1,14 @@
-use clap::Args;
+use anyhow::bail;
+use clap::{Args, ValueEnum};
 use std::{
+    collections::HashMap,
+    fmt::{self, Display},
     fs::{self, File},
-    io::{Read, Seek},
-    path::PathBuf,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
 };
-use zenit_utils::{ok, AnyResult};
+use zenit_lvl::node::{NodeName, OwnedNode, OwnedNodeContents};
+use zenit_utils::{ok, AnyResult, AsciiDisplay};
 
 #[derive(Args)]
 pub struct MergeCommand {
@@ -13,34 +17,227 @@ pub struct MergeCommand {
     pub output: PathBuf,
     /// Individual input files to merge
     pub files: Vec<PathBuf>,
+    /// What to do with resources of the same type and name present in several files
+    #[clap(long, value_enum, default_value_t = DuplicatePolicy::FirstWins)]
+    pub duplicates: DuplicatePolicy,
+}
+
+/// Decides which of the duplicated resources ends up in the merged file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DuplicatePolicy {
+    /// The resource from the earliest input file is kept
+    FirstWins,
+    /// The resource from the latest input file is kept, in place of the earliest one
+    LastWins,
+    /// Merging fails
+    Error,
+}
+
+/// Identifies a resource among the top level nodes of a data file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ResourceKey {
+    pub kind: NodeName,
+    pub name: ResourceName,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ResourceName {
+    /// Name stored in a `NAME` child node
+    String(Vec<u8>),
+    /// Hashed name of a `lvl_` data pack
+    Hash(u32),
+}
+
+impl ResourceKey {
+    /// Returns the key of a top level node, if it's a resource that can be detected as
+    /// a duplicate: a texture, script, data pack or WGSL shader.
+    pub fn of(node: &OwnedNode) -> Option<Self> {
+        let name = match &node.name.0 {
+            b"tex_" | b"scr_" | b"WGSL" => {
+                let name = node.find_child(b"NAME")?.payload()?;
+                let name = name.split(|&c| c == 0).next().unwrap_or_default();
+                ResourceName::String(name.to_vec())
+            }
+            b"lvl_" => match node.children()? {
+                [root] => ResourceName::Hash(root.name.into()),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        Some(Self {
+            kind: node.name,
+            name,
+        })
+    }
+}
+
+impl Display for ResourceKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", AsciiDisplay(self.kind.as_ref()))?;
+        match &self.name {
+            ResourceName::String(name) => write!(f, "`{}`", String::from_utf8_lossy(name)),
+            ResourceName::Hash(hash) => write!(f, "0x{hash:08x}"),
+        }
+    }
+}
+
+/// A resource dropped while merging, in favor of one from another file.
+#[derive(Debug)]
+pub struct DroppedResource<'a> {
+    pub key: ResourceKey,
+    /// File the dropped resource came from
+    pub dropped_from: &'a Path,
+    /// File the kept resource came from
+    pub kept_from: &'a Path,
+}
+
+/// Top level nodes of the merged file, along with the file each of them came from.
+pub struct MergedNodes<'a> {
+    pub nodes: Vec<(OwnedNode, &'a Path)>,
+    pub dropped: Vec<DroppedResource<'a>>,
+    /// Indices of keyed resources in `nodes`
+    index: HashMap<ResourceKey, usize>,
+    policy: DuplicatePolicy,
+}
+
+impl<'a> MergedNodes<'a> {
+    pub fn new(policy: DuplicatePolicy) -> Self {
+        Self {
+            nodes: Vec::new(),
+            dropped: Vec::new(),
+            index: HashMap::new(),
+            policy,
+        }
+    }
+
+    /// Adds top level nodes of a file, resolving duplicates according to the policy.
+    pub fn add_file(&mut self, path: &'a Path, nodes: Vec<OwnedNode>) -> AnyResult {
+        for node in nodes {
+            let Some(key) = ResourceKey::of(&node) else {
+                self.nodes.push((node, path));
+                continue;
+            };
+
+            let Some(&index) = self.index.get(&key) else {
+                self.index.insert(key, self.nodes.len());
+                self.nodes.push((node, path));
+                continue;
+            };
+
+            let kept_from = self.nodes[index].1;
+            match self.policy {
+                DuplicatePolicy::FirstWins => self.dropped.push(DroppedResource {
+                    key,
+                    dropped_from: path,
+                    kept_from,
+                }),
+                DuplicatePolicy::LastWins => {
+                    self.nodes[index] = (node, path);
+                    self.dropped.push(DroppedResource {
+                        key,
+                        dropped_from: kept_from,
+                        kept_from: path,
+                    });
+                }
+                DuplicatePolicy::Error => bail!(
+                    "{key} is present in both {} and {}",
+                    kept_from.display(),
+                    path.display()
+                ),
+            }
+        }
+
+        ok()
+    }
 }
 
 impl crate::Command for MergeCommand {
     fn run(self) -> AnyResult {
         println!("Merging files into {}...", self.output.display());
 
-        let _output = fs::OpenOptions::new()
-            .create_new(true)
-            .write(true)
-            .open(&self.output)?;
-
+        let mut merged = MergedNodes::new(self.duplicates);
         for input_path in &self.files {
             println!("  Merging {}...", input_path.display());
 
-            let mut file = File::open(input_path)?;
+            let mut file = BufReader::new(File::open(input_path)?);
+            let root = OwnedNode::read_from(&mut file)?;
 
-            // Validate and skip the header
-            let mut header = [0u8; 4];
-            file.read_exact(&mut header)?;
-            file.seek(std::io::SeekFrom::Current(4))?;
-            if &header != b"ucfb" {
+            // Validate the header
+            if root.name != b"ucfb" {
                 println!("    Warning: this file doesn't contain a valid header. Skipping...");
                 continue;
             }
 
-            todo!()
+            let children = match root.contents {
+                OwnedNodeContents::Children { children, .. } => children,
+                // The file is empty
+                OwnedNodeContents::Payload(_) => vec![],
+            };
+            merged.add_file(input_path, children)?;
         }
 
+        for dropped in &merged.dropped {
+            println!(
+                "  - Dropped {} from {} (kept the one from {})",
+                dropped.key,
+                dropped.dropped_from.display(),
+                dropped.kept_from.display(),
+            );
+        }
+
+        // Only created once merging succeeds, so failures don't leave an empty file behind
+        let output = fs::OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(&self.output)?;
+
+        let root = OwnedNode::new_parent(
+            b"ucfb",
+            merged.nodes.into_iter().map(|(node, _)| node).collect(),
+        );
+        root.write_into(&mut BufWriter::new(output))?;
+
+        println!(
+            "Merged {} node(s), dropped {} duplicate(s).",
+            root.children().unwrap_or_default().len(),
+            merged.dropped.len()
+        );
+
         ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::fixtures::texture;
+
+    fn merge(policy: DuplicatePolicy) -> AnyResult<(Vec<OwnedNode>, usize)> {
+        let mut merged = MergedNodes::new(policy);
+        let sky = OwnedNode::new_payload(b"sky_", vec![]);
+        merged.add_file(
+            Path::new("a.lvl"),
+            vec![texture("a", &[0]), sky.clone(), texture("b", &[0])],
+        )?;
+        merged.add_file(Path::new("b.lvl"), vec![texture("b", &[1]), sky])?;
+
+        let nodes = merged.nodes.into_iter().map(|(node, _)| node).collect();
+        Ok((nodes, merged.dropped.len()))
+    }
+
+    #[test]
+    fn duplicates_follow_policy() {
+        let (nodes, dropped) = merge(DuplicatePolicy::FirstWins).unwrap();
+        assert_eq!(dropped, 1);
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(nodes[2], texture("b", &[0]));
+
+        let (nodes, dropped) = merge(DuplicatePolicy::LastWins).unwrap();
+        assert_eq!(dropped, 1);
+        assert_eq!(nodes.len(), 4);
+        assert_eq!(nodes[2], texture("b", &[1]));
+
+        assert!(merge(DuplicatePolicy::Error).is_err());
+    }
+}
@@ -1,4 +1,23 @@
 pub mod append;
 pub mod build;
+pub mod diff;
 pub mod export;
+#[cfg(test)]
+mod fixtures;
 pub mod merge;
+pub mod tree;
+pub mod validate;
+
+use std::io::Cursor;
+use zenit_lvl::node::{NodeName, NodeWrite, NodeWriter};
+use zenit_utils::AnyResult;
+
+/// Writes the value as a standalone node with the given name, including its padding.
+pub(crate) fn node_bytes(name: impl Into<NodeName>, value: &impl NodeWrite) -> AnyResult<Vec<u8>> {
+    let mut node = Cursor::new(Vec::new());
+    let mut writer = NodeWriter::new(&mut node, name)?;
+    value.write_node(&mut writer)?;
+    writer.finish()?;
+    drop(writer);
+    Ok(node.into_inner())
+}
@@ -0,0 +1,318 @@
+use clap::Args;
+use serde::Serialize;
+use std::{
+    fmt::{self, Display},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+use zenit_lvl::{
+    game::{D3DFormat, LevelScript, LevelTexture, LevelTextureKind},
+    node::{MappedLevelFile, NodeSlice},
+    zext::LevelWgslShader,
+};
+use zenit_utils::{ok, AnyResult, AsciiDisplay, HashDictionary};
+
+#[derive(Args)]
+pub struct TreeCommand {
+    /// Path to the data file.
+    pub file_path: PathBuf,
+    /// Prints the tree as JSON.
+    #[arg(long)]
+    pub json: bool,
+    /// Loads a word list (one name per line) used for resolving hashed names. Can be repeated.
+    #[arg(long)]
+    pub hash_dictionary: Vec<PathBuf>,
+}
+
+/// A node of the printed tree.
+///
+/// Whether a node has children is guessed with the same heuristic as
+/// [`zenit_lvl::node::read_node_children`], so leaf nodes whose payload happens to look like
+/// a list of nodes are shown with children.
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    /// Offset of the node's header in the file
+    pub offset: u64,
+    /// Size of the node's payload
+    pub size: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<NodeSummary>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeNode>,
+}
+
+/// Decoded information about a node of a known type.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeSummary {
+    Texture {
+        name: String,
+        formats: Vec<TextureFormatSummary>,
+    },
+    Script {
+        name: String,
+    },
+    Pack {
+        hash: u32,
+        /// Name of the pack, if it's in the hash dictionary
+        name: Option<String>,
+    },
+    Shader {
+        name: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct TextureFormatSummary {
+    pub format: D3DFormat,
+    pub width: u16,
+    pub height: u16,
+    pub mipmaps: u16,
+    pub cubemap: bool,
+}
+
+impl TreeNode {
+    pub fn new(node: NodeSlice, names: &HashDictionary) -> Self {
+        let children = match node.children() {
+            Ok(children) => children.map(|child| Self::new(child, names)).collect(),
+            Err(_) => vec![],
+        };
+
+        Self {
+            name: AsciiDisplay(node.name().as_ref()).to_string(),
+            offset: node.header().header_position,
+            size: node.header().size,
+            summary: NodeSummary::new(node, names),
+            children,
+        }
+    }
+
+    fn print(&self, w: &mut impl Write, depth: usize) -> io::Result<()> {
+        write!(
+            w,
+            "{:indent$}{} @ {:#x}, {} bytes",
+            "",
+            self.name,
+            self.offset,
+            self.size,
+            indent = depth * 2
+        )?;
+        match &self.summary {
+            Some(summary) => writeln!(w, " - {summary}")?,
+            None => writeln!(w)?,
+        }
+
+        for child in &self.children {
+            child.print(w, depth + 1)?;
+        }
+        ok()
+    }
+}
+
+impl NodeSummary {
+    /// Decodes the summary of a known node, returns `None` for unknown or invalid nodes.
+    pub fn new(node: NodeSlice, names: &HashDictionary) -> Option<Self> {
+        let summary = match node.name().as_ref() {
+            b"tex_" => {
+                let texture = node.read::<LevelTexture>().ok()?;
+                NodeSummary::Texture {
+                    name: texture.name.to_string_lossy().into_owned(),
+                    formats: texture
+                        .formats
+                        .iter()
+                        .map(|format| TextureFormatSummary {
+                            format: format.info.format,
+                            width: format.info.width,
+                            height: format.info.height,
+                            mipmaps: format.info.mipmaps,
+                            cubemap: format.info.kind == LevelTextureKind::Cubemap,
+                        })
+                        .collect(),
+                }
+            }
+            b"scr_" => NodeSummary::Script {
+                name: (node.read::<LevelScript>().ok()?.name)
+                    .to_string_lossy()
+                    .into_owned(),
+            },
+            b"lvl_" => {
+                let mut children = node.children().ok()?;
+                let hash = children.next()?.name().into();
+                NodeSummary::Pack {
+                    hash,
+                    name: names.lookup(hash).map(str::to_string),
+                }
+            }
+            b"WGSL" => NodeSummary::Shader {
+                name: (node.read::<LevelWgslShader>().ok()?.name)
+                    .to_string_lossy()
+                    .into_owned(),
+            },
+            _ => return None,
+        };
+        Some(summary)
+    }
+}
+
+impl Display for NodeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeSummary::Texture { name, formats } => {
+                write!(f, "texture `{name}`")?;
+                for format in formats {
+                    write!(
+                        f,
+                        ", {:?} {}x{} ({} mips{})",
+                        format.format,
+                        format.width,
+                        format.height,
+                        format.mipmaps,
+                        if format.cubemap { ", cubemap" } else { "" }
+                    )?;
+                }
+                ok()
+            }
+            NodeSummary::Script { name } => write!(f, "script `{name}`"),
+            NodeSummary::Pack { hash, name } => match name {
+                Some(name) => write!(f, "data pack `{name}`"),
+                None => write!(f, "data pack 0x{hash:08x}"),
+            },
+            NodeSummary::Shader { name } => write!(f, "WGSL shader `{name}`"),
+        }
+    }
+}
+
+impl crate::Command for TreeCommand {
+    fn run(self) -> AnyResult {
+        let mut names = HashDictionary::with_builtin_names();
+        for path in &self.hash_dictionary {
+            names.load_word_list(path)?;
+        }
+
+        let file = MappedLevelFile::open(&self.file_path)?;
+        let tree = TreeNode::new(file.root()?, &names);
+
+        let mut stdout = BufWriter::new(io::stdout().lock());
+        if self.json {
+            serde_json::to_writer_pretty(&mut stdout, &tree)?;
+            writeln!(stdout)?;
+        } else {
+            tree.print(&mut stdout, 0)?;
+        }
+        stdout.flush()?;
+
+        ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::fixtures::{encode_node, pack, texture_spec};
+    use crate::exporter::texture::TextureSpecification;
+    use serde_json::json;
+    use std::{env, ffi::CString, fs, io::Cursor};
+    use zenit_lvl::node::OwnedNode;
+    use zenit_utils::fnv1a_hash;
+
+    fn texture() -> OwnedNode {
+        let directory = env::temp_dir().join(format!("zenit_mdk_tree_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let image_path = directory.join("image.png");
+        image::RgbaImage::new(4, 2).save(&image_path).unwrap();
+
+        let texture = TextureSpecification {
+            name: String::from("icon"),
+            ..texture_spec(&image_path)
+        }
+        .export()
+        .unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        encode_node(b"tex_", &texture)
+    }
+
+    fn script(name: &str) -> OwnedNode {
+        let script = LevelScript {
+            name: CString::new(name).unwrap(),
+            info: 1,
+            data: zenit_lvl::node::LazyData::Write(b"return".to_vec()),
+        };
+        encode_node(b"scr_", &script)
+    }
+
+    #[test]
+    fn tree_summaries() {
+        let root = OwnedNode::new_parent(
+            b"ucfb",
+            vec![
+                texture(),
+                script("ifs_main"),
+                pack("side", vec![script("side_cmn")]),
+                pack("unknown", vec![]),
+            ],
+        );
+        let mut file = Cursor::new(Vec::new());
+        root.write_into(&mut file).unwrap();
+        let file = file.into_inner();
+
+        let mut names = HashDictionary::new();
+        names.insert("side");
+        let tree = TreeNode::new(NodeSlice::root(&file).unwrap(), &names);
+
+        let summaries: Vec<_> = tree
+            .children
+            .iter()
+            .map(|child| child.summary.as_ref().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            summaries,
+            [
+                "texture `icon`, A8R8G8B8 4x2 (3 mips)",
+                "script `ifs_main`",
+                "data pack `side`",
+                &format!("data pack 0x{:08x}", fnv1a_hash(b"unknown")),
+            ]
+        );
+
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(json["name"], "ucfb");
+        assert_eq!(json["offset"], 0);
+        assert_eq!(json["size"], file.len() - 8);
+        assert!(json.get("summary").is_none());
+
+        let children = json["children"].as_array().unwrap();
+        assert_eq!(children.len(), 4);
+        assert_eq!(children[0]["summary"]["type"], "texture");
+        assert_eq!(
+            children[0]["summary"]["formats"][0],
+            json!({
+                "format": serde_json::to_value(D3DFormat::A8R8G8B8).unwrap(),
+                "width": 4,
+                "height": 2,
+                "mipmaps": 3,
+                "cubemap": false,
+            })
+        );
+        assert_eq!(
+            children[2]["summary"],
+            json!({"type": "pack", "hash": fnv1a_hash(b"side"), "name": "side"})
+        );
+        assert_eq!(children[3]["summary"]["name"], serde_json::Value::Null);
+
+        // Children of the pack, and leaf nodes without any
+        let side = &children[2]["children"][0];
+        assert_eq!(
+            side["name"],
+            format!("{}", AsciiDisplay(&fnv1a_hash(b"side").to_le_bytes()))
+        );
+        assert_eq!(
+            side["children"][0]["summary"],
+            json!({"type": "script", "name": "side_cmn"})
+        );
+        let name = &side["children"][0]["children"][0];
+        assert_eq!(name["name"], "NAME");
+        assert!(name.get("children").is_none());
+    }
+}
@@ -0,0 +1,432 @@
+use crate::exporter::texture::decoder::decoded_input_size;
+use anyhow::bail;
+use clap::Args;
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    path::PathBuf,
+};
+use zenit_lvl::{
+    game::{LevelTexture, LevelTextureKind},
+    node::{LazyData, MappedLevelFile, NodeRead, NodeReadError, NodeSlice, UnknownNodePolicy},
+};
+use zenit_utils::{ok, AnyResult, AsciiDisplay};
+
+#[derive(Args)]
+pub struct ValidateCommand {
+    /// Path to the data file.
+    pub file_path: PathBuf,
+    /// Prints the diagnostics as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Something unusual, which the loaders can deal with
+    Warning,
+    /// Something the loaders don't expect, which will make them fail or misbehave
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Offset of the offending node's header in the file
+    pub offset: u64,
+    /// Path to the offending resource, like ``tex_ `name`/FMT_ #0``
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(
+            f,
+            "{severity}: {:#010x}: {}: {}",
+            self.offset, self.path, self.message
+        )
+    }
+}
+
+/// Checks the invariants of a level file assumed by the loaders.
+#[derive(Debug, Default)]
+pub struct Validator {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator {
+    /// Validates the level data under the root node.
+    pub fn validate(file: &[u8]) -> Self {
+        let mut validator = Self::default();
+        match NodeSlice::root(file) {
+            Ok(root) if root.name() == b"ucfb" => validator.level(root, "ucfb"),
+            Ok(root) => validator.error(root, "ucfb", "not a valid data file"),
+            Err(error) => validator.diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                offset: 0,
+                path: "ucfb".into(),
+                message: format!("{error:#}"),
+            }),
+        }
+        validator
+    }
+
+    fn push(&mut self, severity: Severity, node: NodeSlice, path: &str, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            offset: node.header().header_position,
+            path: path.to_string(),
+            message,
+        });
+    }
+
+    fn error(&mut self, node: NodeSlice, path: &str, message: impl Into<String>) {
+        self.push(Severity::Error, node, path, message.into());
+    }
+
+    fn warning(&mut self, node: NodeSlice, path: &str, message: impl Into<String>) {
+        self.push(Severity::Warning, node, path, message.into());
+    }
+
+    /// Reports a read error, pointing at the node it happened in.
+    fn read_error(
+        &mut self,
+        severity: Severity,
+        node: NodeSlice,
+        path: &str,
+        error: anyhow::Error,
+    ) {
+        let offset = match error.downcast_ref::<NodeReadError>() {
+            Some(error) => error.offset(),
+            None => None,
+        };
+        self.diagnostics.push(Diagnostic {
+            severity,
+            offset: offset.unwrap_or(node.header().header_position),
+            path: path.to_string(),
+            message: format!("{error:#}"),
+        });
+    }
+
+    /// Reads the node, reporting unknown child nodes as warnings.
+    fn read<T: NodeRead>(&mut self, node: NodeSlice, path: &str) -> Option<T> {
+        let strict_error = match UnknownNodePolicy::Error.apply(|| node.read::<T>()) {
+            Ok(value) => return Some(value),
+            Err(error) => error,
+        };
+
+        match node.read::<T>() {
+            Ok(value) => {
+                self.read_error(Severity::Warning, node, path, strict_error);
+                Some(value)
+            }
+            Err(error) => {
+                self.read_error(Severity::Error, node, path, error);
+                None
+            }
+        }
+    }
+
+    /// Returns the children of a node, reporting nodes without a hierarchy as errors.
+    fn children<'a>(&mut self, node: NodeSlice<'a>, path: &str) -> Vec<NodeSlice<'a>> {
+        match node.children() {
+            Ok(children) => children.collect(),
+            // Empty nodes are fine
+            Err(_) if node.payload().iter().all(|&byte| byte == 0) => vec![],
+            Err(error) => {
+                self.error(node, path, format!("{error:#}"));
+                vec![]
+            }
+        }
+    }
+
+    fn level(&mut self, level: NodeSlice, path: &str) {
+        let mut pack_hashes = HashSet::new();
+        let mut texture_names = HashSet::new();
+
+        for child in self.children(level, path) {
+            match child.name().as_ref() {
+                b"tex_" => {
+                    let name = self.string(child, path, b"NAME");
+                    let path = format!("{path}/tex_ `{name}`");
+                    if !texture_names.insert(name) {
+                        self.warning(child, &path, "duplicate texture name");
+                    }
+                    self.texture(child, &path);
+                }
+                b"scr_" | b"WGSL" => {
+                    let name = self.string(child, path, b"NAME");
+                    let kind = AsciiDisplay(child.name().as_ref()).to_string();
+                    let path = format!("{path}/{kind} `{name}`");
+                    if child.name() == b"WGSL" {
+                        self.string(child, &path, b"CODE");
+                    }
+                }
+                b"lvl_" => {
+                    let children = self.children(child, path);
+                    let [contents] = children.as_slice() else {
+                        let message = format!(
+                            "data pack must contain a single node, found {}",
+                            children.len()
+                        );
+                        self.error(child, &format!("{path}/lvl_"), message);
+                        continue;
+                    };
+
+                    let hash: u32 = contents.name().into();
+                    let path = format!("{path}/lvl_ 0x{hash:08x}");
+                    if !pack_hashes.insert(hash) {
+                        self.warning(child, &path, "duplicate data pack hash");
+                    }
+                    self.level(*contents, &path);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks if the child node is a NUL-terminated string, returning it for use in paths.
+    fn string(&mut self, node: NodeSlice, path: &str, name: &[u8; 4]) -> String {
+        let field = AsciiDisplay(name);
+        let Some(child) = node.find_child(name) else {
+            self.error(node, path, format!("missing {field} node"));
+            return String::from("?");
+        };
+
+        let payload = child.payload();
+        let string = payload.split(|&byte| byte == 0).next().unwrap_or_default();
+        if payload.last() != Some(&0) {
+            self.error(child, path, format!("{field} isn't NUL-terminated"));
+        } else if string.len() != payload.len() - 1 {
+            self.warning(child, path, format!("{field} contains data after a NUL"));
+        }
+        String::from_utf8_lossy(string).into_owned()
+    }
+
+    fn texture(&mut self, node: NodeSlice, path: &str) {
+        let Some(texture) = self.read::<LevelTexture>(node, path) else {
+            return;
+        };
+
+        // tex_:INFO is a u32 count followed by a list of formats
+        let listed: Option<Vec<u32>> = texture
+            .info
+            .chunks(4)
+            .map(|chunk| Some(u32::from_le_bytes(chunk.try_into().ok()?)))
+            .collect();
+        let actual: Vec<u32> = texture
+            .formats
+            .iter()
+            .map(|f| f.info.format.into())
+            .collect();
+        match listed.as_deref() {
+            Some([count, formats @ ..])
+                if *count as usize == formats.len() && formats == actual.as_slice() => {}
+            _ => {
+                let info = node.find_child(b"INFO").unwrap_or(node);
+                self.error(info, path, "format list doesn't match the FMT_ nodes");
+            }
+        }
+
+        let format_nodes = node
+            .children()
+            .map(|children| children.filter(|c| c.name() == b"FMT_").collect())
+            .unwrap_or_else(|_| vec![]);
+
+        for (i, (format, format_node)) in texture.formats.iter().zip(format_nodes).enumerate() {
+            let path = format!("{path}/FMT_ #{i}");
+            let info = &format.info;
+
+            let expected_faces = match info.kind {
+                LevelTextureKind::D2 => 1,
+                LevelTextureKind::Cubemap => 6,
+            };
+            if format.faces.len() != expected_faces {
+                let message = format!(
+                    "expected {expected_faces} face(s) for a {:?} texture, found {}",
+                    info.kind,
+                    format.faces.len()
+                );
+                self.error(format_node, &path, message);
+            }
+
+            let face_nodes = format_node
+                .children()
+                .map(|children| children.filter(|c| c.name() == b"FACE").collect())
+                .unwrap_or_else(|_| vec![]);
+
+            for (j, (face, face_node)) in format.faces.iter().zip(face_nodes).enumerate() {
+                let path = format!("{path}/FACE #{j}");
+                if face.mipmaps.len() != info.mipmaps as usize {
+                    let message = format!(
+                        "expected {} mip level(s), found {}",
+                        info.mipmaps,
+                        face.mipmaps.len()
+                    );
+                    self.error(face_node, &path, message);
+                }
+
+                for (level, mipmap) in face.mipmaps.iter().enumerate() {
+                    let path = format!("{path}/LVL_ #{level}");
+                    let LazyData::Read(header) = mipmap.body else {
+                        continue;
+                    };
+                    // Always in bounds, since it's been just read
+                    let body_node = NodeSlice::new(node.file(), header).unwrap();
+                    let body = body_node.payload();
+
+                    if mipmap.info.mip_level as usize != level {
+                        let message =
+                            format!("mip level is {}, expected {level}", mipmap.info.mip_level);
+                        self.error(body_node, &path, message);
+                    }
+
+                    if mipmap.info.body_size as usize != body.len() {
+                        let message = format!(
+                            "body size is listed as {}, but the body is {} bytes",
+                            mipmap.info.body_size,
+                            body.len()
+                        );
+                        self.error(body_node, &path, message);
+                    }
+
+                    // The mip count is read from the file, so it can exceed the bit width
+                    let width = (info.width as usize).checked_shr(level as u32);
+                    let height = (info.height as usize).checked_shr(level as u32);
+                    let width = width.unwrap_or(0).max(1);
+                    let height = height.unwrap_or(0).max(1);
+                    let expected = decoded_input_size(info.format, width, height);
+                    if body.len() != expected {
+                        let message = format!(
+                            "body is {} bytes, expected {expected} for a {width}x{height} {:?} \
+                             texture",
+                            body.len(),
+                            info.format
+                        );
+                        self.error(body_node, &path, message);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl crate::Command for ValidateCommand {
+    fn run(self) -> AnyResult {
+        let file = MappedLevelFile::open(&self.file_path)?;
+        let validator = Validator::validate(&file);
+
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&validator.diagnostics)?);
+        } else {
+            for diagnostic in &validator.diagnostics {
+                println!("{diagnostic}");
+            }
+        }
+
+        let errors = validator
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .count();
+        if errors > 0 {
+            bail!("validation failed with {errors} error(s)");
+        }
+
+        ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{ffi::CString, io::Cursor};
+    use zenit_lvl::{
+        game::{
+            D3DFormat, LevelTextureFace, LevelTextureFormat, LevelTextureFormatInfo,
+            LevelTextureMipmap, LevelTextureMipmapInfo,
+        },
+        node::NodeWriter,
+    };
+
+    #[test]
+    fn invalid_textures_are_reported() {
+        let mipmap = LevelTextureMipmap {
+            info: LevelTextureMipmapInfo {
+                mip_level: 0,
+                body_size: 32,
+            },
+            // An 8x8 DXT1 texture takes up 32 bytes
+            body: vec![0; 16].into(),
+        };
+        let texture = LevelTexture {
+            name: CString::new("broken").unwrap(),
+            info: [1u32, D3DFormat::DXT3.into()]
+                .map(u32::to_le_bytes)
+                .concat(),
+            formats: vec![LevelTextureFormat {
+                info: LevelTextureFormatInfo {
+                    format: D3DFormat::DXT1,
+                    width: 8,
+                    height: 8,
+                    unk_0x08: 1,
+                    mipmaps: 2,
+                    kind: LevelTextureKind::Cubemap,
+                },
+                faces: vec![LevelTextureFace {
+                    mipmaps: vec![mipmap],
+                }],
+                unfiltered: false,
+            }],
+        };
+
+        let mut file = Cursor::new(Vec::new());
+        let mut writer = NodeWriter::new(&mut file, b"ucfb").unwrap();
+        writer.write_node(b"tex_", texture).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        let validator = Validator::validate(&file.into_inner());
+        let messages: Vec<_> = validator
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.path.as_str(), diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                (
+                    "ucfb/tex_ `broken`",
+                    "format list doesn't match the FMT_ nodes"
+                ),
+                (
+                    "ucfb/tex_ `broken`/FMT_ #0",
+                    "expected 6 face(s) for a Cubemap texture, found 1"
+                ),
+                (
+                    "ucfb/tex_ `broken`/FMT_ #0/FACE #0",
+                    "expected 2 mip level(s), found 1"
+                ),
+                (
+                    "ucfb/tex_ `broken`/FMT_ #0/FACE #0/LVL_ #0",
+                    "body size is listed as 32, but the body is 16 bytes"
+                ),
+                (
+                    "ucfb/tex_ `broken`/FMT_ #0/FACE #0/LVL_ #0",
+                    "body is 16 bytes, expected 32 for a 8x8 DXT1 texture"
+                ),
+            ]
+        );
+        assert!(validator
+            .diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity == Severity::Error));
+    }
+}
@@ -21,24 +21,58 @@ pub trait TextureConverter {
     fn size_after_conversion(&self, width: u16, height: u16) -> usize;
 }
 
-pub fn create_converter(format: D3DFormat) -> Box<dyn TextureConverter> {
+/// Creates a converter for the format.
+///
+/// With `dithering` enabled, formats with less than 8 bits per color channel are encoded with
+/// ordered dithering, which trades banding of smooth gradients for a subtle pattern. Alpha
+/// channels are never dithered, so that edges of cutouts stay sharp.
+pub fn create_converter(format: D3DFormat, dithering: bool) -> Box<dyn TextureConverter> {
     use D3DFormat::*;
     match format {
         DXT1 => Box::new(DXT1Exporter),
         DXT3 => Box::new(DXT3Exporter),
-        A8R8G8B8 => todo!(),
-        R5G6B5 => Box::new(R5G6B5Exporter),
-        A1R5G5B5 => todo!(),
-        A4R4G4B4 => todo!(),
-        A8 => todo!(),
-        L8 => todo!(),
-        A8L8 => todo!(),
-        A4L4 => todo!(),
-        V8U8 => todo!(),
+        A8R8G8B8 => Box::new(A8R8G8B8Exporter),
+        R5G6B5 => Box::new(Packed16Exporter::new([5, 6, 5, 0], dithering)),
+        A1R5G5B5 => Box::new(Packed16Exporter::new([5, 5, 5, 1], dithering)),
+        A4R4G4B4 => Box::new(Packed16Exporter::new([4, 4, 4, 4], dithering)),
+        A8 => Box::new(A8Exporter),
+        L8 => Box::new(L8Exporter),
+        A8L8 => Box::new(A8L8Exporter),
+        A4L4 => Box::new(A4L4Exporter { dithering }),
+        V8U8 => Box::new(V8U8Exporter),
         R8G8B8 => Box::new(R8G8B8Exporter),
     }
 }
 
+/// 4x4 Bayer matrix, used for ordered dithering
+const BAYER_MATRIX: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Reduces an 8-bit value to given amount of bits, rounding to the nearest value. If dithering
+/// is enabled, the rounding threshold is instead taken from the Bayer matrix, based on the
+/// pixel's position.
+fn quantize(value: u8, bits: u32, dithering: Option<(usize, usize)>) -> u16 {
+    debug_assert!(0 < bits && bits <= 8);
+    let max = (1u32 << bits) - 1;
+    let threshold = match dithering {
+        Some((x, y)) => (BAYER_MATRIX[y % 4][x % 4] as u32 * 255 + 127) / 16,
+        None => 127,
+    };
+    ((value as u32 * max + threshold) / 255) as u16
+}
+
+/// Iterates over pixels of the image, along with their coordinates.
+fn pixels(rgba: &[u8], width: u16) -> impl Iterator<Item = ((usize, usize), [u8; 4])> + '_ {
+    rgba.chunks_exact(4).enumerate().map(move |(i, pixel)| {
+        let position = (i % width as usize, i / width as usize);
+        (position, pixel.try_into().unwrap())
+    })
+}
+
+/// Computes the luminance of an RGB color, using the Rec. 601 weights.
+fn luminance(r: u8, g: u8, b: u8) -> u8 {
+    ((r as u32 * 77 + g as u32 * 150 + b as u32 * 29 + 128) >> 8) as u8
+}
+
 // TODO: figure out why DXT1 textures weigh so much
 pub struct DXT1Exporter;
 impl TextureConverter for DXT1Exporter {
@@ -110,9 +144,21 @@ impl TextureConverter for DXT3Exporter {
     }
 }
 
-/// R5G6B5 exporter. Clamps 8-bit channels by effectively rounding down.
-pub struct R5G6B5Exporter;
-impl TextureConverter for R5G6B5Exporter {
+/// Exporter for 16-bit ARGB formats, with given amount of bits per channel.
+pub struct Packed16Exporter {
+    /// Bits per red, green, blue and alpha channel
+    pub bits: [u32; 4],
+    pub dithering: bool,
+}
+
+impl Packed16Exporter {
+    pub fn new(bits: [u32; 4], dithering: bool) -> Self {
+        debug_assert_eq!(bits.iter().sum::<u32>(), 16);
+        Self { bits, dithering }
+    }
+}
+
+impl TextureConverter for Packed16Exporter {
     fn write_texture(
         &self,
         writer: &mut dyn Write,
@@ -121,12 +167,20 @@ impl TextureConverter for R5G6B5Exporter {
         height: u16,
     ) -> AnyResult {
         debug_assert_eq!(width as usize * height as usize * 4, rgba.len());
-        for (r, g, b, a) in rgba.iter().cloned().tuples() {
-            ensure!(a == 255, "the texture isn't fully transparent");
-            let clamped_r = (r >> 3) as u16;
-            let clamped_g = (g >> 2) as u16;
-            let clamped_b = (b >> 3) as u16;
-            let combined = clamped_r << 11 | clamped_g << 6 | clamped_b << 0;
+        let [r_bits, g_bits, b_bits, a_bits] = self.bits;
+        for (position, [r, g, b, a]) in pixels(rgba, width) {
+            let dithering = self.dithering.then_some(position);
+            let alpha = match a_bits {
+                0 => {
+                    ensure!(a == 255, "the texture isn't fully opaque");
+                    0
+                }
+                _ => quantize(a, a_bits, None) << (r_bits + g_bits + b_bits),
+            };
+            let combined = alpha
+                | quantize(r, r_bits, dithering) << (g_bits + b_bits)
+                | quantize(g, g_bits, dithering) << b_bits
+                | quantize(b, b_bits, dithering);
             writer.write_u16::<LE>(combined)?;
         }
         ok()
@@ -138,6 +192,149 @@ impl TextureConverter for R5G6B5Exporter {
     }
 }
 
+pub struct A8R8G8B8Exporter;
+impl TextureConverter for A8R8G8B8Exporter {
+    fn write_texture(
+        &self,
+        writer: &mut dyn Write,
+        rgba: &[u8],
+        width: u16,
+        height: u16,
+    ) -> AnyResult {
+        debug_assert_eq!(width as usize * height as usize * 4, rgba.len());
+        for (r, g, b, a) in rgba.iter().cloned().tuples() {
+            // Little endian 0xAARRGGBB -> BB GG RR AA in memory
+            writer.write_all(&[b, g, r, a])?;
+        }
+        ok()
+    }
+
+    fn size_after_conversion(&self, width: u16, height: u16) -> usize {
+        // 4 bytes per pixel
+        width as usize * height as usize * 4
+    }
+}
+
+/// Exports only the alpha channel.
+pub struct A8Exporter;
+impl TextureConverter for A8Exporter {
+    fn write_texture(
+        &self,
+        writer: &mut dyn Write,
+        rgba: &[u8],
+        width: u16,
+        height: u16,
+    ) -> AnyResult {
+        debug_assert_eq!(width as usize * height as usize * 4, rgba.len());
+        for (_, _, _, a) in rgba.iter().cloned().tuples() {
+            writer.write_u8(a)?;
+        }
+        ok()
+    }
+
+    fn size_after_conversion(&self, width: u16, height: u16) -> usize {
+        // 1 byte per pixel
+        width as usize * height as usize
+    }
+}
+
+pub struct L8Exporter;
+impl TextureConverter for L8Exporter {
+    fn write_texture(
+        &self,
+        writer: &mut dyn Write,
+        rgba: &[u8],
+        width: u16,
+        height: u16,
+    ) -> AnyResult {
+        debug_assert_eq!(width as usize * height as usize * 4, rgba.len());
+        for (r, g, b, a) in rgba.iter().cloned().tuples() {
+            ensure!(a == 255, "the texture isn't fully opaque");
+            writer.write_u8(luminance(r, g, b))?;
+        }
+        ok()
+    }
+
+    fn size_after_conversion(&self, width: u16, height: u16) -> usize {
+        // 1 byte per pixel
+        width as usize * height as usize
+    }
+}
+
+pub struct A8L8Exporter;
+impl TextureConverter for A8L8Exporter {
+    fn write_texture(
+        &self,
+        writer: &mut dyn Write,
+        rgba: &[u8],
+        width: u16,
+        height: u16,
+    ) -> AnyResult {
+        debug_assert_eq!(width as usize * height as usize * 4, rgba.len());
+        for (r, g, b, a) in rgba.iter().cloned().tuples() {
+            // Little endian 0xAALL -> LL AA in memory
+            writer.write_all(&[luminance(r, g, b), a])?;
+        }
+        ok()
+    }
+
+    fn size_after_conversion(&self, width: u16, height: u16) -> usize {
+        // 2 bytes per pixel
+        width as usize * height as usize * 2
+    }
+}
+
+pub struct A4L4Exporter {
+    pub dithering: bool,
+}
+
+impl TextureConverter for A4L4Exporter {
+    fn write_texture(
+        &self,
+        writer: &mut dyn Write,
+        rgba: &[u8],
+        width: u16,
+        height: u16,
+    ) -> AnyResult {
+        debug_assert_eq!(width as usize * height as usize * 4, rgba.len());
+        for (position, [r, g, b, a]) in pixels(rgba, width) {
+            let l = quantize(luminance(r, g, b), 4, self.dithering.then_some(position));
+            let a = quantize(a, 4, None);
+            writer.write_u8((a << 4 | l) as u8)?;
+        }
+        ok()
+    }
+
+    fn size_after_conversion(&self, width: u16, height: u16) -> usize {
+        // 1 byte per pixel
+        width as usize * height as usize
+    }
+}
+
+/// Exports a normal map as a signed 2D vector map, from its red and green channels.
+pub struct V8U8Exporter;
+impl TextureConverter for V8U8Exporter {
+    fn write_texture(
+        &self,
+        writer: &mut dyn Write,
+        rgba: &[u8],
+        width: u16,
+        height: u16,
+    ) -> AnyResult {
+        debug_assert_eq!(width as usize * height as usize * 4, rgba.len());
+        for (r, g, _, _) in rgba.iter().cloned().tuples() {
+            // Maps [0; 255] to [-128; 127], so that 128 is 0
+            writer.write_all(&[r.wrapping_sub(128), g.wrapping_sub(128)])?;
+        }
+        ok()
+    }
+
+    fn size_after_conversion(&self, width: u16, height: u16) -> usize {
+        // 2 bytes per pixel
+        width as usize * height as usize * 2
+    }
+}
+
 pub struct R8G8B8Exporter;
 impl TextureConverter for R8G8B8Exporter {
     fn write_texture(
@@ -149,7 +346,7 @@ impl TextureConverter for R8G8B8Exporter {
     ) -> AnyResult {
         debug_assert_eq!(width as usize * height as usize * 4, rgba.len());
         for (r, g, b, a) in rgba.iter().cloned().tuples() {
-            ensure!(a == 255, "the texture isn't fully transparent");
+            ensure!(a == 255, "the texture isn't fully opaque");
             // Little endian 0xRRGGBB -> BB GG RR in memory
             writer.write_u8(b)?;
             writer.write_u8(g)?;
@@ -163,3 +360,75 @@ impl TextureConverter for R8G8B8Exporter {
         width as usize * height as usize * 3
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter::texture::decoder::{decode_texture, decoded_input_size};
+
+    fn encode(format: D3DFormat, rgba: &[u8], width: u16, height: u16, dithering: bool) -> Vec<u8> {
+        let mut output = vec![];
+        create_converter(format, dithering)
+            .write_texture(&mut output, rgba, width, height)
+            .unwrap();
+        output
+    }
+
+    /// Generates texture data containing every possible pixel value for formats of up to 16 bits
+    /// per pixel, or pseudo-random data otherwise.
+    fn test_data(format: D3DFormat) -> (Vec<u8>, u16, u16) {
+        match decoded_input_size(format, 1, 1) {
+            1 => ((0..=u8::MAX).collect(), 16, 16),
+            2 => (
+                (0..=u16::MAX).flat_map(u16::to_le_bytes).collect(),
+                256,
+                256,
+            ),
+            n => {
+                let data = (0..64 * 64 * n as u32)
+                    .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+                    .collect();
+                (data, 64, 64)
+            }
+        }
+    }
+
+    #[test]
+    fn formats_round_trip() {
+        use D3DFormat::*;
+        for format in [
+            A8R8G8B8, R5G6B5, A1R5G5B5, A4R4G4B4, A8, L8, A8L8, A4L4, V8U8, R8G8B8,
+        ] {
+            let (data, width, height) = test_data(format);
+            let rgba = decode_texture(format, &data, width, height).unwrap();
+            assert_eq!(
+                encode(format, &rgba, width, height, false),
+                data,
+                "{format:?} doesn't round trip"
+            );
+        }
+    }
+
+    #[test]
+    fn r5g6b5_packs_channels() {
+        let rgba = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255];
+        let expected = [0xf800u16, 0x07e0, 0x001f].map(u16::to_le_bytes).concat();
+        assert_eq!(encode(D3DFormat::R5G6B5, &rgba, 3, 1, false), expected);
+    }
+
+    #[test]
+    fn dithering_preserves_average() {
+        // A gray between two 5-bit levels, which would otherwise be rounded down
+        let gray = 8 * 10 + 4;
+        let rgba = [gray, gray, gray, 255].repeat(16);
+
+        let plain = encode(D3DFormat::R5G6B5, &rgba, 4, 4, false);
+        let plain = decode_texture(D3DFormat::R5G6B5, &plain, 4, 4).unwrap();
+        assert!(plain.iter().step_by(4).all(|&r| r == plain[0]));
+
+        let dithered = encode(D3DFormat::R5G6B5, &rgba, 4, 4, true);
+        let dithered = decode_texture(D3DFormat::R5G6B5, &dithered, 4, 4).unwrap();
+        let average = dithered.iter().step_by(4).map(|&r| r as u32).sum::<u32>() / 16;
+        assert!(average.abs_diff(gray as u32) <= 1, "average is {average}");
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::ensure;
+use image::{imageops, RgbaImage};
+use zenit_utils::AnyResult;
+
+/// Suffixes of cubemap faces, in the order they're stored in: +X, -X, +Y, -Y, +Z, -Z.
+///
+/// This matches the layer order of `CubemapFace` in the engine.
+pub const FACE_NAMES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+
+/// Arrangement of cubemap faces within a single image.
+#[derive(Debug, Clone, Copy)]
+pub struct SheetLayout {
+    /// Width of the sheet, in faces
+    pub columns: u32,
+    /// Height of the sheet, in faces
+    pub rows: u32,
+    /// Positions (column, row) of the faces, in the order of [`FACE_NAMES`]
+    pub positions: [(u32, u32); 6],
+    /// Faces stored upside down (rotated by 180 degrees)
+    pub rotated: &'static [usize],
+}
+
+/// 4x3 cross, with +Y on top, followed by a row of -X, +Z, +X and -Z, and -Y at the bottom.
+pub const HORIZONTAL_CROSS: SheetLayout = SheetLayout {
+    columns: 4,
+    rows: 3,
+    positions: [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)],
+    rotated: &[],
+};
+
+/// 3x4 cross, with +Y on top, followed by a row of -X, +Z and +X, then -Y and -Z at the bottom.
+///
+/// As usual for this layout, -Z is stored upside down, so that it lines up with -Y.
+pub const VERTICAL_CROSS: SheetLayout = SheetLayout {
+    columns: 3,
+    rows: 4,
+    positions: [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)],
+    rotated: &[5],
+};
+
+/// 6x1 strip of faces in the order of [`FACE_NAMES`].
+pub const STRIP: SheetLayout = SheetLayout {
+    columns: 6,
+    rows: 1,
+    positions: [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)],
+    rotated: &[],
+};
+
+impl SheetLayout {
+    /// Cuts the sheet into six square faces.
+    pub fn split(&self, sheet: &RgbaImage) -> AnyResult<Vec<RgbaImage>> {
+        let (width, height) = sheet.dimensions();
+        ensure!(
+            width % self.columns == 0 && height % self.rows == 0,
+            "a {width}x{height} image can't be split into {}x{} faces",
+            self.columns,
+            self.rows
+        );
+
+        let size = width / self.columns;
+        ensure!(
+            height / self.rows == size,
+            "faces of a {width}x{height} sheet wouldn't be square"
+        );
+
+        Ok(self
+            .positions
+            .iter()
+            .enumerate()
+            .map(|(index, &(x, y))| {
+                let face = imageops::crop_imm(sheet, x * size, y * size, size, size).to_image();
+                match self.rotated.contains(&index) {
+                    true => imageops::rotate180(&face),
+                    false => face,
+                }
+            })
+            .collect())
+    }
+
+    /// Assembles six faces of the same size into a sheet. Inverse of [`SheetLayout::split`].
+    pub fn join(&self, faces: &[RgbaImage]) -> RgbaImage {
+        debug_assert_eq!(faces.len(), 6);
+
+        let (width, height) = faces[0].dimensions();
+        let mut sheet = RgbaImage::new(width * self.columns, height * self.rows);
+        for (index, (face, &(x, y))) in faces.iter().zip(&self.positions).enumerate() {
+            let (x, y) = ((x * width) as i64, (y * height) as i64);
+            match self.rotated.contains(&index) {
+                true => imageops::replace(&mut sheet, &imageops::rotate180(face), x, y),
+                false => imageops::replace(&mut sheet, face, x, y),
+            }
+        }
+        sheet
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sheets_round_trip() {
+        let faces: Vec<_> = (0..6u8)
+            .map(|index| RgbaImage::from_fn(4, 4, |x, y| [index, x as u8, y as u8, 255].into()))
+            .collect();
+
+        for layout in [HORIZONTAL_CROSS, VERTICAL_CROSS, STRIP] {
+            let sheet = layout.join(&faces);
+            assert_eq!(sheet.dimensions(), (4 * layout.columns, 4 * layout.rows));
+            assert_eq!(layout.split(&sheet).unwrap(), faces);
+        }
+
+        // -Z is stored upside down in a vertical cross
+        let sheet = VERTICAL_CROSS.join(&faces);
+        assert_eq!(sheet.get_pixel(4, 12).0, [5, 3, 3, 255]);
+
+        assert!(HORIZONTAL_CROSS.split(&RgbaImage::new(16, 16)).is_err());
+        assert!(STRIP.split(&RgbaImage::new(25, 4)).is_err());
+    }
+}
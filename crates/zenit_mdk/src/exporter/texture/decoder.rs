@@ -0,0 +1,188 @@
+use anyhow::ensure;
+use itertools::Itertools;
+use zenit_lvl::game::D3DFormat;
+use zenit_utils::AnyResult;
+
+/// Decodes texture data of the specified format into 8-bit RGBA.
+///
+/// Formats without color channels are decoded so that they can be viewed as regular images:
+/// alpha only textures become white, luminance becomes gray and V8U8 vector maps are converted
+/// into a normal map, with the Z component reconstructed into the blue channel.
+pub fn decode_texture(
+    format: D3DFormat,
+    data: &[u8],
+    width: u16,
+    height: u16,
+) -> AnyResult<Vec<u8>> {
+    let (width, height) = (width as usize, height as usize);
+    let expected_size = decoded_input_size(format, width, height);
+    ensure!(
+        data.len() >= expected_size,
+        "texture data is too short ({} bytes, expected {expected_size})",
+        data.len(),
+    );
+    let data = &data[..expected_size];
+
+    use D3DFormat::*;
+    let rgba = match format {
+        DXT1 | DXT3 => {
+            let format = match format {
+                DXT1 => texpresso::Format::Bc1,
+                _ => texpresso::Format::Bc2,
+            };
+            let mut output = vec![0u8; width * height * 4];
+            format.decompress(data, width, height, &mut output);
+            output
+        }
+        // Little endian 0xAARRGGBB -> BB GG RR AA in memory
+        A8R8G8B8 => data
+            .iter()
+            .tuples()
+            .flat_map(|(&b, &g, &r, &a)| [r, g, b, a])
+            .collect(),
+        R5G6B5 => expand_color_depth(data, 5, 6, 5, 0),
+        A1R5G5B5 => expand_color_depth(data, 5, 5, 5, 1),
+        A4R4G4B4 => expand_color_depth(data, 4, 4, 4, 4),
+        A8 => data.iter().flat_map(|&a| [255, 255, 255, a]).collect(),
+        L8 => data.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+        // Little endian 0xAALL -> LL AA in memory
+        A8L8 => data
+            .iter()
+            .tuples()
+            .flat_map(|(&l, &a)| [l, l, l, a])
+            .collect(),
+        A4L4 => data
+            .iter()
+            .flat_map(|&value| {
+                let l = expand_bits(value as u16 & 0xf, 4);
+                let a = expand_bits(value as u16 >> 4, 4);
+                [l, l, l, a]
+            })
+            .collect(),
+        V8U8 => data
+            .iter()
+            .tuples()
+            .flat_map(|(&u, &v)| {
+                let x = u as i8 as f32 / 127.0;
+                let y = v as i8 as f32 / 127.0;
+                let z = (1.0 - x * x - y * y).max(0.0).sqrt();
+                [
+                    u.wrapping_add(128),
+                    v.wrapping_add(128),
+                    (z * 127.5 + 127.5) as u8,
+                    255,
+                ]
+            })
+            .collect(),
+        // Little endian 0xRRGGBB -> BB GG RR in memory
+        R8G8B8 => data
+            .iter()
+            .tuples()
+            .flat_map(|(&b, &g, &r)| [r, g, b, 255])
+            .collect(),
+    };
+
+    Ok(rgba)
+}
+
+/// Size of the texture data of the specified format, in bytes.
+pub fn decoded_input_size(format: D3DFormat, width: usize, height: usize) -> usize {
+    use D3DFormat::*;
+    match format {
+        DXT1 => texpresso::Format::Bc1.compressed_size(width, height),
+        DXT3 => texpresso::Format::Bc2.compressed_size(width, height),
+        A8R8G8B8 => width * height * 4,
+        R8G8B8 => width * height * 3,
+        R5G6B5 | A1R5G5B5 | A4R4G4B4 | A8L8 | V8U8 => width * height * 2,
+        A8 | L8 | A4L4 => width * height,
+    }
+}
+
+/// Converts 16-bit D3D9 ARGB pixels to 8-bit RGBA.
+fn expand_color_depth(
+    data16: &[u8],
+    r_bits: u16,
+    g_bits: u16,
+    b_bits: u16,
+    a_bits: u16,
+) -> Vec<u8> {
+    debug_assert_eq!(r_bits + g_bits + b_bits + a_bits, 16);
+
+    let component = |value: u16, shift: u16, depth: u16| {
+        expand_bits((value >> shift) & ((1 << depth) - 1), depth)
+    };
+
+    data16
+        .iter()
+        .tuples()
+        .flat_map(|(&low, &high)| {
+            let value = u16::from_le_bytes([low, high]);
+            [
+                component(value, g_bits + b_bits, r_bits),
+                component(value, b_bits, g_bits),
+                component(value, 0, b_bits),
+                match a_bits {
+                    0 => 255,
+                    _ => component(value, r_bits + g_bits + b_bits, a_bits),
+                },
+            ]
+        })
+        .collect()
+}
+
+/// Scales a `depth`-bit value to 8 bits, so that the highest value maps to 255.
+fn expand_bits(value: u16, depth: u16) -> u8 {
+    let max = (1u32 << depth) - 1;
+    ((value as u32 * 255 + max / 2) / max) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_16_bit_formats() {
+        // Pure red, green and blue pixels
+        let rgb = [0xf800u16, 0x07e0, 0x001f].map(u16::to_le_bytes).concat();
+        assert_eq!(
+            decode_texture(D3DFormat::R5G6B5, &rgb, 3, 1).unwrap(),
+            [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255],
+        );
+
+        let argb = [0x8000u16, 0x7fff].map(u16::to_le_bytes).concat();
+        assert_eq!(
+            decode_texture(D3DFormat::A1R5G5B5, &argb, 2, 1).unwrap(),
+            [0, 0, 0, 255, 255, 255, 255, 0],
+        );
+
+        let argb = [0x48c0u16].map(u16::to_le_bytes).concat();
+        assert_eq!(
+            decode_texture(D3DFormat::A4R4G4B4, &argb, 1, 1).unwrap(),
+            [0x88, 0xcc, 0x00, 0x44],
+        );
+    }
+
+    #[test]
+    fn decodes_luminance_and_vector_formats() {
+        assert_eq!(
+            decode_texture(D3DFormat::A8L8, &[0x20, 0x80], 1, 1).unwrap(),
+            [0x20, 0x20, 0x20, 0x80],
+        );
+        assert_eq!(
+            decode_texture(D3DFormat::A4L4, &[0xf3], 1, 1).unwrap(),
+            [0x33, 0x33, 0x33, 0xff],
+        );
+
+        // A vector pointing straight out of the surface
+        assert_eq!(
+            decode_texture(D3DFormat::V8U8, &[0, 0], 1, 1).unwrap(),
+            [128, 128, 255, 255],
+        );
+    }
+
+    #[test]
+    fn rejects_short_data() {
+        assert!(decode_texture(D3DFormat::A8R8G8B8, &[0; 12], 2, 2).is_err());
+        assert!(decode_texture(D3DFormat::DXT1, &[0; 7], 4, 4).is_err());
+    }
+}
@@ -0,0 +1,445 @@
+//! Mip chain generation, with resampling of arbitrarily sized images.
+
+use clap::ValueEnum;
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Filter used for downsampling the mip levels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+pub enum MipmapFilter {
+    /// Averages the covered pixels. Fast, but somewhat blurry.
+    #[default]
+    Box,
+    /// Kaiser windowed sinc, a good balance between sharpness and ringing.
+    Kaiser,
+    /// Lanczos (3 lobes), the sharpest of the filters, at the cost of some ringing.
+    Lanczos,
+}
+
+/// What to do with images whose dimensions aren't powers of two.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+pub enum PowerOfTwo {
+    /// The image is used as is
+    #[default]
+    Keep,
+    /// The image is extended to the next power of two, by repeating its right and bottom edges
+    Pad,
+    /// The image is resampled to the next power of two
+    Resize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MipmapOptions {
+    pub filter: MipmapFilter,
+    /// Whether color channels are sRGB encoded and should be filtered in linear space
+    pub srgb: bool,
+    /// Alpha test threshold, for which the coverage of level 0 is preserved in every level
+    pub alpha_coverage: Option<f32>,
+}
+
+/// An image with channels converted to linear floats in the range of [0; 1].
+#[derive(Clone)]
+struct LinearImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl LinearImage {
+    fn new(image: &RgbaImage, srgb: bool) -> Self {
+        let decode = |value: u8| {
+            let value = value as f32 / 255.0;
+            match srgb {
+                true => srgb_to_linear(value),
+                false => value,
+            }
+        };
+
+        Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels: image
+                .pixels()
+                .map(|&image::Rgba([r, g, b, a])| {
+                    [decode(r), decode(g), decode(b), a as f32 / 255.0]
+                })
+                .collect(),
+        }
+    }
+
+    fn encode(&self, srgb: bool) -> Vec<u8> {
+        let quantize = |value: f32| (value * 255.0).round().clamp(0.0, 255.0) as u8;
+        // Alpha is always linear, just like in `new`
+        let encode = |value: f32| match srgb {
+            true => quantize(linear_to_srgb(value.max(0.0))),
+            false => quantize(value),
+        };
+
+        self.pixels
+            .iter()
+            .flat_map(|&[r, g, b, a]| [encode(r), encode(g), encode(b), quantize(a)])
+            .collect()
+    }
+
+    /// Resamples the image with a separable filter.
+    fn resample(&self, width: usize, height: usize, filter: MipmapFilter) -> Self {
+        let mut horizontal = Self {
+            width,
+            height: self.height,
+            pixels: Vec::with_capacity(width * self.height),
+        };
+        let weights = filter_weights(self.width, width, filter);
+        for row in self.pixels.chunks(self.width) {
+            horizontal
+                .pixels
+                .extend(weights.iter().map(|taps| apply_taps(taps, |x| row[x])));
+        }
+
+        let mut result = Self {
+            width,
+            height,
+            pixels: Vec::with_capacity(width * height),
+        };
+        let weights = filter_weights(self.height, height, filter);
+        for taps in &weights {
+            result
+                .pixels
+                .extend((0..width).map(|x| apply_taps(taps, |y| horizontal.pixels[y * width + x])));
+        }
+
+        result
+    }
+
+    /// Fraction of pixels which pass the alpha test after scaling their alpha.
+    fn coverage(&self, threshold: f32, scale: f32) -> f32 {
+        let passed = (self.pixels.iter())
+            .filter(|[.., a]| a * scale > threshold)
+            .count();
+        passed as f32 / self.pixels.len() as f32
+    }
+
+    /// Scales the alpha channel, so that the alpha test coverage gets as close to the target
+    /// as possible.
+    fn preserve_coverage(&mut self, threshold: f32, target: f32) {
+        // Any scale reaches a target of zero, so the search would end up fading out the alpha
+        if target <= 0.0 {
+            return;
+        }
+
+        let (mut low, mut high) = (0.0f32, 4.0f32);
+        for _ in 0..16 {
+            let middle = (low + high) / 2.0;
+            if self.coverage(threshold, middle) < target {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        for [.., a] in &mut self.pixels {
+            *a = (*a * high).min(1.0);
+        }
+    }
+}
+
+/// Source pixels, along with their weights, contributing to a single resampled pixel.
+type Taps = Vec<(usize, f32)>;
+
+fn apply_taps(taps: &Taps, mut pixel: impl FnMut(usize) -> [f32; 4]) -> [f32; 4] {
+    let mut result = [0.0; 4];
+    for &(index, weight) in taps {
+        for (result, channel) in result.iter_mut().zip(pixel(index)) {
+            *result += channel * weight;
+        }
+    }
+    result
+}
+
+/// Computes the normalized filter weights for resampling a row of `source` pixels into
+/// `target` pixels. Pixels outside of the image are clamped to the edge.
+fn filter_weights(source: usize, target: usize, filter: MipmapFilter) -> Vec<Taps> {
+    let scale = source as f32 / target as f32;
+    // When upsampling, the filter isn't stretched
+    let stretch = scale.max(1.0);
+    let radius = match filter {
+        MipmapFilter::Box => 0.5,
+        MipmapFilter::Kaiser | MipmapFilter::Lanczos => 3.0,
+    } * stretch;
+
+    (0..target)
+        .map(|index| {
+            let center = (index as f32 + 0.5) * scale;
+            let first = (center - radius).floor() as isize;
+            let last = (center + radius).ceil() as isize;
+
+            let mut taps: Taps = (first..last)
+                .map(|position| {
+                    let weight = match filter {
+                        // Fraction of the source pixel covered by the filter
+                        MipmapFilter::Box => {
+                            let start = (position as f32).max(center - radius);
+                            let end = (position as f32 + 1.0).min(center + radius);
+                            (end - start).max(0.0)
+                        }
+                        MipmapFilter::Kaiser => kaiser((position as f32 + 0.5 - center) / stretch),
+                        MipmapFilter::Lanczos => {
+                            lanczos((position as f32 + 0.5 - center) / stretch)
+                        }
+                    };
+                    (position.clamp(0, source as isize - 1) as usize, weight)
+                })
+                .filter(|&(_, weight)| weight != 0.0)
+                .collect();
+
+            let sum: f32 = taps.iter().map(|(_, weight)| weight).sum();
+            for (_, weight) in &mut taps {
+                *weight /= sum;
+            }
+            taps
+        })
+        .collect()
+}
+
+fn sinc(x: f32) -> f32 {
+    match x.abs() < 1e-6 {
+        true => 1.0,
+        false => (PI * x).sin() / (PI * x),
+    }
+}
+
+fn lanczos(x: f32) -> f32 {
+    match x.abs() < 3.0 {
+        true => sinc(x) * sinc(x / 3.0),
+        false => 0.0,
+    }
+}
+
+fn kaiser(x: f32) -> f32 {
+    const ALPHA: f32 = 4.0;
+    const WIDTH: f32 = 3.0;
+
+    /// Modified Bessel function of the first kind, of order zero
+    fn bessel_i0(x: f32) -> f32 {
+        let (mut sum, mut term) = (1.0, 1.0);
+        for k in 1..32 {
+            term *= (x / (2.0 * k as f32)).powi(2);
+            sum += term;
+            if term < sum * 1e-8 {
+                break;
+            }
+        }
+        sum
+    }
+
+    let ratio = x / WIDTH;
+    match ratio.abs() < 1.0 {
+        true => sinc(x) * bessel_i0(ALPHA * (1.0 - ratio * ratio).sqrt()) / bessel_i0(ALPHA),
+        false => 0.0,
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    match value <= 0.04045 {
+        true => value / 12.92,
+        false => ((value + 0.055) / 1.055).powf(2.4),
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    match value <= 0.0031308 {
+        true => value * 12.92,
+        false => 1.055 * value.powf(1.0 / 2.4) - 0.055,
+    }
+}
+
+/// Extends or resamples the image to power of two dimensions, depending on the mode.
+pub fn to_power_of_two(image: RgbaImage, mode: PowerOfTwo, options: &MipmapOptions) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let (new_width, new_height) = (width.next_power_of_two(), height.next_power_of_two());
+    if (width, height) == (new_width, new_height) {
+        return image;
+    }
+
+    match mode {
+        PowerOfTwo::Keep => image,
+        PowerOfTwo::Pad => RgbaImage::from_fn(new_width, new_height, |x, y| {
+            *image.get_pixel(x.min(width - 1), y.min(height - 1))
+        }),
+        PowerOfTwo::Resize => {
+            let resized = LinearImage::new(&image, options.srgb).resample(
+                new_width as usize,
+                new_height as usize,
+                options.filter,
+            );
+            RgbaImage::from_raw(new_width, new_height, resized.encode(options.srgb)).unwrap()
+        }
+    }
+}
+
+/// Generates the mip chain of an image. Every level is resampled from the original image,
+/// with dimensions halved (and rounded down) for each level, down to 1x1.
+///
+/// If more levels are requested than there are in the chain, the 1x1 level is repeated.
+pub fn generate_mipmaps(
+    image: &RgbaImage,
+    mipmap_count: usize,
+    options: &MipmapOptions,
+) -> Vec<(u16, u16, Vec<u8>)> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let mut mipmaps = Vec::with_capacity(mipmap_count);
+    if mipmap_count == 0 {
+        return mipmaps;
+    }
+    mipmaps.push((width as u16, height as u16, image.as_raw().clone()));
+
+    let source = LinearImage::new(image, options.srgb);
+    let target_coverage = options
+        .alpha_coverage
+        .map(|threshold| (threshold, source.coverage(threshold, 1.0)));
+
+    for level in 1..mipmap_count {
+        let level_width = (width >> level.min(usize::BITS as usize - 1)).max(1);
+        let level_height = (height >> level.min(usize::BITS as usize - 1)).max(1);
+
+        let mut mipmap = source.resample(level_width, level_height, options.filter);
+        if let Some((threshold, target)) = target_coverage {
+            mipmap.preserve_coverage(threshold, target);
+        }
+
+        mipmaps.push((
+            level_width as u16,
+            level_height as u16,
+            mipmap.encode(options.srgb),
+        ));
+    }
+
+    mipmaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINEAR_BOX: MipmapOptions = MipmapOptions {
+        filter: MipmapFilter::Box,
+        srgb: false,
+        alpha_coverage: None,
+    };
+
+    fn sizes(mipmaps: &[(u16, u16, Vec<u8>)]) -> Vec<(u16, u16)> {
+        mipmaps.iter().map(|&(w, h, _)| (w, h)).collect()
+    }
+
+    #[test]
+    fn box_filter_averages_blocks() {
+        let image = RgbaImage::from_fn(4, 2, |x, y| [(x * 40 + y * 80) as u8, 0, 0, 255].into());
+        let mipmaps = generate_mipmaps(&image, 4, &LINEAR_BOX);
+
+        assert_eq!(sizes(&mipmaps), [(4, 2), (2, 1), (1, 1), (1, 1)]);
+        assert_eq!(&mipmaps[1].2, &[60, 0, 0, 255, 140, 0, 0, 255]);
+        assert_eq!(&mipmaps[2].2, &[100, 0, 0, 255]);
+    }
+
+    #[test]
+    fn odd_sizes_are_resampled() {
+        for filter in [
+            MipmapFilter::Box,
+            MipmapFilter::Kaiser,
+            MipmapFilter::Lanczos,
+        ] {
+            let options = MipmapOptions {
+                filter,
+                ..LINEAR_BOX
+            };
+            let image = RgbaImage::from_pixel(7, 3, [10, 20, 30, 40].into());
+            let mipmaps = generate_mipmaps(&image, 3, &options);
+
+            assert_eq!(sizes(&mipmaps), [(7, 3), (3, 1), (1, 1)]);
+            // Filters are normalized, so a flat image stays flat
+            for (_, _, data) in &mipmaps[1..] {
+                assert!(data.chunks(4).all(|pixel| pixel == [10, 20, 30, 40]));
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_is_filtered_linearly() {
+        let image = RgbaImage::from_fn(2, 1, |x, _| [(x * 255) as u8, 0, 0, 0].into());
+
+        let srgb = MipmapOptions {
+            srgb: true,
+            ..LINEAR_BOX
+        };
+        assert_eq!(generate_mipmaps(&image, 2, &srgb)[1].2, [188, 0, 0, 0]);
+        assert_eq!(
+            generate_mipmaps(&image, 2, &LINEAR_BOX)[1].2,
+            [128, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn srgb_keeps_alpha_linear() {
+        let image = RgbaImage::from_fn(2, 1, |x, _| [255, 255, 255, (x * 100) as u8].into());
+
+        let srgb = MipmapOptions {
+            srgb: true,
+            ..LINEAR_BOX
+        };
+        assert_eq!(generate_mipmaps(&image, 2, &srgb)[1].2, [255, 255, 255, 50]);
+    }
+
+    #[test]
+    fn alpha_coverage_is_preserved() {
+        // Thin opaque lines, like blades of grass, one of them with a faint edge
+        let image = RgbaImage::from_fn(8, 8, |x, _| {
+            [0, 0, 0, [200, 0, 0, 0, 200, 40, 0, 0][x as usize]].into()
+        });
+        let coverage = |data: &[u8]| {
+            let passed = data.chunks(4).filter(|pixel| pixel[3] > 127).count();
+            passed as f32 / (data.len() / 4) as f32
+        };
+
+        // Without preserving the coverage, the lines fade away
+        let plain = generate_mipmaps(&image, 2, &LINEAR_BOX);
+        assert_eq!(coverage(&plain[0].2), 0.25);
+        assert_eq!(coverage(&plain[1].2), 0.0);
+
+        // Only the line with the edge passes the test again, matching the coverage of level 0
+        let options = MipmapOptions {
+            alpha_coverage: Some(0.5),
+            ..LINEAR_BOX
+        };
+        let preserved = generate_mipmaps(&image, 2, &options);
+        assert_eq!(coverage(&preserved[1].2), 0.25);
+    }
+
+    #[test]
+    fn alpha_is_kept_without_coverage() {
+        // Nothing passes the alpha test at level 0, so there's no coverage to preserve
+        let image = RgbaImage::from_fn(8, 8, |x, _| [0, 0, 0, (x * 10) as u8].into());
+        let options = MipmapOptions {
+            alpha_coverage: Some(0.5),
+            ..LINEAR_BOX
+        };
+        assert_eq!(
+            generate_mipmaps(&image, 4, &options),
+            generate_mipmaps(&image, 4, &LINEAR_BOX)
+        );
+    }
+
+    #[test]
+    fn images_are_extended_to_power_of_two() {
+        let image = RgbaImage::from_fn(3, 2, |x, y| [x as u8, y as u8, 0, 255].into());
+
+        let padded = to_power_of_two(image.clone(), PowerOfTwo::Pad, &LINEAR_BOX);
+        assert_eq!(padded.dimensions(), (4, 2));
+        assert_eq!(padded.get_pixel(3, 1).0, [2, 1, 0, 255]);
+
+        let resized = to_power_of_two(image.clone(), PowerOfTwo::Resize, &LINEAR_BOX);
+        assert_eq!(resized.dimensions(), (4, 2));
+
+        let kept = to_power_of_two(image.clone(), PowerOfTwo::Keep, &LINEAR_BOX);
+        assert_eq!(kept, image);
+    }
+}
@@ -1,42 +1,89 @@
-use anyhow::{bail, ensure};
+use anyhow::{ensure, Context};
 use byteorder::{WriteBytesExt, LE};
-use itertools::Itertools;
-use serde::Deserialize;
-use std::{ffi::CString, io::Cursor, path::PathBuf};
+use image::RgbaImage;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::CString,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
 use zenit_lvl::game::{
     D3DFormat, LevelTexture, LevelTextureFace, LevelTextureFormat, LevelTextureFormatInfo,
-    LevelTextureKind, LevelTextureMipmap, LevelTextureMipmapInfo, ZLevelTextureFiltering,
+    LevelTextureKind, LevelTextureMipmap, LevelTextureMipmapInfo,
 };
 use zenit_utils::AnyResult;
 
 pub mod converter;
+pub mod cubemap;
+pub mod decoder;
+pub mod mipmap;
+
+use mipmap::{MipmapFilter, MipmapOptions, PowerOfTwo};
 
-#[derive(Debug, Deserialize)]
+/// Where the faces of a cubemap come from. Faces are ordered +X, -X, +Y, -Y, +Z, -Z.
+#[derive(Debug, Deserialize, Serialize)]
 pub enum CubemapFaces {
+    /// The image is used for every face
     Repeated,
-    Sheeted,
+    /// The image is a 4x3 horizontal cross, see [`cubemap::HORIZONTAL_CROSS`]
+    #[serde(alias = "Sheeted")]
+    HorizontalCross,
+    /// The image is a 3x4 vertical cross, see [`cubemap::VERTICAL_CROSS`]
+    VerticalCross,
+    /// The image is a 6x1 strip of faces
+    Strip,
+    /// Each face is loaded from a separate image, the `file` field isn't used
+    Separate([PathBuf; 6]),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(tag = "kind", content = "faces" /* This def is a bit hacky as it only applies to ::Cubemap */)]
 pub enum TextureKind {
     Color,
     Cubemap(#[serde(rename = "faces")] CubemapFaces),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct TextureSpecification {
     pub name: String,
-    pub file: PathBuf,
+    /// Source image, required unless the cubemap faces are in separate files
+    #[serde(default)]
+    pub file: Option<PathBuf>,
     pub formats: Vec<D3DFormat>,
     #[serde(flatten)]
     pub kind: TextureKind,
     pub mipmaps: Option<u16>,
     #[serde(default)]
     pub unfiltered: bool,
+    /// Enables ordered dithering for formats with less than 8 bits per channel
+    #[serde(default)]
+    pub dithering: bool,
+    /// Filter used for generating the mip levels
+    #[serde(default)]
+    pub mipmap_filter: MipmapFilter,
+    /// Whether the color channels hold linear data (such as normal maps), rather than sRGB colors
+    #[serde(default)]
+    pub linear: bool,
+    /// Alpha test threshold (0 to 1) of cutout textures, such as foliage. When set, mip levels
+    /// keep the same fraction of pixels passing the test as the full size image.
+    pub alpha_coverage: Option<f32>,
+    /// What to do with images whose dimensions aren't powers of two
+    #[serde(default)]
+    pub power_of_two: PowerOfTwo,
 }
 
 impl TextureSpecification {
+    /// Returns paths of all images used by the texture.
+    pub fn input_files(&self) -> Vec<&Path> {
+        match &self.kind {
+            TextureKind::Cubemap(CubemapFaces::Separate(files)) => {
+                files.iter().map(PathBuf::as_path).collect()
+            }
+            _ => self.file.iter().map(PathBuf::as_path).collect(),
+        }
+    }
+
     pub fn export(self) -> AnyResult<LevelTexture> {
         let TextureSpecification {
             name,
@@ -45,11 +92,22 @@ impl TextureSpecification {
             kind,
             mipmaps,
             unfiltered,
+            dithering,
+            mipmap_filter,
+            linear,
+            alpha_coverage,
+            power_of_two,
         } = self;
 
+        let mipmap_options = MipmapOptions {
+            filter: mipmap_filter,
+            srgb: !linear,
+            alpha_coverage,
+        };
+
         let mut texture = LevelTexture {
             name: CString::new(name)?,
-            formats: Vec::with_capacity(formats.len()),
+            formats: vec![],
             info: {
                 // tex_:INFO has a dynamically sized format, so we just assemble it here manually
                 let mut result = vec![];
@@ -62,11 +120,39 @@ impl TextureSpecification {
             },
         };
 
-        let image = image::open(file)?.into_rgba8();
-        ensure!(image.width() <= i16::MAX as u32, "image too large");
-        ensure!(image.height() <= i16::MAX as u32, "image too large");
-        let width = image.width() as u16;
-        let height = image.height() as u16;
+        use CubemapFaces::*;
+        use TextureKind::*;
+
+        let file = || file.as_deref().context("missing the source image file");
+        let images = match &kind {
+            Color | Cubemap(Repeated) => vec![open_image(file()?)?],
+            Cubemap(HorizontalCross) => cubemap::HORIZONTAL_CROSS.split(&open_image(file()?)?)?,
+            Cubemap(VerticalCross) => cubemap::VERTICAL_CROSS.split(&open_image(file()?)?)?,
+            Cubemap(Strip) => cubemap::STRIP.split(&open_image(file()?)?)?,
+            Cubemap(Separate(files)) => files
+                .iter()
+                .map(|path| open_image(path))
+                .collect::<AnyResult<_>>()?,
+        };
+        let images: Vec<_> = images
+            .into_iter()
+            .map(|image| mipmap::to_power_of_two(image, power_of_two, &mipmap_options))
+            .collect();
+
+        let (width, height) = images[0].dimensions();
+        ensure!(width <= i16::MAX as u32, "image too large");
+        ensure!(height <= i16::MAX as u32, "image too large");
+        ensure!(
+            images
+                .iter()
+                .all(|image| image.dimensions() == (width, height)),
+            "all cubemap faces must have the same size"
+        );
+        if let Cubemap(_) = kind {
+            ensure!(width == height, "cubemap faces must be square");
+        }
+        let width = width as u16;
+        let height = height as u16;
 
         let mipmaps = match mipmaps {
             Some(user_amount) => user_amount,
@@ -76,128 +162,68 @@ impl TextureSpecification {
             }
         };
 
-        for format in formats {
-            let generated_mipmaps = generate_mipmaps(
-                &image.as_raw(),
-                width as usize,
-                height as usize,
-                mipmaps as usize,
-            );
-
-            use CubemapFaces::*;
-            use TextureKind::*;
-            texture.formats.push(LevelTextureFormat {
-                info: LevelTextureFormatInfo {
-                    format,
-                    width,
-                    height,
-                    unk_0x08: 1, // ??
-                    mipmaps,
-                    kind: match &kind {
-                        Color => LevelTextureKind::D2,
-                        Cubemap(_) => LevelTextureKind::Cubemap,
-                    },
-                },
-                faces: match &kind {
-                    Color => vec![export_face(generated_mipmaps, format)?],
-                    Cubemap(cubemap_faces) => match cubemap_faces {
-                        Repeated => vec![export_face(generated_mipmaps, format)?; 6],
-                        Sheeted => bail!("cubemap sheets aren't supported yet"),
+        // Mip chains don't depend on the format, so they're only generated once per face
+        let face_mipmaps: Vec<_> = images
+            .par_iter()
+            .map(|image| mipmap::generate_mipmaps(image, mipmaps as usize, &mipmap_options))
+            .collect();
+
+        // Formats and faces are encoded in parallel, as block compression is quite slow
+        texture.formats = formats
+            .into_par_iter()
+            .map(|format| {
+                let mut faces = face_mipmaps
+                    .par_iter()
+                    .map(|mipmaps| export_face(mipmaps, format, dithering))
+                    .collect::<AnyResult<Vec<_>>>()?;
+
+                if let Cubemap(Repeated) = kind {
+                    faces = vec![faces.remove(0); 6];
+                }
+
+                Ok(LevelTextureFormat {
+                    info: LevelTextureFormatInfo {
+                        format,
+                        width,
+                        height,
+                        unk_0x08: 1, // ??
+                        mipmaps,
+                        kind: match &kind {
+                            Color => LevelTextureKind::D2,
+                            Cubemap(_) => LevelTextureKind::Cubemap,
+                        },
                     },
-                },
-                unfiltered: if unfiltered {
-                    vec![ZLevelTextureFiltering {}]
-                } else {
-                    vec![]
-                },
-            });
-        }
+                    faces,
+                    unfiltered,
+                })
+            })
+            .collect::<AnyResult<_>>()?;
 
         Ok(texture)
     }
 }
 
-/// Shrinks the texture by a factor of 2, filtering the pixel by using the average of 4x4 blocks.
-/// In other words, this generates another mip level in the chain.
-fn downscale_texture(rgba: &[u8], width: usize, height: usize) -> Vec<u8> {
-    // This could be potentially heavily optimized with SIMD ops, unless the compiler
-    // figures it out automatically
-    //
-    // I could look into that someday :3
-
-    debug_assert!(width.is_power_of_two() && height.is_power_of_two());
-    debug_assert!(rgba.len() == width * height * 4);
-    if width == 1 || height == 1 {
-        return Vec::from(rgba);
-    }
-
-    let mut result = Vec::with_capacity(rgba.len() / 4);
-
-    for two_rows in rgba.chunks(width * 4 * 2) {
-        let (upper, lower) = two_rows.split_at(two_rows.len() / 2);
-
-        let upper_doubles = upper.iter().map(|&byte| byte as u16).tuples();
-        let lower_doubles = lower.iter().map(|&byte| byte as u16).tuples();
-
-        for ((r1, g1, b1, a1, r2, g2, b2, a2), (r3, g3, b3, a3, r4, g4, b4, a4)) in
-            upper_doubles.zip(lower_doubles)
-        {
-            result.push(((r1 + r2 + r3 + r4) / 4) as u8);
-            result.push(((g1 + g2 + g3 + g4) / 4) as u8);
-            result.push(((b1 + b2 + b3 + b4) / 4) as u8);
-            result.push(((a1 + a2 + a3 + a4) / 4) as u8);
-        }
-    }
-
-    result
-}
-
-fn generate_mipmaps(
-    rgba: &[u8],
-    width: usize,
-    height: usize,
-    mipmap_count: usize,
-) -> Vec<(u16, u16, Vec<u8>)> {
-    let mut mipmaps = Vec::with_capacity(mipmap_count);
-
-    let mut current_rgba = Vec::from(rgba);
-    let mut current_width = width;
-    let mut current_height = height;
-    for _ in 0..mipmap_count {
-        mipmaps.push((
-            current_width.try_into().unwrap(),
-            current_height.try_into().unwrap(),
-            current_rgba.clone(),
-        ));
-
-        // Only generate further downsamples if we aren't at either dimension of 1.
-        // We keep on generating mips even if they're dupes, as we were asked for a
-        // specific amount of mips, and we're a good obedient girl :3
-        if current_width != 1 && current_height != 1 {
-            current_rgba = downscale_texture(&current_rgba, current_width, current_height);
-            current_width /= 2;
-            current_height /= 2;
-        }
-    }
-
-    mipmaps
+fn open_image(path: &Path) -> AnyResult<RgbaImage> {
+    let image = image::open(path).with_context(|| format!("couldn't open {}", path.display()))?;
+    Ok(image.into_rgba8())
 }
 
 fn export_face(
-    rgba_mipmaps: Vec<(u16, u16, Vec<u8>)>,
+    rgba_mipmaps: &[(u16, u16, Vec<u8>)],
     format: D3DFormat,
+    dithering: bool,
 ) -> AnyResult<LevelTextureFace> {
-    let tc = converter::create_converter(format);
+    let tc = converter::create_converter(format, dithering);
 
     Ok(LevelTextureFace {
         mipmaps: rgba_mipmaps
-            .into_iter()
+            .iter()
             .enumerate()
-            .map(|(level, (width, height, rgba))| {
+            .map(|(level, &(width, height, ref rgba))| {
                 let mut body = Vec::with_capacity(tc.size_after_conversion(width, height));
                 let mut cursor = Cursor::new(&mut body);
 
-                tc.write_texture(&mut cursor, &rgba, width, height)?;
+                tc.write_texture(&mut cursor, rgba, width, height)?;
                 Ok(LevelTextureMipmap {
                     info: LevelTextureMipmapInfo {
                         mip_level: level.try_into()?,
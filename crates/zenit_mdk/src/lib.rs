@@ -5,7 +5,8 @@
 
 use clap::{Parser, Subcommand};
 use commands::{
-    append::AppendCommand, build::BuildCommand, export::ExportCommand, merge::MergeCommand,
+    append::AppendCommand, build::BuildCommand, diff::DiffCommand, export::ExportCommand,
+    merge::MergeCommand, tree::TreeCommand, validate::ValidateCommand,
 };
 use zenit_utils::{ok, AnyResult};
 
@@ -31,6 +32,13 @@ pub enum CliCommand {
     /// Appends new data into a data file
     #[command(subcommand)]
     Append(AppendCommand),
+    /// Prints the node tree of a data file
+    #[command(alias = "list")]
+    Tree(TreeCommand),
+    /// Compares resources of two data files
+    Diff(DiffCommand),
+    /// Checks a data file for problems
+    Validate(ValidateCommand),
 }
 
 pub trait Command {
@@ -47,6 +55,9 @@ pub fn run(cli: Cli) -> AnyResult {
         CliCommand::Merge(c) => c.run()?,
         CliCommand::Export(c) => c.run()?,
         CliCommand::Append(c) => c.run()?,
+        CliCommand::Tree(c) => c.run()?,
+        CliCommand::Diff(c) => c.run()?,
+        CliCommand::Validate(c) => c.run()?,
     }
     ok()
 }
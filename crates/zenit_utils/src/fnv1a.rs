@@ -11,15 +11,26 @@ pub const OFFSET_BASIS: u32 = 2166136261;
 /// let hash = fnv1a_hash(speeder.as_bytes());
 /// assert_eq!(hash, 0x266561d8);
 /// ```
-pub fn fnv1a_hash(buffer: &[u8]) -> u32 {
+///
+/// The function is `const`, so it can also be used to define hashes of known names:
+/// ```
+/// use zenit_utils::fnv1a_hash;
+///
+/// const SPEEDER: u32 = fnv1a_hash(b"all_fly_snowspeeder");
+/// assert_eq!(SPEEDER, 0x266561d8);
+/// ```
+pub const fn fnv1a_hash(buffer: &[u8]) -> u32 {
     let mut result = OFFSET_BASIS;
-    for &byte in buffer {
+    // (a while loop, as iterators can't be used in const functions)
+    let mut i = 0;
+    while i < buffer.len() {
         // NOTE: BF2 additionally ORs every byte with 0x20, presumably to make
         //       the encoding case-insensitive, but it does actually screw up
         //       characters like underscores, which don't fall for such nasty
         //       ASCII tricks.
-        result ^= (byte | 0x20) as u32;
+        result ^= (buffer[i] | 0x20) as u32;
         result = result.wrapping_mul(FNV_PRIME);
+        i += 1;
     }
     result
 }
@@ -0,0 +1,194 @@
+use crate::{fnv1a_hash, ok, AnyResult};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    path::Path,
+};
+
+/// Names known to be hashed by the game's formats, mostly property names of config nodes.
+pub const BUILTIN_NAMES: &[&str] = &[
+    // Skies (`sky_`)
+    "SkyInfo",
+    "DomeInfo",
+    "DomeModel",
+    "SunInfo",
+    "Geometry",
+    "Texture",
+    "FogColor",
+    "FogRange",
+    "NearSceneRange",
+    "FarSceneRange",
+    "Angle",
+    "Color",
+    "Offset",
+    // Lighting (`lght`)
+    "GlobalLights",
+    "Light",
+    "Light1",
+    "Light2",
+    "Ambient",
+    "Top",
+    "Bottom",
+    "Position",
+    "Rotation",
+    "Type",
+    "Range",
+    "Cone",
+    "CastShadow",
+    "CastSpecular",
+    "Static",
+    // Particle effects (`fx__`)
+    "ParticleEmitter",
+    "MaxParticles",
+    "StartDelay",
+    "BurstDelay",
+    "BurstCount",
+    "MaxLodDist",
+    "MinLodDist",
+    "BoundingRadius",
+    "SoundName",
+    "NoRegisterStep",
+    "Spawner",
+    "Spread",
+    "PositionX",
+    "PositionY",
+    "PositionZ",
+    "PositionScale",
+    "VelocityScale",
+    "InheritVelocityFactor",
+    "Size",
+    "Red",
+    "Green",
+    "Blue",
+    "Alpha",
+    "StartRotation",
+    "RotationVelocity",
+    "FadeInTime",
+    "Transformer",
+    "LifeTime",
+    "Accelerate",
+    "Scale",
+    "Reach",
+    "Next",
+    "BlendMode",
+    "MovementScale",
+];
+
+/// Reverse lookup table for [`fnv1a_hash`]ed names.
+///
+/// FNV-1a hashes can't be reversed, so the dictionary can only recognize names that it was
+/// seeded with, either from [`BUILTIN_NAMES`], or from user-provided word lists.
+///
+/// ## Example
+/// ```
+/// use zenit_utils::{fnv1a_hash, HashDictionary};
+///
+/// let mut dictionary = HashDictionary::with_builtin_names();
+/// dictionary.extend_from_word_list("# Vehicles\nall_fly_snowspeeder\n");
+///
+/// assert_eq!(dictionary.lookup(0x266561d8), Some("all_fly_snowspeeder"));
+/// assert_eq!(dictionary.lookup(fnv1a_hash(b"FogColor")), Some("FogColor"));
+/// assert_eq!(dictionary.display(0xdeadbeef).to_string(), "0xdeadbeef");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HashDictionary {
+    names: HashMap<u32, Box<str>>,
+}
+
+impl HashDictionary {
+    /// Creates an empty dictionary.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a dictionary seeded with [`BUILTIN_NAMES`].
+    pub fn with_builtin_names() -> Self {
+        let mut dictionary = Self::new();
+        dictionary.extend(BUILTIN_NAMES.iter().copied());
+        dictionary
+    }
+
+    /// Adds a name to the dictionary, returning its hash.
+    ///
+    /// If a different name with the same hash is already known, the existing one is kept.
+    pub fn insert(&mut self, name: &str) -> u32 {
+        let hash = fnv1a_hash(name.as_bytes());
+        self.names.entry(hash).or_insert_with(|| name.into());
+        hash
+    }
+
+    /// Adds every name from a word list: one name per line. Surrounding whitespace, empty lines
+    /// and lines starting with `#` are ignored.
+    pub fn extend_from_word_list(&mut self, list: &str) {
+        self.extend(
+            list.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#')),
+        );
+    }
+
+    /// Reads a word list file, see [`Self::extend_from_word_list`] for the format.
+    pub fn load_word_list(&mut self, path: impl AsRef<Path>) -> AnyResult {
+        let list = std::fs::read_to_string(path)?;
+        self.extend_from_word_list(&list);
+        ok()
+    }
+
+    /// Returns the name with the given hash, if it's known.
+    pub fn lookup(&self, hash: u32) -> Option<&str> {
+        self.names.get(&hash).map(|name| &**name)
+    }
+
+    /// Returns a [`Display`] wrapper showing the name of the hash, or the hash itself if the
+    /// name isn't known.
+    pub fn display(&self, hash: u32) -> HashDisplay<'_> {
+        HashDisplay(hash, Some(self))
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Iterates over all known hashes and their names, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.names.iter().map(|(&hash, name)| (hash, &**name))
+    }
+}
+
+impl<'a> Extend<&'a str> for HashDictionary {
+    fn extend<T: IntoIterator<Item = &'a str>>(&mut self, iter: T) {
+        for name in iter {
+            self.insert(name);
+        }
+    }
+}
+
+/// Wrapper type for displaying [`fnv1a_hash`]ed names. If the dictionary knows the name, it's
+/// displayed as is, otherwise the hash is displayed as `0xNNNNNNNN`. Its behavior is implemented
+/// through the [`Display`] trait.
+///
+/// ## Example
+/// ```
+/// # use zenit_utils::{HashDictionary, HashDisplay};
+/// let dictionary = HashDictionary::with_builtin_names();
+///
+/// let a = HashDisplay(0x266561d8, None);
+/// assert_eq!(a.to_string(), "0x266561d8");
+///
+/// let b = HashDisplay(zenit_utils::fnv1a_hash(b"SkyInfo"), Some(&dictionary));
+/// assert_eq!(b.to_string(), "SkyInfo");
+/// ```
+pub struct HashDisplay<'a>(pub u32, pub Option<&'a HashDictionary>);
+
+impl<'a> Display for HashDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1.and_then(|dictionary| dictionary.lookup(self.0)) {
+            Some(name) => write!(f, "{name}"),
+            None => write!(f, "0x{:08x}", self.0),
+        }
+    }
+}
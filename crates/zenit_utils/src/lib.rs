@@ -10,6 +10,7 @@ pub mod color;
 pub mod counter;
 pub mod math;
 pub mod packed;
+pub mod wav;
 
 pub mod fnv1a;
 pub use fnv1a::fnv1a_hash;
@@ -24,6 +25,9 @@ pub use arc_pool::*;
 mod ascii_display;
 pub use ascii_display::*;
 
+mod hash_dictionary;
+pub use hash_dictionary::*;
+
 mod cell_ext;
 pub use cell_ext::RefCellExt;
 
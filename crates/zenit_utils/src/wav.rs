@@ -0,0 +1,96 @@
+//! Minimal WAV file writer
+//!
+//! Only supports 16-bit PCM, which is all Zenit needs for debug output and exports.
+
+use byteorder::{WriteBytesExt, LE};
+use std::io::{self, Write};
+
+/// Size of everything in the file before the samples, excluding the RIFF chunk header
+const HEADER_SIZE: u32 = 36;
+
+/// Maximum amount of samples (across all channels) a single WAV file can hold, as sizes in its
+/// header are 32-bit.
+pub const MAX_WAV_SAMPLES: u32 = (u32::MAX - HEADER_SIZE) / 2;
+
+/// Writes a complete 16-bit PCM WAV file. Samples of multiple channels must be interleaved.
+pub fn write_wav(
+    w: &mut impl Write,
+    sample_rate: u32,
+    channels: u16,
+    samples: &[i16],
+) -> io::Result<()> {
+    let sample_count = u32::try_from(samples.len()).unwrap_or(u32::MAX);
+    write_wav_header(w, sample_rate, channels, sample_count)?;
+    for &sample in samples {
+        w.write_i16::<LE>(sample)?;
+    }
+    Ok(())
+}
+
+/// Writes the header of a 16-bit PCM WAV file, with `sample_count` total samples (across all
+/// channels) following it. Fails if there are more than [`MAX_WAV_SAMPLES`] samples.
+pub fn write_wav_header(
+    w: &mut impl Write,
+    sample_rate: u32,
+    channels: u16,
+    sample_count: u32,
+) -> io::Result<()> {
+    const BYTES_PER_SAMPLE: u16 = 2;
+    if sample_count > MAX_WAV_SAMPLES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many samples for a WAV file",
+        ));
+    }
+    let data_size = sample_count * BYTES_PER_SAMPLE as u32;
+
+    w.write_all(b"RIFF")?;
+    w.write_u32::<LE>(HEADER_SIZE + data_size)?;
+    w.write_all(b"WAVE")?;
+
+    w.write_all(b"fmt ")?;
+    w.write_u32::<LE>(16)?;
+    w.write_u16::<LE>(1)?; // PCM
+    w.write_u16::<LE>(channels)?;
+    w.write_u32::<LE>(sample_rate)?;
+    w.write_u32::<LE>(sample_rate * (channels * BYTES_PER_SAMPLE) as u32)?;
+    w.write_u16::<LE>(channels * BYTES_PER_SAMPLE)?;
+    w.write_u16::<LE>(BYTES_PER_SAMPLE * 8)?;
+
+    w.write_all(b"data")?;
+    w.write_u32::<LE>(data_size)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wav_header_bytes() {
+        let mut bytes = Vec::new();
+        write_wav(&mut bytes, 22050, 2, &[1, -2]).unwrap();
+
+        #[rustfmt::skip]
+        let expected: &[u8] = &[
+            b'R', b'I', b'F', b'F', 40, 0, 0, 0, b'W', b'A', b'V', b'E',
+            b'f', b'm', b't', b' ', 16, 0, 0, 0,
+            1, 0, // PCM
+            2, 0, // channels
+            0x22, 0x56, 0, 0, // sample rate
+            0x88, 0x58, 0x01, 0, // byte rate
+            4, 0, // block align
+            16, 0, // bits per sample
+            b'd', b'a', b't', b'a', 4, 0, 0, 0,
+            1, 0, 0xfe, 0xff,
+        ];
+        assert_eq!(bytes, expected);
+
+        // Sizes in the header can't overflow
+        let mut header = Vec::new();
+        write_wav_header(&mut header, 44100, 2, MAX_WAV_SAMPLES).unwrap();
+        assert_eq!(&header[4..8], &(u32::MAX - 1).to_le_bytes());
+        assert!(write_wav_header(&mut Vec::new(), 44100, 2, MAX_WAV_SAMPLES + 1).is_err());
+    }
+}